@@ -0,0 +1,38 @@
+# git-clean's widget for bash: `eval "$(git-clean init bash)"` in ~/.bashrc.
+#
+# Ctrl-X Ctrl-G plans a cleanup of the current repository, offline, and lists
+# the branches it would delete in fzf, all selected, with each one's log
+# alongside. The command to delete those still selected is then left on the
+# command line, to run with Enter or edit first.
+
+__git_clean_widget() {
+  local branches chosen branch command
+  if ! branches=$(git-clean --dry-run --local-only --format names 2>/dev/null); then
+    echo "git-clean: planning failed; is this a git repository?" >&2
+    return 1
+  fi
+  if [[ -z $branches ]]; then
+    echo "git-clean: nothing to clean" >&2
+    return 0
+  fi
+  if ! command -v fzf >/dev/null; then
+    READLINE_LINE="git-clean --local-only --interactive"
+    READLINE_POINT=${#READLINE_LINE}
+    return 0
+  fi
+  chosen=$(printf '%s\n' "$branches" | fzf --multi --bind start:select-all \
+    --prompt 'delete> ' --header 'Tab to keep a branch, Enter to accept' \
+    --preview 'git log --oneline --decorate --color=always -n 20 {}')
+  [[ -z $chosen ]] && return 0
+  # --include takes globs, so match each name's own special characters
+  # literally, as a class of one
+  chosen=$(printf '%s\n' "$chosen" | sed 's/[][*?{}\\]/[&]/g')
+  command="git-clean --local-only"
+  while IFS= read -r branch; do
+    command+=" --include $(printf '%q' "$branch")"
+  done <<< "$chosen"
+  READLINE_LINE=$command
+  READLINE_POINT=${#READLINE_LINE}
+}
+
+bind -x '"\C-x\C-g": __git_clean_widget'
@@ -0,0 +1,125 @@
+//! Parsers for option values which people write by hand.
+
+use std::time::Duration;
+
+/// Units a duration may be given in, by every name they are accepted under.
+const UNITS: &[(&[&str], u64)] = &[
+    (&["s", "sec", "secs", "second", "seconds"], 1),
+    (&["m", "min", "mins", "minute", "minutes"], 60),
+    (&["h", "hour", "hours"], 60 * 60),
+    (&["d", "day", "days"], 24 * 60 * 60),
+    (&["w", "week", "weeks"], 7 * 24 * 60 * 60),
+    // `m` is taken by minutes
+    (&["mo", "month", "months"], 30 * 24 * 60 * 60),
+    (&["y", "year", "years"], 365 * 24 * 60 * 60),
+];
+
+const EXAMPLES: &str = "e.g. `90d`, `6h30m` or `2weeks`";
+
+/// Parse a duration such as `90d` or `6h30m`.
+///
+/// Each number must have a unit, and the whole must be positive: a bare `90`
+/// could mean anything, and a zero age or grace period is never intended.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return Err(format!("expected a duration, {EXAMPLES}"));
+    }
+
+    let mut seconds = 0_u64;
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return Err(format!("expected a number at `{rest}`, {EXAMPLES}"));
+        }
+        let (number, after) = rest.split_at(digits);
+        let after = after.trim_start();
+        let unit_len = after.len()
+            - after
+                .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+                .len();
+        let (unit, after) = after.split_at(unit_len);
+        if unit.is_empty() {
+            return Err(format!("`{number}` needs a unit, e.g. `{number}d`"));
+        }
+        let Some((_, scale)) = UNITS.iter().find(|(names, _)| names.contains(&unit)) else {
+            return Err(format!(
+                "unknown unit `{unit}`; use s, m, h, d, w, mo or y, {EXAMPLES}"
+            ));
+        };
+        seconds = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(*scale))
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(|| format!("`{input}` is too long a duration"))?;
+        rest = after.trim_start();
+    }
+
+    if seconds == 0 {
+        return Err(format!("`{input}` is no time at all"));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a size, a number of lines say, such as `500`, `16k` or `2M`.
+///
+/// Suffixes are decimal, as a count of things is, and the whole must be
+/// positive: no buffer or limit of nothing is ever intended.
+pub fn parse_size(input: &str) -> Result<usize, String> {
+    let input = input.trim();
+    let digits = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return Err("expected a size, e.g. `500`, `16k` or `2M`".to_owned());
+    }
+    let (number, suffix) = input.split_at(digits);
+    let scale = match suffix.trim_start() {
+        "" => 1,
+        "k" | "K" => 1_000,
+        "M" => 1_000_000,
+        suffix => return Err(format!("unknown suffix `{suffix}`; use k or M")),
+    };
+    let size = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(scale))
+        .ok_or_else(|| format!("`{input}` is too large"))?;
+    if size == 0 {
+        return Err(format!("`{input}` is nothing at all"));
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        let hours = |hours: u64| Duration::from_secs(hours * 60 * 60);
+        assert_eq!(parse_duration("90d"), Ok(hours(90 * 24)));
+        assert_eq!(
+            parse_duration("6h30m"),
+            Ok(hours(6) + Duration::from_secs(30 * 60))
+        );
+        assert_eq!(parse_duration("30days"), Ok(hours(30 * 24)));
+        assert_eq!(parse_duration("1w 2d"), Ok(hours(9 * 24)));
+        assert_eq!(parse_duration("18mo"), Ok(hours(18 * 30 * 24)));
+
+        assert!(parse_duration("90").unwrap_err().contains("needs a unit"));
+        assert!(parse_duration("6x").unwrap_err().contains("unknown unit"));
+        assert!(parse_duration("0h").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("16k"), Ok(16_000));
+        assert_eq!(parse_size("2 M"), Ok(2_000_000));
+
+        assert!(parse_size("4x").unwrap_err().contains("unknown suffix"));
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("0").is_err());
+    }
+}
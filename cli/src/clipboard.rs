@@ -0,0 +1,56 @@
+//! Putting text on the system clipboard.
+//!
+//! This goes through the platform's clipboard tool rather than talking to the
+//! clipboard directly: on X11, a selection owned by a process vanishes when it
+//! exits, which we are about to do.
+
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Clipboard tools to try, in order, with their arguments.
+#[cfg(target_os = "macos")]
+const TOOLS: &[(&str, &[&str])] = &[("pbcopy", &[])];
+#[cfg(windows)]
+const TOOLS: &[(&str, &[&str])] = &[("clip", &[])];
+#[cfg(not(any(target_os = "macos", windows)))]
+const TOOLS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Copy `text` to the clipboard with the first clipboard tool available.
+pub fn copy(text: &str) -> io::Result<()> {
+    for (tool, args) in TOOLS {
+        let mut child = match Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("{tool} failed ({status})")));
+        }
+        return Ok(());
+    }
+
+    let names = TOOLS.iter().map(|(tool, _)| *tool).collect::<Vec<_>>();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "no clipboard tool found; install one of: {}",
+            names.join(", ")
+        ),
+    ))
+}
@@ -0,0 +1,97 @@
+//! Finding every repository under a directory, for `--recursive`.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Every git repository under `top`, `top` itself included, sorted.
+///
+/// A directory with a `.git` in it, directory or file, is a repository, and
+/// isn't looked into any further: its submodules and nested clones belong
+/// to it. Hidden directories and symlinks aren't followed. A clone and its
+/// linked worktrees share their branches, so count once, as the clone if it
+/// was found too.
+pub fn repositories(top: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![top.to_owned()];
+    while let Some(dir) = pending.pop() {
+        if dir.join(".git").exists() {
+            found.push(dir);
+            continue;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if dir == top => return Err(err),
+            // anything below may be unreadable, such as another user's
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    // clones, whose `.git` is a directory, before worktrees
+    found.sort_unstable_by_key(|dir| (dir.join(".git").is_file(), dir.clone()));
+    let mut common_dirs = HashSet::new();
+    found.retain(|dir| common_dirs.insert(common_dir(dir)));
+    found.sort_unstable();
+    Ok(found)
+}
+
+/// The git directory shared by the repository at `dir` and all of its
+/// worktrees, or `dir` itself if it can't be opened.
+fn common_dir(dir: &Path) -> PathBuf {
+    let Ok(repo) = git_clean_core::open_repository(dir) else {
+        return dir.to_owned();
+    };
+    let git_dir = repo.path();
+    let common = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir.to_owned(),
+    };
+    common.canonicalize().unwrap_or(common)
+}
+
+#[cfg(test)]
+mod tests {
+    use git_clean_core::git2::{self, Repository};
+
+    use super::*;
+
+    #[test]
+    fn stops_at_repositories_and_skips_hidden_directories() {
+        let top = std::env::temp_dir().join(format!("git-clean-discover-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&top);
+        for dir in [
+            "a/.git",
+            "a/nested/.git",
+            "team/b/.git",
+            ".hidden/c/.git",
+            "empty",
+        ] {
+            fs::create_dir_all(top.join(dir)).unwrap();
+        }
+        // a linked worktree's .git is a file, and it counts as its clone
+        let clone = Repository::init(top.join("team/clone")).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree = clone
+            .find_tree(clone.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        clone
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        clone
+            .worktree("worktree", &top.join("team/worktree"), None)
+            .unwrap();
+
+        assert_eq!(
+            repositories(&top).unwrap(),
+            [top.join("a"), top.join("team/b"), top.join("team/clone")]
+        );
+        fs::remove_dir_all(&top).unwrap();
+    }
+}
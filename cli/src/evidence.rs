@@ -0,0 +1,70 @@
+//! What justifies each planned deletion, printed after a dry run.
+
+use std::time::SystemTime;
+
+use git_clean_core::{CleanReport, Outcome, PrSummary};
+
+/// Each branch which would be deleted, by name, with its pull requests'
+/// numbers, states, titles and urls; empty if there is none.
+pub fn render(report: &CleanReport) -> String {
+    let mut branches = report
+        .branches
+        .iter()
+        .filter_map(|branch| match &branch.decision.outcome {
+            Outcome::Delete { bot, confidence } => Some((branch, bot, confidence)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if branches.is_empty() {
+        return String::new();
+    }
+    branches.sort_unstable_by(|a, b| a.0.decision.branch_name.cmp(&b.0.decision.branch_name));
+
+    let mut out = "Would delete:\n".to_owned();
+    for (branch, bot, confidence) in branches {
+        out.push_str(&format!(
+            "  {} ({confidence} confidence{})\n",
+            branch.decision.branch_name,
+            if *bot { ", bot branch" } else { "" },
+        ));
+        for pr in &branch.pull_requests {
+            out.push_str(&format!("    {}\n", pull_request(pr)));
+            if let Some(url) = &pr.url {
+                out.push_str(&format!("      {url}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// A pull request's number, state and title on one line.
+pub fn pull_request(pr: &PrSummary) -> String {
+    let state = match (&pr.merged_by, pr.merged_at, pr.open) {
+        (_, _, true) => "open".to_owned(),
+        (Some(merged_by), merged_at, _) => {
+            format!("merged by {merged_by}{}", when(merged_at))
+        }
+        (None, Some(merged_at), _) => format!("merged{}", when(Some(merged_at))),
+        (None, None, false) => format!("closed{}", when(pr.closed_at)),
+    };
+    let title = pr.title.as_deref().unwrap_or_default();
+    format!("#{} {state}: {title}", pr.number)
+}
+
+fn when(time: Option<SystemTime>) -> String {
+    time.map(|time| format!(" {}", ago(time)))
+        .unwrap_or_default()
+}
+
+/// How long ago `time` was, roughly.
+pub fn ago(time: SystemTime) -> String {
+    let days = SystemTime::now()
+        .duration_since(time)
+        .map(|elapsed| elapsed.as_secs() / (24 * 60 * 60))
+        .unwrap_or_default();
+    match days {
+        0 => "today".to_owned(),
+        1 => "yesterday".to_owned(),
+        days => format!("{days} days ago"),
+    }
+}
@@ -0,0 +1,154 @@
+//! Explaining a run which deleted nothing, instead of ending silently.
+
+use git_clean_core::{Decision, ErrorKind, Outcome, Reason};
+
+/// Why a branch was kept, and what might be done about it.
+fn why(outcome: &Outcome) -> (&'static str, Option<&'static str>) {
+    match outcome {
+        Outcome::Delete { .. } => ("were deletable", None),
+        Outcome::Failed(issue) => match issue.kind() {
+            ErrorKind::Auth => (
+                "could not be looked up for lack of authorization",
+                Some("authenticate with `-T` on GitHub, or set `GITLAB_TOKEN` or `BITBUCKET_TOKEN`"),
+            ),
+            ErrorKind::RateLimited => (
+                "hit the rate limit",
+                Some("authenticate with `-T`, or add tokens with `--additional-token`, then retry just those branches with `--retry-file`"),
+            ),
+            ErrorKind::Network => (
+                "had network errors",
+                Some("retry just those branches with `--retry-file`"),
+            ),
+            ErrorKind::GitState | ErrorKind::Policy => (
+                "could not be evaluated",
+                Some("run with `--copy-summary` or `--dump-snapshot` to see why"),
+            ),
+        },
+        Outcome::Retain(reason) => match reason {
+            Reason::DefaultBranch => ("are the default branch", None),
+            Reason::NoPullRequests => (
+                "had no pull requests, so may be unpushed or in progress",
+                Some("delete those you are done with yourself with `git branch -D`"),
+            ),
+            Reason::OpenPullRequest => ("had an open pull request", None),
+            Reason::NotVetted => (
+                "had closed pull requests which were not vetted",
+                Some("relax `--require-vetting` if unvetted pull requests are done with too"),
+            ),
+            Reason::ReviewRequested => ("are awaiting my review", None),
+            Reason::NotMerged => (
+                "were not merged into the integration branch",
+                Some("check that `--integration-branch` names the branch work is merged into"),
+            ),
+            Reason::ShallowClone => (
+                "could not be checked in a shallow clone",
+                Some("run `git fetch --unshallow` first"),
+            ),
+            Reason::IntegrationRewritten => (
+                "could not be checked since the integration branch was rewritten",
+                None,
+            ),
+            Reason::LowConfidence(_) => (
+                "fell short of `--min-confidence`",
+                Some("lower `--min-confidence` to delete them"),
+            ),
+            Reason::Referenced(_) => (
+                "are still referenced in issues or files",
+                Some("run without `--grep-references` to ignore mentions"),
+            ),
+            Reason::NoUpstream => ("had no upstream", None),
+            Reason::Declined => ("were skipped when asked", None),
+            Reason::CheckedOut(_) => (
+                "are checked out",
+                Some("switch to another branch, or remove the worktree, to delete them"),
+            ),
+            Reason::Unpushed(_) => (
+                "had commits which were never pushed",
+                Some("push them, or delete the branches yourself with `git branch -D`"),
+            ),
+            Reason::TipMoved => (
+                "had moved on from their closed pull requests",
+                Some("check them for new work, or pass `--no-verify-pr-heads` to delete them"),
+            ),
+            Reason::RecentCommit(_) => (
+                "were committed to too recently",
+                Some("shorten `--exclude-recent` to delete them"),
+            ),
+            Reason::ClosedUnmerged => (
+                "had pull requests closed without merging",
+                Some("run without `--require-merged` to delete those too"),
+            ),
+            Reason::UpstreamExists => (
+                "had an upstream which still exists",
+                Some("run `git fetch --prune` first, so that deleted upstreams are noticed"),
+            ),
+        },
+    }
+}
+
+/// A paragraph on why none of the `decisions` was to delete a branch, with
+/// suggestions, or `None` if some were.
+pub fn nothing_deleted(decisions: &[Decision], dry_run: bool) -> Option<String> {
+    if decisions.is_empty() || decisions.iter().any(Decision::is_delete) {
+        return None;
+    }
+
+    let mut tallies: Vec<(&str, Option<&str>, usize)> = Vec::new();
+    for decision in decisions {
+        let (what, hint) = why(&decision.outcome);
+        match tallies.iter_mut().find(|(known, _, _)| *known == what) {
+            Some((_, _, count)) => *count += 1,
+            None => tallies.push((what, hint, 1)),
+        }
+    }
+    // most common first; ties in a stable order
+    tallies.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+
+    let causes = tallies
+        .iter()
+        .map(|(what, _, count)| format!("{count} {what}"))
+        .collect::<Vec<_>>();
+    let mut paragraph = format!(
+        "Nothing {} deleted. Of {} branch{}, {}.",
+        if dry_run { "would be" } else { "was" },
+        decisions.len(),
+        if decisions.len() == 1 { "" } else { "es" },
+        causes.join("; ")
+    );
+    for (_, hint, _) in &tallies {
+        if let Some(hint) = hint {
+            paragraph.push(' ');
+            paragraph.push_str(&capitalize(hint));
+            paragraph.push('.');
+        }
+    }
+    Some(paragraph)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_the_most_common_causes_first() {
+        let decisions = [
+            Decision::retain("a".into(), Reason::OpenPullRequest),
+            Decision::retain("b".into(), Reason::NoPullRequests),
+            Decision::retain("c".into(), Reason::NoPullRequests),
+        ];
+        assert_eq!(
+            nothing_deleted(&decisions, false).unwrap(),
+            "Nothing was deleted. Of 3 branches, 2 had no pull requests, so may be unpushed \
+             or in progress; 1 had an open pull request. Delete those you are done with \
+             yourself with `git branch -D`."
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! `git-clean init`: a key binding for the shell, to clean the current
+//! repository with a preview.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+}
+
+/// The script to `eval` in `shell`'s startup file.
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => include_str!("../shell/git-clean.bash"),
+        Shell::Zsh => include_str!("../shell/git-clean.zsh"),
+    }
+}
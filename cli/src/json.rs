@@ -0,0 +1,73 @@
+//! The report of a run as JSON, for `--format json`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git_clean_core::{BranchReport, CleanReport, Outcome, PrSummary};
+use serde_json::{json, Value};
+
+/// Every branch, sorted by name, followed by counts of each outcome.
+pub fn render(report: &CleanReport) -> Value {
+    let mut branches = report.branches.iter().collect::<Vec<_>>();
+    branches.sort_unstable_by(|a, b| a.decision.branch_name.cmp(&b.decision.branch_name));
+
+    let count = |matches: fn(&Outcome) -> bool| {
+        branches
+            .iter()
+            .filter(|branch| matches(&branch.decision.outcome))
+            .count()
+    };
+    let summary = json!({
+        "deleted": count(|outcome| matches!(outcome, Outcome::Delete { bot: false, .. })),
+        "deleted_bots": count(|outcome| matches!(outcome, Outcome::Delete { bot: true, .. })),
+        "retained": count(|outcome| matches!(outcome, Outcome::Retain(_))),
+        "failed": count(|outcome| matches!(outcome, Outcome::Failed(_))),
+    });
+    json!({
+        "dry_run": report.dry_run,
+        "branches": branches.into_iter().map(branch).collect::<Vec<_>>(),
+        "summary": summary,
+    })
+}
+
+fn branch(branch: &BranchReport) -> Value {
+    let mut value = match &branch.decision.outcome {
+        Outcome::Delete { bot, confidence } => json!({
+            "decision": "delete",
+            "confidence": confidence.to_string(),
+            "bot": bot,
+            "tip": branch.tip,
+        }),
+        Outcome::Retain(reason) => json!({
+            "decision": "retain",
+            "reason": reason.to_string(),
+        }),
+        Outcome::Failed(issue) => json!({
+            "decision": "failed",
+            "kind": issue.kind().to_string(),
+            "reason": issue.message(),
+        }),
+    };
+    value["branch"] = json!(branch.decision.branch_name.as_str());
+    value["owners"] = json!(branch.owners);
+    value["pull_requests"] = branch.pull_requests.iter().map(pull_request).collect();
+    value
+}
+
+fn pull_request(pr: &PrSummary) -> Value {
+    json!({
+        "number": pr.number,
+        "open": pr.open,
+        "url": pr.url,
+        "title": pr.title,
+        "author": pr.author,
+        "closed_at": pr.closed_at.and_then(unix_seconds),
+        "merged_at": pr.merged_at.and_then(unix_seconds),
+        "merged_by": pr.merged_by,
+    })
+}
+
+fn unix_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|since| since.as_secs())
+}
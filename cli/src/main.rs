@@ -0,0 +1,1262 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use git_clean_core::{
+    clean_branches, clean_branches_many, delete_branches, open_repository,
+    registry::{self, RegisteredRepository},
+    render_markdown, token, BranchReport, CleanReport, Confidence, ConfirmHook, ErrorKind,
+    FatalError, Options, Provider, Snapshot, Vetting, DEFAULT_LOG_SAMPLE,
+    DEFAULT_STALE_REF_NAMESPACES,
+};
+use miette::{Diagnostic, IntoDiagnostic, Result, WrapErr};
+use redacted::{RedactDrain, RedactedError};
+use slog::Logger;
+
+mod cli_types;
+mod clipboard;
+mod discover;
+mod evidence;
+mod explain;
+mod init;
+mod json;
+mod prompt;
+mod prs;
+mod redacted;
+mod sarif;
+mod summary;
+mod tui;
+
+fn slog_init(buffer: usize) -> Logger {
+    use slog::o;
+    use slog::Drain;
+
+    let decorator = slog_term::TermDecorator::new().build();
+    let drain = slog_term::FullFormat::new(decorator).build().fuse();
+    let drain = slog_async::Async::new(RedactDrain(drain).fuse())
+        .chan_size(buffer)
+        .build()
+        .fuse();
+
+    slog::Logger::root(drain, o!())
+}
+
+/// A library error, dressed up with a code and a hint for the terminal, and
+/// with any tokens masked.
+#[derive(Debug)]
+struct CliError {
+    kind: ErrorKind,
+    help: &'static str,
+    error: RedactedError,
+}
+
+impl From<FatalError> for CliError {
+    fn from(err: FatalError) -> Self {
+        let help = match &err {
+            FatalError::WrongRemoteCount(_) => {
+                "name the remote to use with `--remote`, or remove the others with `git remote remove`"
+            }
+            FatalError::NoSuchRemote(_) => "list the remotes with `git remote -v`",
+            FatalError::RemoteUrlNotProvider(_) => {
+                "check the remote's url with `git remote -v`, or drop `--provider`"
+            }
+            FatalError::RemoteUrlNotGithub => {
+                "the remote must be a github.com, GitLab or bitbucket.org url; check it with `git remote -v`, name a self-hosted GitLab with `--gitlab-host`, or a GitHub Enterprise Server with `--api-base-url`"
+            }
+            FatalError::NoIntegrationBranch | FatalError::IntegrationBranchNotFound(_) => {
+                "name an existing branch with `--integration-branch`"
+            }
+            FatalError::PlanNotApproved { .. } => {
+                "run again with `--dry-run` to see the current plan and its hash"
+            }
+            FatalError::GitLab {
+                status: Some(401 | 403),
+                ..
+            } => "set `GITLAB_TOKEN` to a token with the `read_api` scope",
+            FatalError::Bitbucket {
+                status: Some(401 | 403),
+                ..
+            } => "set `BITBUCKET_TOKEN` to an access token with the `pullrequest` scope",
+            FatalError::InvalidGitConfig { .. } => {
+                "see where it is set with `git-clean config show --origin`"
+            }
+            FatalError::WouldDeleteAll(_) => {
+                "this usually means the default branch or the policy is wrong; review with `--dry-run`, then pass `--allow-delete-all` or confirm each with `--interactive`"
+            }
+            FatalError::TooManyDeletions { .. } => {
+                "review the plan with `--dry-run`, then raise `--max-delete` or confirm each with `--interactive`"
+            }
+            FatalError::NoSuchRun { .. } => {
+                "pass one of the journaled runs' ids, as logged by the run which deleted the branches"
+            }
+            FatalError::NotArchived { .. } => {
+                "only branches deleted with `--archive` can be restored; others may be recovered with `git-clean recover`"
+            }
+            FatalError::CommitsPruned(_) => {
+                "`git fsck --lost-found` may still find them; if not, they are gone"
+            }
+            FatalError::BranchesProtected(_) => {
+                "leave them out; `git-clean config show --origin` shows what protects them"
+            }
+            FatalError::TokenRequired(_) => {
+                "provide one with `--personal-access-token`; it is cached for later runs"
+            }
+            FatalError::PreflightFailed(_) => {
+                "check out another branch, or wait for other git processes to finish"
+            }
+            err => match err.kind() {
+                ErrorKind::Auth => {
+                    "provide a classic token with `--personal-access-token`; it needs the `repo` and `read:org` permissions, and `--trace-http` shows the responses"
+                }
+                ErrorKind::RateLimited => {
+                    "authenticate with `--personal-access-token` for a higher rate limit, or add more tokens with `--additional-token`"
+                }
+                ErrorKind::Network => {
+                    "check your connection to GitHub; `--trace-http` logs every request"
+                }
+                ErrorKind::GitState => "check the repository's state with `git status`",
+                ErrorKind::Policy => {
+                "run with `--dry-run` to review what would be deleted, and `git-clean config show --origin` to see which settings decided it"
+            }
+            },
+        };
+        Self {
+            kind: err.kind(),
+            help,
+            error: RedactedError::new(&err),
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl Diagnostic for CliError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(format!("git_clean::{}", self.kind)))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.help))
+    }
+}
+
+/// Clean outdated local git branches.
+///
+/// Removes local branches which have been pushed to the remote, and at least 1
+/// PR has been created for them, and all such PRs are now closed.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Use and cache a GitHub Personal Access Token
+    ///
+    /// This must be a "classic" token and it must have at least
+    /// `repo` and `read:org` permissions assigned.
+    ///
+    /// To create a token, visit
+    /// <https://github.com/settings/tokens>.
+    #[arg(long, short = 'T')]
+    personal_access_token: Option<String>,
+
+    /// Cache an additional GitHub token to spread the rate limit across
+    ///
+    /// PR lookups rotate between all cached tokens, failing over when one
+    /// hits its rate limit. May be repeated.
+    #[arg(long, value_name = "TOKEN")]
+    additional_token: Vec<String>,
+
+    /// Cache a shell command which prints the token, instead of the token
+    ///
+    /// The command runs at every startup, e.g. `op read op://dev/github/token`
+    /// for 1Password or `pass show github/token`. It takes precedence over a
+    /// cached `--personal-access-token`.
+    #[arg(long, value_name = "COMMAND")]
+    token_command: Option<String>,
+
+    /// Store cached tokens encrypted from now on
+    ///
+    /// The key is kept in a file of its own, by default in the local data
+    /// directory; set `GIT_CLEAN_KEY_FILE` to keep it somewhere else. Keep it
+    /// out of any backups which include the config.
+    #[arg(long)]
+    encrypt_config: bool,
+
+    /// Do not actually edit the repository.
+    #[arg(short, long)]
+    dry_run: bool,
+
+    /// Ask before deleting each branch
+    ///
+    /// Each branch is shown with its pull requests and when it was last
+    /// committed to. Answer `y` to delete it, `n` to keep it, or `a` to
+    /// abort, deleting nothing at all.
+    #[arg(short, long, conflicts_with = "dry_run")]
+    interactive: bool,
+
+    /// Delete even if that would leave only the default branch
+    ///
+    /// A plan deleting every branch but one, and at least three, usually
+    /// means that the default branch was mis-detected or the policy is
+    /// wrong, so is refused unless each deletion is confirmed with
+    /// `--interactive`, or this is passed.
+    #[arg(long)]
+    allow_delete_all: bool,
+
+    /// Refuse to delete more than N branches in one run
+    ///
+    /// So that a misparsed remote or a misbehaving API can't delete dozens
+    /// of branches unnoticed. Stale refs count too. With `--interactive`,
+    /// each deletion is confirmed instead.
+    #[arg(long, value_name = "N")]
+    max_delete: Option<usize>,
+
+    /// Choose which branches to delete in a full-screen list
+    ///
+    /// Every branch is listed with what a dry run decided about it, those it
+    /// would delete already selected. Select others with space, then press
+    /// enter to delete the selection, or `q` to quit without deleting.
+    #[arg(long, conflicts_with_all = ["dry_run", "interactive", "sample", "replay"])]
+    tui: bool,
+
+    /// Deactivate GitHub deployments of deleted branches.
+    #[arg(long)]
+    clean_deployments: bool,
+
+    /// Also delete deployment environments named after deleted branches
+    ///
+    /// As created by common PR-preview setups. Environments which are
+    /// protected, or which other refs were deployed to, such as `staging` or
+    /// `production`, are kept with a warning.
+    #[arg(long, requires = "clean_deployments")]
+    delete_environments: bool,
+
+    /// Require closed pull requests to have been vetted before deleting
+    ///
+    /// Guards against deleting branches whose PRs were closed by a stale-bot.
+    /// One of: `approval` (at least one approving review), `checks` (all
+    /// status checks on the PR head passed), `approval-or-checks`.
+    #[arg(long, value_name = "VETTING")]
+    require_vetting: Option<Vetting>,
+
+    /// Only delete branches one of whose pull requests was merged
+    ///
+    /// By default, a branch whose pull requests were all closed is deleted
+    /// whether or not they were merged. Squash-merged branches count as
+    /// merged.
+    #[arg(long)]
+    require_merged: bool,
+
+    /// Only consider branches matching this glob, e.g. `feature/*`
+    ///
+    /// May be given more than once. Other branches are neither looked up nor
+    /// deleted.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Never consider branches matching this glob, e.g. `release/*`
+    ///
+    /// May be given more than once, and takes precedence over `--include`.
+    /// The config file's `protected_branches` are always excluded too.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only consider branches this owner owns, e.g. `@payments-team`
+    ///
+    /// Owners are read from a `.git-clean-owners` file at the top of the
+    /// working tree, where each line is a branch prefix ending in `/`, or a
+    /// glob, followed by its owners, as in `CODEOWNERS`.
+    #[arg(long, value_name = "OWNER")]
+    owner: Option<String>,
+
+    /// Treat bot branches like any other branch
+    ///
+    /// By default, branches created by dependency bots (`dependabot/*`,
+    /// `renovate/*`) whose PRs are all closed skip vetting and plan approval,
+    /// and are counted separately.
+    #[arg(long)]
+    no_bot_fast_path: bool,
+
+    /// Delete branches whose tip differs from their closed PRs' heads
+    ///
+    /// By default, a branch is only deleted for its closed PRs if its tip is
+    /// the head of one of them, or behind one, so that a branch reused since
+    /// is kept. Only GitHub says what a PR's head was.
+    #[arg(long)]
+    no_verify_pr_heads: bool,
+
+    /// Keep deleted branches under `refs/archive/`, to bring back with
+    /// `git-clean restore <branch>`
+    ///
+    /// Archived refs keep their commits from `git gc`, unlike the journal
+    /// `git-clean recover` reads, but aren't listed as branches.
+    #[arg(long)]
+    archive: bool,
+
+    /// Never delete branches committed to more recently than this, e.g. `48h`
+    ///
+    /// Whatever their PRs say: work closed and reopened in the last day or
+    /// two is likely still going on.
+    #[arg(long, value_name = "DURATION", value_parser = cli_types::parse_duration)]
+    exclude_recent: Option<Duration>,
+
+    /// Ignore PRs closed longer ago than this, e.g. `18mo` or `2y`
+    ///
+    /// For long-lived repositories where a branch name was reused: the
+    /// closed PRs of an old branch of the same name then don't doom a branch
+    /// recreated since. Only GitHub says when a PR was closed.
+    #[arg(long, value_name = "DURATION", value_parser = cli_types::parse_duration)]
+    recent_prs_only: Option<Duration>,
+
+    /// Retain branches of open PRs on which you are a requested reviewer
+    ///
+    /// Such branches are always listed; with this flag they are also kept
+    /// until the review request is resolved. Requires a personal access token.
+    #[arg(long)]
+    keep_review_requests: bool,
+
+    /// Update the remote url if the repository was renamed or transferred
+    #[arg(long)]
+    fix_remote: bool,
+
+    /// Also delete stale refs left behind by tools
+    ///
+    /// By default this cleans `refs/original/**` (from `git filter-branch`)
+    /// and `refs/backup/**`; use `--stale-ref-namespace` to choose others.
+    /// They are part of the plan, journaled and approved with the branches,
+    /// and left alone with `--interactive`.
+    #[arg(long)]
+    clean_stale_refs: bool,
+
+    /// Ref namespace to clean with `--clean-stale-refs`, as a glob
+    ///
+    /// May be repeated. Replaces the default namespaces.
+    #[arg(long, value_name = "GLOB")]
+    stale_ref_namespace: Vec<String>,
+
+    /// How old a ref's commit must be for `--clean-stale-refs` to delete it
+    #[arg(long, default_value = "30d", value_parser = cli_types::parse_duration)]
+    stale_ref_age: Duration,
+
+    /// Catch up with a changed remote default branch
+    ///
+    /// When the remote default has changed (e.g. `master` to `main`), renames
+    /// the local default branch, tracks the new remote branch, updates the
+    /// remote HEAD, and sets `init.defaultBranch` for this repository.
+    #[arg(long)]
+    retarget_default: bool,
+
+    /// Log method, path, status and remaining rate limit of every GitHub request
+    #[arg(long)]
+    trace_http: bool,
+
+    /// Upload a report of the run to a secret gist and print its URL
+    ///
+    /// Combine with `--dry-run` to share what would be deleted with a reviewer
+    /// before deleting anything. Requires a personal access token with the
+    /// `gist` permission.
+    #[arg(long)]
+    gist: bool,
+
+    /// Only delete branches if the plan matches this hash
+    ///
+    /// Every dry run prints the hash of its plan, signed with this machine's
+    /// secrets key. Passing that hash here guarantees that exactly the
+    /// reviewed plan is applied: if any branch would be added or dropped, or
+    /// any doomed branch has moved, nothing is deleted.
+    #[arg(long, value_name = "PLAN_HASH")]
+    approve: Option<String>,
+
+    /// Branch to compare against when the repository has no remotes
+    ///
+    /// Without a remote there are no pull requests to consult, so branches are
+    /// deleted once they are fully merged into this branch. Defaults to the
+    /// branch named like the remote's default branch, then
+    /// `init.defaultBranch`, then `main`, then `master`.
+    #[arg(long)]
+    integration_branch: Option<String>,
+
+    /// Never ask a forge: only delete branches merged into the integration
+    /// branch
+    ///
+    /// As for a repository without remotes, so this works offline and with
+    /// remotes git-clean doesn't recognize. Squash- and rebase-merged
+    /// branches are recognized by their changes.
+    #[arg(long)]
+    local_only: bool,
+
+    /// Never ask a forge: only delete branches whose upstream is gone
+    ///
+    /// That is, whose upstream was deleted from the remote, as `git branch
+    /// -vv` shows with `[gone]`. Run `git fetch --prune` first, so that the
+    /// deletions are seen. Those with commits on no remote-tracking branch
+    /// are kept; the rest not merged into the integration branch are deleted
+    /// with low confidence.
+    #[arg(long, conflicts_with = "local_only")]
+    gone: bool,
+
+    /// Write what was learned about each branch to this file
+    ///
+    /// Replay it with `git-clean simulate --snapshot FILE` to see the same
+    /// decisions again without git or the network.
+    #[arg(long, value_name = "FILE")]
+    dump_snapshot: Option<PathBuf>,
+
+    /// Record this run's inputs into a directory
+    ///
+    /// Keeps the local branches and every GitHub response, along with a
+    /// snapshot of the decisions, so that `--replay` can reproduce them later.
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Reproduce a run recorded with `--record`, without asking GitHub
+    ///
+    /// Implies `--dry-run`.
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+
+    /// Remember branches which could not be evaluated, and retry only those
+    ///
+    /// If the file exists, only the branches it lists are evaluated. Either
+    /// way, afterwards it lists the branches which failed, e.g. because of
+    /// network errors, or is removed if none did.
+    #[arg(long, value_name = "PATH")]
+    retry_file: Option<PathBuf>,
+
+    /// Retain branches still mentioned in open issues or tracked files
+    ///
+    /// Mentions are searched for as whole words in the titles and bodies of
+    /// open issues, and in every tracked text file, so branches named in docs,
+    /// runbooks or TODO comments are kept. Where they were found is given as
+    /// the reason.
+    #[arg(long)]
+    grep_references: bool,
+
+    /// Match pull requests to branches whose name differs only in case
+    ///
+    /// For case-insensitive filesystems, where a branch may be checked out
+    /// as `Fix/Login` for a pull request from `fix/login`. Branches which
+    /// then share pull requests are warned about.
+    #[arg(long)]
+    case_insensitive_heads: bool,
+
+    /// Only delete branches we are at least this sure are done with
+    ///
+    /// One of: `high` (merged into the default branch, or a bot's),
+    /// `medium` (a closed pull request was approved or checked), `low`.
+    /// Less certain deletions are retained, for a supervised run to review.
+    #[arg(long, value_name = "CONFIDENCE")]
+    min_confidence: Option<Confidence>,
+
+    /// Evaluate only this many branches, chosen at random
+    ///
+    /// Estimates from them how many of all the branches a full run would
+    /// delete, to judge whether it is worth its API calls. Nothing is
+    /// modified.
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Treat remotes on this host as a self-hosted GitLab
+    ///
+    /// Branches are then judged by their merge requests, read with the token
+    /// in `GITLAB_TOKEN`. gitlab.com is recognized without this.
+    #[arg(long = "gitlab-host", value_name = "HOST")]
+    gitlab_hosts: Vec<String>,
+
+    /// Look pull requests up by this remote
+    ///
+    /// Needed when a repository has more than one.
+    #[arg(long, value_name = "NAME")]
+    remote: Option<String>,
+
+    /// Take the remote to be on this forge: github, gitlab or bitbucket
+    ///
+    /// Only needed when its url doesn't say, such as a self-hosted GitLab on
+    /// a host not named `gitlab.*`.
+    #[arg(long)]
+    provider: Option<Provider>,
+
+    /// Copy a Markdown report of the run to the clipboard when done
+    ///
+    /// Uses `pbcopy` on macOS, `clip` on Windows, and `wl-copy`, `xclip` or
+    /// `xsel` elsewhere.
+    #[arg(long)]
+    copy_summary: bool,
+
+    /// How to report the decisions when done
+    ///
+    /// With `json`, every branch's decision, with why and its pull requests,
+    /// and counts of each outcome, are printed to stdout as one JSON object.
+    /// With `sarif`, the deletable branches are printed as SARIF findings,
+    /// for dashboards which ingest them from linters. Logs stay on stderr.
+    #[arg(long, value_enum, default_value_t = Format::Text, conflicts_with = "tui")]
+    format: Format,
+
+    /// Use the GitHub Enterprise Server API at this url, e.g.
+    /// `https://github.example.com/api/v3`
+    ///
+    /// Remotes on its host are then recognized. The url is cached, like
+    /// tokens, since they belong to that server.
+    #[arg(long, value_name = "URL")]
+    api_base_url: Option<String>,
+
+    /// Reject unknown keys in the config file instead of warning about them
+    #[arg(long)]
+    strict_config: bool,
+
+    /// Log every branch
+    ///
+    /// Otherwise, past the first hundred branches decided alike, such as
+    /// retained for unpushed commits, the rest are only counted, and how
+    /// many there were is logged once all are decided.
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Hold up to this many log lines waiting to be written, e.g. `4k`
+    ///
+    /// Lines beyond that are dropped, and how many is logged. Raise it if a
+    /// run over very many branches reports dropped lines.
+    #[arg(long, value_name = "LINES", default_value = "128", value_parser = cli_types::parse_size)]
+    log_buffer: usize,
+
+    /// Clean every git repository in this directory, however deep
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["path", "tui"])]
+    recursive: Option<PathBuf>,
+
+    /// Paths to the repositories to clean
+    ///
+    /// Given more than one, each is cleaned in turn, and a summary of them
+    /// all follows theirs.
+    #[arg(default_value = ".")]
+    path: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Logs, then a table of how many branches were deleted and retained,
+    /// and why.
+    Text,
+    /// One JSON object on stdout.
+    Json,
+    /// A SARIF log on stdout, with a finding for each deletable branch.
+    Sarif,
+    /// The names of the branches deleted, or which would be on a dry run,
+    /// one per line on stdout.
+    Names,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Decide about the branches in a snapshot, and print the report
+    ///
+    /// Nothing is read from git or GitHub, and nothing is deleted.
+    Simulate {
+        /// Snapshot written by `--dump-snapshot`
+        #[arg(long, value_name = "FILE")]
+        snapshot: PathBuf,
+    },
+    /// Restore the branches and refs deleted by an earlier run
+    ///
+    /// Every deletion is journaled first, under an id which is logged. Refs
+    /// which exist again are left alone. Deleted commits are only kept until
+    /// `git gc` prunes them, two weeks later by default.
+    Recover {
+        /// The id of the run, as logged when it deleted anything
+        #[arg(long = "from", value_name = "RUN_ID")]
+        run_id: String,
+        /// Only recover the branches, or refs, whose names match this glob
+        #[arg(long = "match", value_name = "GLOB")]
+        matching: Option<String>,
+        /// Only recover the branches which were the head of this pull request
+        #[arg(long, value_name = "NUMBER")]
+        pr: Option<u64>,
+        /// Only say which refs would be recovered
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+    /// Restore a branch deleted with `--archive`
+    ///
+    /// The branch is recreated from its latest archive, under
+    /// `refs/archive/<branch>/`, which is then removed. A branch of the same
+    /// name which exists again is left alone.
+    Restore {
+        /// The name of the archived branch
+        branch: String,
+        /// Only say whether it would be restored
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+    /// Inspect the settings in effect
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// List my open PRs, and where their branches are checked out
+    ///
+    /// Also lists the local branches which have no PR at all. Needs a
+    /// personal access token, to know who "my" is.
+    Prs,
+    /// Register repositories for `git-clean all`
+    Repos {
+        #[command(subcommand)]
+        command: ReposCommand,
+    },
+    /// Clean every registered repository
+    ///
+    /// Takes the same flags as cleaning a single repository, such as
+    /// `git-clean all --dry-run`, but no paths. Each repository's registered
+    /// settings apply on top of them.
+    All {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+        flags: Vec<String>,
+    },
+    /// Print a shell key binding which cleans the current repository
+    ///
+    /// Ctrl-X Ctrl-G then plans a cleanup, offline, and lists the branches
+    /// it would delete in fzf to choose from. The command to delete those
+    /// chosen is left on the command line. Add `eval "$(git-clean init zsh)"`
+    /// to `~/.zshrc`, or the same for bash to `~/.bashrc`.
+    Init { shell: init::Shell },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print every setting in effect for the repository
+    ///
+    /// From the config file and git's config, with the defaults of those not
+    /// set. Flags given to a run override them all.
+    Show {
+        /// Also print where each came from, and the values overridden
+        #[arg(long)]
+        origin: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ReposCommand {
+    /// Register the repository at a path, or replace its registration
+    Add {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Look pull requests up by this remote, if it has more than one
+        #[arg(long, value_name = "NAME")]
+        remote: Option<String>,
+        /// The forge its remote is on, if its url doesn't say
+        #[arg(long)]
+        provider: Option<Provider>,
+        /// Never delete branches matching this glob here; may be repeated
+        #[arg(long = "protect", value_name = "GLOB")]
+        protected_branches: Vec<String>,
+    },
+    /// List the registered repositories and their settings
+    List,
+    /// Unregister the repository at a path
+    Remove {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
+
+fn manage_repos(command: &ReposCommand) -> Result<()> {
+    match command {
+        ReposCommand::Add {
+            path,
+            remote,
+            provider,
+            protected_branches,
+        } => {
+            // register the top of the repository, however deep in it `path` is
+            let repo = open_repository(path)
+                .map_err(|err| RedactedError::new(&err))
+                .into_diagnostic()
+                .wrap_err_with(|| format!("{} is not in a git repository", path.display()))?;
+            let path = repo.workdir().unwrap_or_else(|| repo.path());
+            let path = std::fs::canonicalize(path)
+                .into_diagnostic()
+                .wrap_err("failed to resolve repository path")?;
+            let replaced = registry::add(RegisteredRepository {
+                path: path.clone(),
+                remote: remote.clone(),
+                provider: *provider,
+                protected_branches: protected_branches.clone(),
+            })
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to register repository")?;
+            let verb = if replaced { "Updated" } else { "Registered" };
+            eprintln!("{verb} {}.", path.display());
+        }
+        ReposCommand::List => {
+            let registered = registry::list()
+                .map_err(|err| RedactedError::new(&err))
+                .into_diagnostic()
+                .wrap_err("failed to load config")?;
+            if registered.is_empty() {
+                eprintln!("No repositories registered; add one with `git-clean repos add <path>`.");
+            }
+            for repository in registered {
+                println!("{}", repository.path.display());
+                if let Some(remote) = &repository.remote {
+                    println!("    remote: {remote}");
+                }
+                if let Some(provider) = repository.provider {
+                    println!("    provider: {provider}");
+                }
+                if !repository.protected_branches.is_empty() {
+                    println!(
+                        "    protected: {}",
+                        repository.protected_branches.join(", ")
+                    );
+                }
+            }
+        }
+        ReposCommand::Remove { path } => {
+            // it may be registered under its resolved path, or be gone by now
+            let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            let removed = registry::remove(&path)
+                .map_err(|err| RedactedError::new(&err))
+                .into_diagnostic()
+                .wrap_err("failed to unregister repository")?;
+            if !removed {
+                return Err(miette::miette!("{} is not registered", path.display()));
+            }
+            eprintln!("Unregistered {}.", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Print the name of each branch deleted, or which would be, sorted.
+fn print_names(report: &CleanReport) {
+    let mut names = report
+        .deleted()
+        .map(|branch| &branch.decision.branch_name)
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    for name in names {
+        println!("{name}");
+    }
+}
+
+/// The name of the repository at `path`, for reports.
+fn repo_title(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .ok()
+        .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Report on each of several repositories cleaned, then on them all.
+///
+/// A repository which failed is reported and the rest go on; the run then
+/// fails once they're done.
+fn report_many(
+    paths: &[String],
+    reports: Vec<Result<CleanReport, FatalError>>,
+    dry_run: bool,
+    format: Format,
+    copy_summary: bool,
+    logger: &Logger,
+) -> Result<()> {
+    let mut combined = CleanReport {
+        dry_run,
+        branches: Vec::new(),
+    };
+    let mut failed = 0;
+    let mut json = Vec::new();
+    let mut runs = Vec::new();
+    for (path, report) in paths.iter().zip(reports) {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                failed += 1;
+                eprintln!("\n{path}: {err}");
+                json.push(serde_json::json!({ "path": path, "error": err.to_string() }));
+                continue;
+            }
+        };
+        match format {
+            Format::Text => {
+                eprintln!("\n{path}:");
+                let evidence = evidence::render(&report);
+                if report.dry_run && !evidence.is_empty() {
+                    eprintln!("{evidence}");
+                }
+                eprint!("{}", summary::table(&report));
+            }
+            Format::Json => {
+                let mut value = json::render(&report);
+                value["path"] = path.as_str().into();
+                json.push(value);
+            }
+            Format::Sarif => {
+                let mut run = sarif::render(&report)["runs"][0].take();
+                run["automationDetails"] = serde_json::json!({ "id": path });
+                runs.push(run);
+            }
+            // named with their repositories, once all are done
+            Format::Names => {}
+        }
+        // the same branch name in two repositories is two branches
+        let title = repo_title(path);
+        combined
+            .branches
+            .extend(report.branches.into_iter().map(|mut branch| {
+                branch.decision.branch_name =
+                    format!("{title}/{}", branch.decision.branch_name).into();
+                branch
+            }));
+    }
+
+    match format {
+        Format::Text => eprint!(
+            "\nAll {} repositories:\n{}",
+            paths.len(),
+            summary::table(&combined)
+        ),
+        Format::Json => println!(
+            "{}",
+            serde_json::json!({
+                "repositories": json,
+                "summary": json::render(&combined)["summary"],
+            })
+        ),
+        Format::Sarif => {
+            let mut log = sarif::render(&combined);
+            log["runs"] = runs.into();
+            println!("{log}");
+        }
+        Format::Names => print_names(&combined),
+    }
+    let mut decisions = combined.decisions();
+    if let Some(explanation) = explain::nothing_deleted(&decisions, dry_run) {
+        eprintln!("{explanation}");
+    }
+    if copy_summary {
+        decisions.sort_unstable_by(|a, b| a.branch_name.cmp(&b.branch_name));
+        let title = format!("{} repositories", paths.len());
+        let markdown = render_markdown(&title, &decisions, &[], dry_run);
+        match clipboard::copy(&markdown) {
+            Ok(()) => slog::info!(logger, "copied report to clipboard"),
+            Err(err) => slog::warn!(logger, "failed to copy report to clipboard"; "err" => %err),
+        }
+    }
+    if failed > 0 {
+        return Err(miette::miette!(
+            "{failed} of {} repositories failed",
+            paths.len()
+        ));
+    }
+    Ok(())
+}
+
+fn show_config(path: &str, origin: bool) -> Result<()> {
+    let settings = git_clean_core::settings::effective(path).map_err(CliError::from)?;
+    let settings = settings
+        .iter()
+        .filter(|setting| origin || !setting.overridden)
+        .map(|setting| (format!("{} = {}", setting.key, setting.value), setting))
+        .collect::<Vec<_>>();
+    let width = settings
+        .iter()
+        .map(|(line, _)| line.chars().count())
+        .max()
+        .unwrap_or_default();
+    for (line, setting) in settings {
+        if origin {
+            let overridden = if setting.overridden {
+                ", overridden"
+            } else {
+                ""
+            };
+            println!("{line:width$}  ({}{overridden})", setting.origin);
+        } else {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+fn simulate(path: &Path) -> Result<()> {
+    let data = std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err("failed to read snapshot")?;
+    let snapshot = serde_json::from_str::<Snapshot>(&data)
+        .into_diagnostic()
+        .wrap_err("failed to parse snapshot")?;
+
+    let review_branches = snapshot
+        .branches
+        .iter()
+        .filter(|facts| facts.review_requested)
+        .map(|facts| facts.branch_name.clone())
+        .collect::<Vec<_>>();
+    let title = format!("snapshot {}", path.display());
+    print!(
+        "{}",
+        render_markdown(&title, &snapshot.decide(), &review_branches, true)
+    );
+    Ok(())
+}
+
+/// Print my open pull requests, matched to the local branches at `path`.
+async fn list_prs(path: &str) -> Result<()> {
+    let mut options = Options {
+        api_base_url: token::api_base_url()
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to load config")?,
+        ..Options::default()
+    };
+    options.apply_git_config(path).map_err(CliError::from)?;
+    let tokens = token::load(false)
+        .await
+        .map_err(|err| RedactedError::new(&err))
+        .into_diagnostic()
+        .wrap_err("failed to load config")?;
+    let map = git_clean_core::my_pull_requests(path, &options, tokens)
+        .await
+        .map_err(CliError::from)?;
+    print!("{}", prs::render(&map));
+    Ok(())
+}
+
+/// Show `branches` in the tui, and delete the ones chosen there.
+fn choose_and_delete(path: &str, mut branches: Vec<BranchReport>, options: &Options) -> Result<()> {
+    branches.sort_unstable_by(|a, b| a.decision.branch_name.cmp(&b.decision.branch_name));
+    let repo = open_repository(path).ok();
+    let rows = branches
+        .into_iter()
+        .map(|branch| tui::Row::new(repo.as_ref(), branch.decision, &branch.pull_requests))
+        .collect();
+    let Some(chosen) = tui::choose(rows)
+        .into_diagnostic()
+        .wrap_err("failed to run the tui")?
+    else {
+        eprintln!("Quit; nothing was deleted.");
+        return Ok(());
+    };
+    // what was chosen from the dry run is deleted for real
+    let options = Options {
+        dry_run: false,
+        ..options.clone()
+    };
+    delete_branches(path, &chosen, &options).map_err(CliError::from)?;
+    eprintln!(
+        "Deleted {} branch{}.",
+        chosen.len(),
+        if chosen.len() == 1 { "" } else { "es" }
+    );
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = Args::parse();
+    // `git-clean all` takes a run's flags, to apply to each registered repository
+    let all = if let Some(Command::All { flags }) = &args.command {
+        args =
+            Args::parse_from(std::iter::once("git-clean").chain(flags.iter().map(String::as_str)));
+        if args.command.is_some() || args.recursive.is_some() || args.path != ["."] {
+            Args::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    "`git-clean all` cleans the registered repositories, so takes no paths",
+                )
+                .exit();
+        }
+        true
+    } else {
+        false
+    };
+    let logger = slog_init(args.log_buffer);
+    git_clean_core::set_logger(logger.clone());
+    if args.tui && (args.path.len() > 1 || all) {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--tui takes a single repository",
+            )
+            .exit();
+    }
+    // subcommands conflict with paths, so take the default
+    let path = &args.path[0];
+
+    match &args.command {
+        Some(Command::Simulate { snapshot }) => return simulate(snapshot),
+        Some(Command::Config {
+            command: ConfigCommand::Show { origin },
+        }) => return show_config(path, *origin),
+        Some(Command::Recover {
+            run_id,
+            matching,
+            pr,
+            dry_run,
+        }) => {
+            let restored =
+                git_clean_core::journal::recover(path, run_id, matching.as_deref(), *pr, *dry_run)
+                    .map_err(CliError::from)?;
+            eprintln!(
+                "{} {} ref{}.",
+                if *dry_run {
+                    "Would recover"
+                } else {
+                    "Recovered"
+                },
+                restored.len(),
+                if restored.len() == 1 { "" } else { "s" }
+            );
+            return Ok(());
+        }
+        Some(Command::Prs) => return list_prs(path).await,
+        Some(Command::Repos { command }) => return manage_repos(command),
+        Some(Command::All { .. }) => unreachable!("replaced by its flags"),
+        Some(Command::Init { shell }) => {
+            print!("{}", init::script(*shell));
+            return Ok(());
+        }
+        Some(Command::Restore { branch, dry_run }) => {
+            let tip =
+                git_clean_core::archive::restore(path, branch, *dry_run).map_err(CliError::from)?;
+            let restored = if *dry_run {
+                "Would restore"
+            } else {
+                "Restored"
+            };
+            eprintln!("{restored} {branch} at {tip}.");
+            return Ok(());
+        }
+        None => {}
+    }
+    let registered = if all {
+        let registered = registry::list()
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to load config")?;
+        if registered.is_empty() {
+            return Err(miette::miette!(
+                "no repositories registered; add one with `git-clean repos add <path>`"
+            ));
+        }
+        Some(registered)
+    } else {
+        None
+    };
+    let paths = match &args.recursive {
+        _ if registered.is_some() => registered
+            .iter()
+            .flatten()
+            .map(|repository| repository.path.display().to_string())
+            .collect(),
+        Some(top) => {
+            let found = discover::repositories(top)
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    format!("failed to look for repositories in {}", top.display())
+                })?;
+            if found.is_empty() {
+                return Err(miette::miette!(
+                    "found no git repositories in {}",
+                    top.display()
+                ));
+            }
+            found
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect()
+        }
+        None => args.path.clone(),
+    };
+
+    if args.encrypt_config {
+        token::encrypt()
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to encrypt cached tokens")?;
+    }
+    if let Some(command) = args.token_command {
+        token::set_command(command)
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to cache token command")?;
+    }
+    // this run's url wins over git config, which wins over the cached one
+    let explicit_api_base_url = args.api_base_url.clone();
+    if let Some(url) = args.api_base_url {
+        token::set_api_base_url(url)
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to cache API base url")?;
+    }
+    if let Some(token) = args.personal_access_token {
+        git_clean_core::redact::register(&token);
+        token::save(token)
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to cache personal access token")?;
+    }
+    for token in args.additional_token {
+        git_clean_core::redact::register(&token);
+        token::add(token)
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to cache additional token")?;
+    }
+
+    let mut options = Options {
+        dry_run: args.dry_run,
+        clean_deployments: args.clean_deployments,
+        delete_environments: args.delete_environments,
+        integration_branch: args.integration_branch,
+        local_only: args.local_only,
+        gone: args.gone,
+        allow_delete_all: args.allow_delete_all,
+        max_delete: args.max_delete,
+        gist: args.gist,
+        approve: args.approve,
+        require_vetting: args.require_vetting,
+        require_merged: args.require_merged,
+        include_branches: args.include,
+        owner: args.owner,
+        exclude_branches: args
+            .exclude
+            .into_iter()
+            .chain(
+                token::protected_branches()
+                    .map_err(|err| RedactedError::new(&err))
+                    .into_diagnostic()
+                    .wrap_err("failed to load config")?,
+            )
+            .collect(),
+        keep_review_requests: args.keep_review_requests,
+        fix_remote: args.fix_remote,
+        stale_ref_age: args.stale_ref_age,
+        retarget_default: args.retarget_default,
+        trace_http: args.trace_http,
+        dump_snapshot: args.dump_snapshot,
+        record: args.record,
+        replay: args.replay,
+        retry_file: args.retry_file,
+        grep_references: args.grep_references,
+        case_insensitive_heads: args.case_insensitive_heads,
+        verify_pr_heads: !args.no_verify_pr_heads,
+        archive: args.archive,
+        exclude_recent: args.exclude_recent,
+        log_sample: (!args.verbose).then_some(DEFAULT_LOG_SAMPLE),
+        recent_prs_only: args.recent_prs_only,
+        min_confidence: args.min_confidence,
+        sample: args.sample,
+        gitlab_hosts: args.gitlab_hosts,
+        remote: args.remote,
+        provider: args.provider,
+        api_base_url: token::api_base_url()
+            .map_err(|err| RedactedError::new(&err))
+            .into_diagnostic()
+            .wrap_err("failed to load config")?,
+        ..Options::default()
+    };
+    if args.no_bot_fast_path {
+        options.bot_branches.clear();
+    }
+    if args.clean_stale_refs {
+        options.stale_ref_namespaces = if args.stale_ref_namespace.is_empty() {
+            DEFAULT_STALE_REF_NAMESPACES
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect()
+        } else {
+            args.stale_ref_namespace
+        };
+    }
+
+    // the tui deletes what is chosen from a dry run's decisions
+    options.dry_run |= args.tui;
+    if args.interactive {
+        options.confirm = Some(ConfirmHook::new(prompt::ask));
+    }
+    // each repository's git config applies to it alone
+    let configured = |path: &str| -> Result<Options> {
+        let mut options = options.clone();
+        options.apply_git_config(path).map_err(CliError::from)?;
+        if explicit_api_base_url.is_some() {
+            options.api_base_url = explicit_api_base_url.clone();
+        }
+        Ok(options)
+    };
+
+    let tokens = token::load(args.strict_config)
+        .await
+        .map_err(|err| RedactedError::new(&err))
+        .into_diagnostic()
+        .wrap_err("failed to load config")?;
+    if paths.len() > 1 || registered.is_some() {
+        let mut repositories = paths
+            .iter()
+            .map(|path| {
+                let options = configured(path).wrap_err_with(|| format!("in {path}"))?;
+                Ok((PathBuf::from(path), options))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for ((_, options), repository) in repositories.iter_mut().zip(registered.iter().flatten()) {
+            repository.apply(options);
+        }
+        let reports = clean_branches_many(&repositories, tokens).await;
+        return report_many(
+            &paths,
+            reports,
+            options.dry_run,
+            args.format,
+            args.copy_summary,
+            &logger,
+        );
+    }
+    let path = &paths[0];
+    let options = configured(path)?;
+    let title = repo_title(path);
+    let report = clean_branches(path, &options, tokens)
+        .await
+        .map_err(CliError::from)?;
+
+    if args.tui {
+        return choose_and_delete(path, report.branches, &options);
+    }
+    match args.format {
+        Format::Text => {
+            let evidence = evidence::render(&report);
+            if report.dry_run && !evidence.is_empty() {
+                eprint!("\n{evidence}");
+            }
+            eprint!("\n{}", summary::table(&report));
+        }
+        Format::Json => println!("{}", json::render(&report)),
+        Format::Sarif => println!("{}", sarif::render(&report)),
+        Format::Names => print_names(&report),
+    }
+    let mut decisions = report.decisions();
+    let dry_run = report.dry_run;
+    if let Some(explanation) = explain::nothing_deleted(&decisions, dry_run) {
+        eprintln!("{explanation}");
+    }
+    if args.copy_summary {
+        decisions.sort_unstable_by(|a, b| a.branch_name.cmp(&b.branch_name));
+        let markdown = render_markdown(&title, &decisions, &[], dry_run);
+        match clipboard::copy(&markdown) {
+            Ok(()) => slog::info!(logger, "copied report to clipboard"),
+            Err(err) => slog::warn!(logger, "failed to copy report to clipboard"; "err" => %err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Asking about each branch before deleting it, for `--interactive`.
+
+use std::io::{self, BufRead, Write};
+
+use git_clean_core::{Candidate, Confirmation};
+
+use crate::evidence::{ago, pull_request};
+
+/// Show `candidate` on stderr, and ask on stdin what to do with it.
+///
+/// The end of input aborts, so that nothing is deleted unasked.
+pub fn ask(candidate: &Candidate) -> Confirmation {
+    let mut stderr = io::stderr().lock();
+    let _ = writeln!(stderr, "\n{}", describe(candidate));
+    let mut answer = String::new();
+    loop {
+        let _ = write!(
+            stderr,
+            "Delete {}? [y]es, [n]o, [a]bort: ",
+            candidate.branch_name
+        );
+        let _ = stderr.flush();
+        answer.clear();
+        match io::stdin().lock().read_line(&mut answer) {
+            Ok(0) | Err(_) => return Confirmation::Abort,
+            Ok(_) => {}
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Confirmation::Delete,
+            "n" | "no" => return Confirmation::Skip,
+            "a" | "abort" => return Confirmation::Abort,
+            _ => {}
+        }
+    }
+}
+
+fn describe(candidate: &Candidate) -> String {
+    let mut lines = vec![format!(
+        "{}: {} confidence{}{}",
+        candidate.branch_name,
+        candidate.confidence,
+        if candidate.bot { ", bot branch" } else { "" },
+        candidate
+            .last_commit
+            .map(|time| format!(", last commit {}", ago(time)))
+            .unwrap_or_default()
+    )];
+    for pr in &candidate.pull_requests {
+        lines.push(format!("  {}", pull_request(pr)));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,80 @@
+//! `git-clean prs`: my open pull requests, and the local branches for them.
+
+use git_clean_core::{Checkout, PullRequestMap};
+
+/// One line per pull request, saying where its branch is, then the local
+/// branches without any pull request.
+pub fn render(map: &PullRequestMap) -> String {
+    let mut out = String::new();
+    if map.pull_requests.is_empty() {
+        out.push_str("No open pull requests of mine.\n");
+    }
+    for mine in &map.pull_requests {
+        let pr = &mine.pull_request;
+        let title = pr.title.as_deref().unwrap_or("(untitled)");
+        let branch = match (&mine.checked_out, mine.local) {
+            (Some(Checkout::MainWorktree), _) => "checked out".to_owned(),
+            (Some(Checkout::Worktree(worktree)), _) => format!("checked out in {worktree}"),
+            (None, true) => "local branch".to_owned(),
+            (None, false) => "no local branch".to_owned(),
+        };
+        out.push_str(&format!("#{} {title}\n", pr.number));
+        out.push_str(&format!("    {}: {branch}\n", mine.head));
+    }
+    if !map.without_pull_requests.is_empty() {
+        out.push_str("\nLocal branches without pull requests:\n");
+        for branch_name in &map.without_pull_requests {
+            out.push_str(&format!("    {branch_name}\n"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use git_clean_core::{MyPullRequest, PrSummary};
+
+    use super::*;
+
+    #[test]
+    fn says_where_each_branch_is() {
+        let mine = |number, head: &str, local, checked_out| MyPullRequest {
+            pull_request: PrSummary {
+                number,
+                open: true,
+                title: Some(format!("Pull request {number}")),
+                url: None,
+                author: None,
+                closed_at: None,
+                merged_at: None,
+                merged_by: None,
+                head_sha: None,
+            },
+            head: head.into(),
+            local,
+            checked_out,
+        };
+        let map = PullRequestMap {
+            pull_requests: vec![
+                mine(
+                    1,
+                    "fix",
+                    true,
+                    Some(Checkout::Worktree("review".to_owned())),
+                ),
+                mine(2, "feature", false, None),
+            ],
+            without_pull_requests: vec!["wip".into()],
+        };
+        assert_eq!(
+            render(&map),
+            "#1 Pull request 1\n    \
+                 fix: checked out in review\n\
+             #2 Pull request 2\n    \
+                 feature: no local branch\n\
+             \n\
+             Local branches without pull requests:\n    \
+                 wip\n"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Redacting tokens from everything the command line prints.
+
+use std::fmt;
+
+use git_clean_core::redact::redact;
+use slog::{Drain, Key, OwnedKVList, Record, Serializer, KV};
+
+/// Collects key-value pairs as redacted strings.
+#[derive(Default)]
+struct Collect(Vec<(Key, String)>);
+
+impl Serializer for Collect {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        self.0.push((key, redact(&val.to_string()).into_owned()));
+        Ok(())
+    }
+}
+
+impl KV for Collect {
+    fn serialize(&self, _record: &Record, serializer: &mut dyn Serializer) -> slog::Result {
+        // already in the order the original pairs were emitted
+        for (key, value) in &self.0 {
+            serializer.emit_str(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A drain which masks tokens in messages and values before passing records
+/// on.
+pub struct RedactDrain<D>(pub D);
+
+impl<D: Drain> Drain for RedactDrain<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let mut kv = Collect::default();
+        // the record's own pairs are printed before the logger's
+        let _ = record.kv().serialize(record, &mut kv);
+        let _ = values.serialize(record, &mut kv);
+
+        let msg = redact(&record.msg().to_string()).into_owned();
+        let empty = OwnedKVList::from(slog::o!());
+        self.0.log(
+            &Record::new(
+                &slog::RecordStatic {
+                    location: record.location(),
+                    tag: record.tag(),
+                    level: record.level(),
+                },
+                &format_args!("{msg}"),
+                slog::BorrowedKV(&kv),
+            ),
+            &empty,
+        )
+    }
+}
+
+/// An error chain with every message redacted, for display.
+#[derive(Debug)]
+pub struct RedactedError {
+    message: String,
+    source: Option<Box<RedactedError>>,
+}
+
+impl RedactedError {
+    pub fn new(err: &(dyn std::error::Error + 'static)) -> Self {
+        Self {
+            message: redact(&err.to_string()).into_owned(),
+            source: err.source().map(|source| Box::new(Self::new(source))),
+        }
+    }
+}
+
+impl fmt::Display for RedactedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RedactedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
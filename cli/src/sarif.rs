@@ -0,0 +1,87 @@
+//! The report of a run as SARIF, for `--format sarif`, so that dashboards
+//! which ingest findings from linters can show a repository's hygiene.
+//!
+//! Each deletable branch is a finding. Branches have no place in a file, so
+//! findings are located logically, by ref name.
+
+use git_clean_core::{BranchReport, CleanReport, Outcome};
+use serde_json::{json, Value};
+
+const STALE_BRANCH: &str = "stale-branch";
+const STALE_BOT_BRANCH: &str = "stale-bot-branch";
+
+/// A SARIF 2.1.0 log with one run, whose results are the deletable branches,
+/// sorted by name.
+pub fn render(report: &CleanReport) -> Value {
+    let mut branches = report
+        .branches
+        .iter()
+        .filter(|branch| matches!(branch.decision.outcome, Outcome::Delete { .. }))
+        .collect::<Vec<_>>();
+    branches.sort_unstable_by(|a, b| a.decision.branch_name.cmp(&b.decision.branch_name));
+    let results = branches
+        .into_iter()
+        .map(|branch| result(branch, report.dry_run))
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "git-clean",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/coriolinus/git-clean",
+                    "rules": [
+                        rule(STALE_BRANCH, "Branch whose work has been merged or abandoned"),
+                        rule(STALE_BOT_BRANCH, "Bot-created branch whose work is done"),
+                    ],
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn rule(id: &str, description: &str) -> Value {
+    json!({
+        "id": id,
+        "shortDescription": { "text": description },
+        "defaultConfiguration": { "level": "note" },
+    })
+}
+
+fn result(branch: &BranchReport, dry_run: bool) -> Value {
+    let Outcome::Delete { bot, confidence } = &branch.decision.outcome else {
+        unreachable!("only deletable branches are findings");
+    };
+    let name = branch.decision.branch_name.as_str();
+    let text = if dry_run {
+        format!("branch `{name}` can be deleted ({confidence} confidence)")
+    } else {
+        format!("branch `{name}` was deleted ({confidence} confidence)")
+    };
+    json!({
+        "ruleId": if *bot { STALE_BOT_BRANCH } else { STALE_BRANCH },
+        "level": "note",
+        "message": { "text": text },
+        "locations": [{
+            "logicalLocations": [{
+                "name": name,
+                "fullyQualifiedName": format!("refs/heads/{name}"),
+                "kind": "branch",
+            }],
+        }],
+        "properties": {
+            "confidence": confidence.to_string(),
+            "tip": branch.tip,
+            "owners": branch.owners,
+            "pullRequests": branch
+                .pull_requests
+                .iter()
+                .filter_map(|pr| pr.url.clone())
+                .collect::<Vec<_>>(),
+        },
+    })
+}
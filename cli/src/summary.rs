@@ -0,0 +1,107 @@
+//! A table of what became of the branches, printed when a run is done.
+
+use git_clean_core::{CleanReport, Outcome, Reason};
+
+/// Counts of deleted, retained and failed branches, the latter two broken
+/// down by why.
+pub fn table(report: &CleanReport) -> String {
+    let mut deleted = 0;
+    let mut bots = 0;
+    let mut retained = Vec::new();
+    let mut failed = Vec::new();
+    for branch in &report.branches {
+        match &branch.decision.outcome {
+            Outcome::Delete { bot: false, .. } => deleted += 1,
+            Outcome::Delete { bot: true, .. } => bots += 1,
+            Outcome::Retain(reason) => tally(&mut retained, label(reason)),
+            Outcome::Failed(issue) => tally(&mut failed, issue.kind().to_string()),
+        }
+    }
+
+    let mut rows = vec![(
+        if report.dry_run {
+            "would delete"
+        } else {
+            "deleted"
+        }
+        .to_owned(),
+        deleted,
+    )];
+    if bots > 0 {
+        rows.push(("  of which bots".to_owned(), bots));
+        rows[0].1 += bots;
+    }
+    for (heading, mut tallies) in [("retained", retained), ("failed", failed)] {
+        rows.push((heading.to_owned(), tallies.iter().map(|(_, n)| n).sum()));
+        // most common first; ties in a stable order
+        tallies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        rows.extend(tallies.into_iter().map(|(why, n)| (format!("  {why}"), n)));
+    }
+
+    let width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or_default();
+    rows.iter()
+        .map(|(label, count)| format!("{label:width$}  {count:>4}\n"))
+        .collect()
+}
+
+fn tally(tallies: &mut Vec<(String, usize)>, key: String) {
+    match tallies.iter_mut().find(|(known, _)| *known == key) {
+        Some((_, count)) => *count += 1,
+        None => tallies.push((key, 1)),
+    }
+}
+
+/// Why a branch was retained, without what differs between branches.
+fn label(reason: &Reason) -> String {
+    match reason {
+        Reason::Referenced(_) => "still referenced".to_owned(),
+        Reason::CheckedOut(_) => "checked out".to_owned(),
+        Reason::Unpushed(_) => "unpushed commits".to_owned(),
+        Reason::RecentCommit(_) => "committed to recently".to_owned(),
+        reason => reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use git_clean_core::{BranchReport, Confidence, Decision};
+
+    use super::*;
+
+    #[test]
+    fn breaks_down_retained_branches_by_reason() {
+        let report = CleanReport {
+            dry_run: true,
+            branches: [
+                Decision::delete("a".into(), Confidence::High),
+                Decision::delete_bot("b".into()),
+                Decision::retain("c".into(), Reason::NoPullRequests),
+                Decision::retain("d".into(), Reason::OpenPullRequest),
+                Decision::retain("e".into(), Reason::NoPullRequests),
+                Decision::retain("f".into(), Reason::Referenced(vec!["issue #1".into()])),
+            ]
+            .into_iter()
+            .map(|decision| BranchReport {
+                decision,
+                pull_requests: Vec::new(),
+                tip: None,
+                owners: Vec::new(),
+            })
+            .collect(),
+        };
+        assert_eq!(
+            table(&report),
+            "would delete            2\n  \
+               of which bots         1\n\
+             retained                4\n  \
+               no pull requests      2\n  \
+               open pull request     1\n  \
+               still referenced      1\n\
+             failed                  0\n"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! A full-screen list of every branch, for choosing which to delete, for
+//! `--tui`.
+//!
+//! Branches are listed with the decision of a dry run, and the ones it would
+//! delete start out selected. The selection is deleted once confirmed, and
+//! confirmed again if it includes branches of open pull requests.
+//!
+//! Branches sharing a prefix, such as `dependabot/`, are grouped under a line
+//! of their own, which selects or deselects them all at once and can be
+//! collapsed. Typing after `/` narrows the list to the branches whose names
+//! contain it, and `d` to the ones the dry run would delete.
+
+use std::{collections::HashSet, io};
+
+use git_clean_core::{
+    git2::{BranchType, Repository},
+    Decision, Outcome, PrSummary, Reason, SmallStr,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal,
+};
+
+const HELP: &str = "↑/↓ move · pgup/pgdn page · ←/→ fold group · / search · d only to delete\n\
+                    space select · a all to delete · n none · enter delete selected · q quit";
+
+/// A branch as listed.
+pub struct Row {
+    branch_name: SmallStr,
+    state: String,
+    pull_requests: String,
+    /// The dry run would delete it.
+    deletable: bool,
+    /// Any of its pull requests is open.
+    open: bool,
+    selected: bool,
+}
+
+impl Row {
+    /// Classify a branch by the states of [`git_clean_core::clean_branches`],
+    /// or why else it was decided as it was.
+    pub fn new(repo: Option<&Repository>, decision: Decision, pull_requests: &[PrSummary]) -> Self {
+        let pushed = || {
+            repo.and_then(|repo| {
+                repo.find_branch(&decision.branch_name, BranchType::Local)
+                    .ok()
+            })
+            .is_some_and(|branch| branch.upstream().is_ok())
+        };
+        let state = match &decision.outcome {
+            Outcome::Retain(Reason::NoPullRequests) if pushed() => "2 pushed, no PRs".to_owned(),
+            Outcome::Retain(Reason::NoPullRequests) => "1 not pushed".to_owned(),
+            Outcome::Retain(Reason::OpenPullRequest) => "3 PR open".to_owned(),
+            Outcome::Delete { bot: true, .. } => "4 PRs closed, bot".to_owned(),
+            Outcome::Delete { confidence, .. } if pull_requests.is_empty() => {
+                format!("4 landed, {confidence}")
+            }
+            Outcome::Delete { confidence, .. } => format!("4 PRs closed, {confidence}"),
+            Outcome::Retain(reason) => format!("- {reason}"),
+            Outcome::Failed(issue) => format!("? {issue}"),
+        };
+        let open = pull_requests.iter().any(|pr| pr.open);
+        let pull_requests = pull_requests
+            .iter()
+            .map(|pr| {
+                let state = match (&pr.merged_by, pr.merged_at, pr.open) {
+                    (_, _, true) => "open".to_owned(),
+                    (Some(merged_by), _, _) => format!("merged by {merged_by}"),
+                    (None, Some(_), _) => "merged".to_owned(),
+                    (None, None, false) => "closed".to_owned(),
+                };
+                format!("#{} {state}", pr.number)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self {
+            deletable: decision.is_delete(),
+            open,
+            selected: decision.is_delete(),
+            branch_name: decision.branch_name,
+            state,
+            pull_requests,
+        }
+    }
+}
+
+/// The part of `branch_name` up to and including its first `/`, by which
+/// branches are grouped.
+fn prefix(branch_name: &str) -> Option<&str> {
+    branch_name.find('/').map(|end| &branch_name[..=end])
+}
+
+/// A line of the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    /// Branches sharing a prefix.
+    Group(String),
+    /// The row at an index, and whether it belongs to a group.
+    Branch(usize, bool),
+}
+
+/// Which rows are listed, and how.
+#[derive(Default)]
+struct View {
+    /// Only the branches whose names contain this are listed.
+    search: String,
+    /// Keys are typed into `search`.
+    searching: bool,
+    /// Only the branches the dry run would delete are listed.
+    deletable_only: bool,
+    /// Groups whose branches are left out.
+    collapsed: HashSet<String>,
+}
+
+impl View {
+    fn shows(&self, row: &Row) -> bool {
+        (row.deletable || !self.deletable_only) && row.branch_name.contains(self.search.as_str())
+    }
+
+    /// The lines listing `rows`, which are sorted by name: a group line goes
+    /// before the branches of each prefix shown more than once.
+    fn entries(&self, rows: &[Row]) -> Vec<Entry> {
+        let shown = (0..rows.len())
+            .filter(|&i| self.shows(&rows[i]))
+            .collect::<Vec<_>>();
+        let mut entries = Vec::new();
+        let mut rest = &shown[..];
+        while let Some(&first) = rest.first() {
+            let group = prefix(&rows[first].branch_name);
+            let len = match group {
+                Some(group) => rest
+                    .iter()
+                    .take_while(|&&i| prefix(&rows[i].branch_name) == Some(group))
+                    .count(),
+                None => 1,
+            };
+            let (members, tail) = rest.split_at(len);
+            match group {
+                Some(group) if len > 1 => {
+                    entries.push(Entry::Group(group.to_owned()));
+                    if !self.collapsed.contains(group) {
+                        entries.extend(members.iter().map(|&i| Entry::Branch(i, true)));
+                    }
+                }
+                _ => entries.extend(members.iter().map(|&i| Entry::Branch(i, false))),
+            }
+            rest = tail;
+        }
+        entries
+    }
+
+    /// The rows shown in `group`.
+    fn members<'a>(&'a self, rows: &'a [Row], group: &'a str) -> impl Iterator<Item = usize> + 'a {
+        (0..rows.len())
+            .filter(move |&i| prefix(&rows[i].branch_name) == Some(group) && self.shows(&rows[i]))
+    }
+
+    /// Select every branch shown in `group`, or deselect them all if they
+    /// already are.
+    fn toggle_group(&self, rows: &mut [Row], group: &str) {
+        let members = self.members(rows, group).collect::<Vec<_>>();
+        let selected = !members.iter().all(|&i| rows[i].selected);
+        for i in members {
+            rows[i].selected = selected;
+        }
+    }
+}
+
+/// Let the user choose among `rows`, returning the branches to delete, or
+/// `None` if they quit.
+pub fn choose(mut rows: Vec<Row>) -> io::Result<Option<Vec<SmallStr>>> {
+    let mut terminal = ratatui::init();
+    let restore = Restore;
+    let chosen = run(&mut terminal, &mut rows);
+    drop(restore);
+    let chosen = chosen?;
+    Ok(chosen.then(|| {
+        rows.into_iter()
+            .filter(|row| row.selected)
+            .map(|row| row.branch_name)
+            .collect()
+    }))
+}
+
+/// Restores the terminal when dropped, even while unwinding from a panic in
+/// [`run`].
+struct Restore;
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Whether the selection was confirmed.
+fn run(terminal: &mut DefaultTerminal, rows: &mut [Row]) -> io::Result<bool> {
+    let name_width = rows
+        .iter()
+        .map(|row| row.branch_name.chars().count())
+        .max()
+        .unwrap_or_default();
+    let state_width = rows
+        .iter()
+        .map(|row| row.state.chars().count())
+        .max()
+        .unwrap_or_default();
+    let mut list_state = ListState::default().with_selected((!rows.is_empty()).then_some(0));
+    let mut view = View::default();
+    // the selected branches of open pull requests, once asked to delete them
+    let mut confirming: Option<Vec<String>> = None;
+    // the rows the list has room for, as of the last draw
+    let mut page = 1;
+
+    loop {
+        let entries = view.entries(rows);
+        terminal.draw(|frame| {
+            let [list_area, help_area] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(2)]).areas(frame.area());
+            page = list_area.height.saturating_sub(2).max(1);
+            let selected = rows.iter().filter(|row| row.selected).count();
+            let items = entries.iter().map(|entry| {
+                let line = match entry {
+                    Entry::Group(group) => {
+                        let members = view.members(rows, group).collect::<Vec<_>>();
+                        let selected = members.iter().filter(|&&i| rows[i].selected).count();
+                        format!(
+                            "[{}] {} {group} {selected} of {} selected",
+                            match selected {
+                                0 => " ",
+                                n if n == members.len() => "x",
+                                _ => "-",
+                            },
+                            if view.collapsed.contains(group) {
+                                "▸"
+                            } else {
+                                "▾"
+                            },
+                            members.len(),
+                        )
+                    }
+                    &Entry::Branch(i, grouped) => {
+                        let row = &rows[i];
+                        format!(
+                            "[{}] {}{:name_width$}  {:state_width$}  {}",
+                            if row.selected { "x" } else { " " },
+                            if grouped { "  " } else { "" },
+                            row.branch_name,
+                            row.state,
+                            row.pull_requests,
+                            name_width = name_width + if grouped { 0 } else { 2 },
+                        )
+                    }
+                };
+                ListItem::new(Line::from(line))
+            });
+            let mut title = format!(" git-clean: {selected} of {} branches selected", rows.len());
+            if view.deletable_only {
+                title.push_str(" · only to delete");
+            }
+            if !view.search.is_empty() || view.searching {
+                title.push_str(&format!(" · matching \"{}\"", view.search));
+            }
+            title.push(' ');
+            let list = List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, list_area, &mut list_state);
+            let help = match &confirming {
+                Some(open) => format!(
+                    "{} of open PRs: {} · y delete anyway · any other key back",
+                    if open.len() == 1 {
+                        "a branch"
+                    } else {
+                        "branches"
+                    },
+                    open.join(", "),
+                ),
+                None if view.searching => {
+                    format!("/{}▏\nenter done · esc clear", view.search)
+                }
+                None => HELP.to_owned(),
+            };
+            frame.render_widget(Paragraph::new(help), help_area);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if confirming.take().is_some() {
+            if key.code == KeyCode::Char('y') {
+                return Ok(true);
+            }
+            continue;
+        }
+        if view.searching {
+            match key.code {
+                KeyCode::Char(c) => view.search.push(c),
+                KeyCode::Backspace => {
+                    view.search.pop();
+                }
+                KeyCode::Enter => view.searching = false,
+                KeyCode::Esc => {
+                    view.search.clear();
+                    view.searching = false;
+                }
+                _ => continue,
+            }
+            list_state.select_first();
+            continue;
+        }
+        let entry = list_state.selected().and_then(|i| entries.get(i));
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => list_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => list_state.select_next(),
+            KeyCode::PageUp => list_state.scroll_up_by(page),
+            KeyCode::PageDown => list_state.scroll_down_by(page),
+            KeyCode::Home => list_state.select_first(),
+            KeyCode::End => list_state.select_last(),
+            KeyCode::Left | KeyCode::Char('h') => {
+                let group = match entry {
+                    Some(Entry::Group(group)) => group,
+                    Some(&Entry::Branch(i, true)) => {
+                        prefix(&rows[i].branch_name).expect("grouped by its prefix")
+                    }
+                    _ => continue,
+                };
+                view.collapsed.insert(group.to_owned());
+                let line = view.entries(rows).iter().position(
+                    |entry| matches!(entry, Entry::Group(collapsed) if collapsed == group),
+                );
+                list_state.select(line);
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(Entry::Group(group)) = entry {
+                    view.collapsed.remove(group);
+                }
+            }
+            KeyCode::Char(' ') => match entry {
+                Some(Entry::Group(group)) => view.toggle_group(rows, group),
+                Some(&Entry::Branch(i, _)) => rows[i].selected = !rows[i].selected,
+                None => {}
+            },
+            KeyCode::Char('/') => view.searching = true,
+            KeyCode::Char('d') => {
+                view.deletable_only = !view.deletable_only;
+                list_state.select_first();
+            }
+            KeyCode::Char('a') => rows
+                .iter_mut()
+                .filter(|row| view.shows(row))
+                .for_each(|row| row.selected = row.deletable),
+            KeyCode::Char('n') => rows
+                .iter_mut()
+                .filter(|row| view.shows(row))
+                .for_each(|row| row.selected = false),
+            KeyCode::Enter => {
+                let open = rows
+                    .iter()
+                    .filter(|row| row.selected && row.open)
+                    .map(|row| row.branch_name.to_string())
+                    .collect::<Vec<_>>();
+                if open.is_empty() {
+                    return Ok(true);
+                }
+                confirming = Some(open);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use git_clean_core::Decision;
+
+    use super::*;
+
+    fn rows(names: &[&str]) -> Vec<Row> {
+        names
+            .iter()
+            .map(|&name| {
+                let decision = if name.starts_with("dependabot/") {
+                    Decision::delete_bot(name.into())
+                } else {
+                    Decision::retain(name.into(), Reason::NoPullRequests)
+                };
+                Row::new(None, decision, &[])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_branches_by_prefix() {
+        let rows = rows(&["dependabot/a", "dependabot/b", "fix/c", "topic"]);
+        let mut view = View::default();
+        assert_eq!(
+            view.entries(&rows),
+            [
+                Entry::Group("dependabot/".to_owned()),
+                Entry::Branch(0, true),
+                Entry::Branch(1, true),
+                Entry::Branch(2, false),
+                Entry::Branch(3, false),
+            ]
+        );
+
+        view.collapsed.insert("dependabot/".to_owned());
+        view.search = "o".to_owned();
+        assert_eq!(
+            view.entries(&rows),
+            [
+                Entry::Group("dependabot/".to_owned()),
+                Entry::Branch(3, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn toggles_the_shown_branches_of_a_group() {
+        let mut rows = rows(&["dependabot/a", "dependabot/b", "topic"]);
+        let mut view = View::default();
+        view.toggle_group(&mut rows, "dependabot/");
+        assert!(rows.iter().all(|row| !row.selected));
+        view.toggle_group(&mut rows, "dependabot/");
+        assert!(rows[0].selected && rows[1].selected && !rows[2].selected);
+
+        view.search = "/b".to_owned();
+        view.toggle_group(&mut rows, "dependabot/");
+        assert!(rows[0].selected && !rows[1].selected);
+    }
+}
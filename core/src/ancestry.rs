@@ -0,0 +1,431 @@
+//! Offline, ancestry-based branch analysis.
+//!
+//! When there is no forge to ask about pull requests, the best evidence we have
+//! that a branch is finished is that its tip is already contained in the
+//! integration branch, or else that its changes are, as after a squash merge.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Once,
+};
+
+use git2::{Branch, BranchType, Commit, Oid, Repository, Sort};
+
+use crate::{
+    error::ContextErr,
+    report::{Confidence, Decision, Reason},
+    FatalError, SmallStr,
+};
+
+/// Branch names tried, in order, when no integration branch is configured.
+const FALLBACK_INTEGRATION_BRANCHES: &[&str] = &["main", "master"];
+
+/// Let libgit2 open partial clones.
+///
+/// libgit2 refuses to open repositories declaring the `partialclone`
+/// extension unless told that the caller copes with missing objects. We do:
+/// see [`is_partial_clone`].
+fn allow_partial_clones() -> Result<(), FatalError> {
+    static ALLOW: Once = Once::new();
+    let mut result = Ok(());
+    ALLOW.call_once(|| {
+        // Safety: this is the only place we touch libgit2's extension list,
+        // and `Once` guarantees it happens exactly once, before any
+        // repository is opened.
+        result = unsafe { git2::opts::set_extensions(&["partialclone"]) }
+            .context("register partialclone extension");
+    });
+    result
+}
+
+/// Open the repository containing `path`, partial clones included.
+///
+/// Everything which opens a repository should do so through this, or
+/// partial clones fail with "unsupported extension name".
+pub fn open_repository(path: impl AsRef<Path>) -> Result<Repository, FatalError> {
+    allow_partial_clones()?;
+    Repository::discover(path).context("open repo from path")
+}
+
+/// Whether this is a partial (blobless or treeless) clone.
+///
+/// Objects missing from a partial clone are fetched on demand by git, but
+/// libgit2 cannot do that: reading them fails instead. Heuristics which need
+/// trees or blobs must check this first; walking commits is always safe.
+pub(crate) fn is_partial_clone(repo: &Repository) -> bool {
+    let Ok(config) = repo.config() else {
+        return false;
+    };
+    if config.get_string("extensions.partialClone").is_ok() {
+        return true;
+    }
+
+    let Ok(mut entries) = config.entries(Some(r"^remote\..*\.promisor$")) else {
+        return false;
+    };
+    while let Some(Ok(entry)) = entries.next() {
+        if entry.value() == Some("true") {
+            return true;
+        }
+    }
+    false
+}
+
+/// Find the local branch against which merged-ness is judged.
+///
+/// If none is configured, use the local branch named like a remote's default
+/// branch, then git's `init.defaultBranch`, then the usual suspects.
+pub(crate) fn integration_branch<'repo>(
+    repo: &'repo Repository,
+    configured: Option<&str>,
+) -> Result<Branch<'repo>, FatalError> {
+    if let Some(name) = configured {
+        return repo
+            .find_branch(name, BranchType::Local)
+            .map_err(|_| FatalError::IntegrationBranchNotFound(name.to_owned()));
+    }
+
+    let init_default = repo
+        .config()
+        .and_then(|config| config.get_string("init.defaultBranch"))
+        .ok();
+    remote_defaults(repo)
+        .iter()
+        .chain(&init_default)
+        .map(String::as_str)
+        .chain(FALLBACK_INTEGRATION_BRANCHES.iter().copied())
+        .find_map(|name| repo.find_branch(name, BranchType::Local).ok())
+        .ok_or(FatalError::NoIntegrationBranch)
+}
+
+/// The default branches of the remotes, as last fetched, from their HEAD
+/// symrefs.
+pub(crate) fn remote_defaults(repo: &Repository) -> Vec<String> {
+    let Ok(remotes) = repo.remotes() else {
+        return Vec::new();
+    };
+    remotes
+        .iter()
+        .flatten()
+        .filter_map(|remote_name| {
+            let remote_prefix = format!("refs/remotes/{remote_name}/");
+            let head = repo.find_reference(&format!("{remote_prefix}HEAD")).ok()?;
+            let target = head.symbolic_target()?;
+            target.strip_prefix(&remote_prefix).map(str::to_owned)
+        })
+        .collect()
+}
+
+/// Whether the integration branch has ever been moved to a commit which
+/// neither contains its previous tip nor is contained in it, according to
+/// its reflog.
+///
+/// After such a rebase or force-push, the branch may contain commits nobody
+/// merged into it, so ancestry says nothing about them. Moving it back, as
+/// `git reset --hard HEAD~1` does, only drops commits: branches merged in
+/// those are retained as unmerged, which is safe.
+pub(crate) fn was_rewritten(repo: &Repository, integration_refname: &str) -> bool {
+    let Ok(reflog) = repo.reflog(integration_refname) else {
+        return false;
+    };
+    reflog.iter().any(|entry| {
+        let (old, new) = (entry.id_old(), entry.id_new());
+        let related = |descendant, ancestor| {
+            repo.graph_descendant_of(descendant, ancestor)
+                .unwrap_or(false)
+        };
+        // a missing old commit was rewritten away and since collected
+        !old.is_zero() && old != new && !related(new, old) && !related(old, new)
+    })
+}
+
+/// Whether `tip` is contained in the history of `integration_tip`.
+///
+/// This matches `git branch --merged`: a branch whose tip is exactly the
+/// integration tip counts as merged.
+pub(crate) fn contains(
+    repo: &Repository,
+    integration_tip: Oid,
+    tip: Oid,
+) -> Result<bool, FatalError> {
+    Ok(tip == integration_tip
+        || repo
+            .graph_descendant_of(integration_tip, tip)
+            .context("compare branch ancestry")?)
+}
+
+/// How a branch's work reached the integration branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Landed {
+    /// Its tip is contained in the integration branch.
+    Merged,
+    /// Its changes are, under other commits, after a squash or rebase merge.
+    Squashed,
+}
+
+/// The most integration commits searched for a branch's changes.
+const MAX_PATCH_SEARCH: usize = 2000;
+
+/// Finds branches whose changes reached the integration branch under other
+/// commits, by comparing patch ids as `git cherry` does.
+///
+/// Patch ids need file contents, so this is no use in a partial clone.
+pub(crate) struct PatchIndex<'repo> {
+    repo: &'repo Repository,
+    integration_tip: Oid,
+    /// Patch ids of integration commits; `None` for merges, and commits
+    /// which change nothing or could not be diffed.
+    known: HashMap<Oid, Option<Oid>>,
+}
+
+impl<'repo> PatchIndex<'repo> {
+    pub fn new(repo: &'repo Repository, integration_tip: Oid) -> Self {
+        Self {
+            repo,
+            integration_tip,
+            known: HashMap::new(),
+        }
+    }
+
+    /// Patch ids of the integration commits since `base`.
+    fn landed_since(&mut self, base: Oid) -> HashSet<Oid> {
+        let Some(walk) = walk_range(self.repo, self.integration_tip, base) else {
+            return HashSet::new();
+        };
+        let mut landed = HashSet::new();
+        for oid in walk.take(MAX_PATCH_SEARCH) {
+            let repo = self.repo;
+            let patch_id = *self.known.entry(oid).or_insert_with(|| {
+                repo.find_commit(oid)
+                    .ok()
+                    .and_then(|commit| commit_patch_id(repo, &commit))
+            });
+            landed.extend(patch_id);
+        }
+        landed
+    }
+
+    /// Whether the changes of the branch at `tip` are all in the integration
+    /// branch: either squashed into one commit, or commit by commit.
+    pub fn squashed(&mut self, tip: Oid) -> bool {
+        let Ok(base) = self.repo.merge_base(self.integration_tip, tip) else {
+            return false;
+        };
+        let landed = self.landed_since(base);
+        if landed.is_empty() {
+            return false;
+        }
+
+        let (Ok(base_tree), Ok(tip_tree)) = (
+            self.repo.find_commit(base).and_then(|commit| commit.tree()),
+            self.repo.find_commit(tip).and_then(|commit| commit.tree()),
+        ) else {
+            return false;
+        };
+        if patch_id(self.repo, Some(&base_tree), &tip_tree).is_some_and(|id| landed.contains(&id)) {
+            return true;
+        }
+
+        let Some(walk) = walk_range(self.repo, tip, base) else {
+            return false;
+        };
+        let mut any = false;
+        for oid in walk {
+            let Ok(commit) = self.repo.find_commit(oid) else {
+                return false;
+            };
+            match commit_patch_id(self.repo, &commit) {
+                Some(id) if landed.contains(&id) => any = true,
+                Some(_) => return false,
+                // merges and empty commits carry no changes of their own
+                None => {}
+            }
+        }
+        any
+    }
+}
+
+/// The commits reachable from `tip` but not from `base`.
+fn walk_range(repo: &Repository, tip: Oid, base: Oid) -> Option<impl Iterator<Item = Oid> + '_> {
+    let mut walk = repo.revwalk().ok()?;
+    walk.set_sorting(Sort::TOPOLOGICAL).ok()?;
+    walk.push(tip).ok()?;
+    walk.hide(base).ok()?;
+    Some(walk.filter_map(Result::ok))
+}
+
+/// The patch id of the changes `commit` makes to its parent, unless it is a
+/// merge.
+fn commit_patch_id(repo: &Repository, commit: &Commit) -> Option<Oid> {
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        1 => Some(commit.parent(0).ok()?.tree().ok()?),
+        _ => return None,
+    };
+    patch_id(repo, parent_tree.as_ref(), &commit.tree().ok()?)
+}
+
+/// The patch id of the changes from `old` to `new`, if there are any.
+fn patch_id(repo: &Repository, old: Option<&git2::Tree>, new: &git2::Tree) -> Option<Oid> {
+    let diff = repo.diff_tree_to_tree(old, Some(new), None).ok()?;
+    if diff.deltas().len() == 0 {
+        return None;
+    }
+    diff.patchid(None).ok()
+}
+
+/// Decide for each local branch whether its tip, or else its changes, are
+/// already contained in `integration`, in which case it is deleted.
+///
+/// In a shallow clone, history is truncated, so ancestry can't be established
+/// and every branch is retained. Likewise if the integration branch was ever
+/// rewritten.
+pub(crate) fn merged_branches(
+    repo: &Repository,
+    integration: &Branch,
+    considered: &dyn Fn(&str) -> bool,
+    logger: &slog::Logger,
+) -> Result<Vec<Decision>, FatalError> {
+    let integration_name = integration
+        .name()
+        .context("get integration branch name")?
+        .ok_or(FatalError::BranchNameNotUtf8)?;
+    let integration_tip = integration
+        .get()
+        .peel_to_commit()
+        .context("resolve integration branch tip")?
+        .id();
+
+    let shallow = repo.is_shallow();
+    if shallow {
+        slog::warn!(
+            logger, "repository is a shallow clone; ancestry checks are disabled and no branch will be deleted";
+            "hint" => "run `git fetch --unshallow` to enable them",
+        );
+    }
+
+    // the remote-tracking branch records what was pushed, not what was done
+    // locally meanwhile
+    let upstream = integration.upstream().ok();
+    let rewritten = upstream
+        .as_ref()
+        .unwrap_or(integration)
+        .get()
+        .name()
+        .is_some_and(|refname| was_rewritten(repo, refname));
+    if rewritten {
+        slog::warn!(
+            logger, "integration branch was rebased or force-pushed; ancestry checks are disabled and no branch will be deleted";
+            "integration branch" => integration_name,
+            "hint" => "add a GitHub remote so that pull requests can be consulted instead",
+        );
+    }
+
+    let mut patches = (!is_partial_clone(repo)).then(|| PatchIndex::new(repo, integration_tip));
+    let mut decisions = Vec::new();
+    for (branch, _branch_type) in repo
+        .branches(Some(BranchType::Local))
+        .context("list local branches")?
+        .filter_map(Result::ok)
+    {
+        let Some(branch_name) = branch.name().ok().flatten() else {
+            continue;
+        };
+        if branch_name == integration_name || !considered(branch_name) {
+            continue;
+        }
+        let branch_name_small = SmallStr::from_str(branch_name);
+        if shallow {
+            slog::debug!(logger, "retaining branch"; "branch name" => branch_name, "reason" => "shallow clone");
+            decisions.push(Decision::retain(branch_name_small, Reason::ShallowClone));
+            continue;
+        }
+        if rewritten {
+            slog::debug!(logger, "retaining branch"; "branch name" => branch_name, "reason" => "integration branch rewritten");
+            decisions.push(Decision::retain(
+                branch_name_small,
+                Reason::IntegrationRewritten,
+            ));
+            continue;
+        }
+        let Ok(tip) = branch.get().peel_to_commit().map(|commit| commit.id()) else {
+            continue;
+        };
+
+        if contains(repo, integration_tip, tip)? {
+            slog::info!(logger, "deleting branch merged into integration branch"; "branch name" => branch_name);
+            decisions.push(Decision::delete(branch_name_small, Confidence::High));
+        } else if patches
+            .as_mut()
+            .is_some_and(|patches| patches.squashed(tip))
+        {
+            slog::info!(logger, "deleting branch squash-merged into integration branch"; "branch name" => branch_name);
+            decisions.push(Decision::delete(branch_name_small, Confidence::High));
+        } else {
+            slog::debug!(logger, "retaining branch"; "branch name" => branch_name);
+            decisions.push(Decision::retain(branch_name_small, Reason::NotMerged));
+        }
+    }
+
+    Ok(decisions)
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Signature;
+
+    use super::*;
+    use crate::testing::scratch_repo;
+
+    /// Commit `files` on top of `parent`, outside of any branch.
+    fn commit(repo: &Repository, parent: Option<Oid>, files: &[(&str, &str)]) -> Oid {
+        let parent = parent.map(|parent| repo.find_commit(parent).unwrap());
+        let base_tree = parent.as_ref().map(|parent| parent.tree().unwrap());
+        let mut tree = repo.treebuilder(base_tree.as_ref()).unwrap();
+        for (path, contents) in files {
+            let blob = repo.blob(contents.as_bytes()).unwrap();
+            tree.insert(path, blob, 0o100644).unwrap();
+        }
+        let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(None, &signature, &signature, "commit", &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn rewinding_is_no_rewrite() {
+        let (dir, repo) = scratch_repo("rewritten");
+        let base = repo.refname_to_id("refs/heads/main").unwrap();
+        crate::testing::commit(&repo, "refs/heads/main", "merge feature");
+        repo.reference("refs/heads/main", base, true, "reset: moving to HEAD~1")
+            .unwrap();
+        assert!(!was_rewritten(&repo, "refs/heads/main"));
+
+        let unrelated = commit(&repo, None, &[("x", "x\n")]);
+        repo.reference("refs/heads/main", unrelated, true, "forced")
+            .unwrap();
+        assert!(was_rewritten(&repo, "refs/heads/main"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_squash_merged_branches() {
+        let dir = std::env::temp_dir().join(format!("git-clean-squash-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let base = commit(&repo, None, &[("a", "a\n")]);
+        let feature = commit(&repo, Some(base), &[("b", "b\n")]);
+        let feature = commit(&repo, Some(feature), &[("c", "c\n")]);
+        let unfinished = commit(&repo, Some(feature), &[("d", "d\n")]);
+        let main = commit(&repo, Some(base), &[("b", "b\n"), ("c", "c\n")]);
+        let main = commit(&repo, Some(main), &[("e", "e\n")]);
+
+        let mut patches = PatchIndex::new(&repo, main);
+        assert!(patches.squashed(feature));
+        assert!(!patches.squashed(unfinished));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+//! Branches kept under `refs/archive/` instead of being deleted outright.
+//!
+//! With [`Options::archive`](crate::Options::archive), each deleted branch
+//! is first copied to `refs/archive/<branch>/<run id>`, in the same
+//! transaction, and given the branch's reflog. A branch deleted again under
+//! the same name is archived next to the earlier one, never over it. The
+//! archived refs keep their commits from `git gc`, and don't show up as
+//! branches, so [`restore`] can bring the latest back however long ago it
+//! was.
+
+use std::path::Path;
+
+use git2::Repository;
+
+use crate::{
+    ancestry, error::ContextErr, guard::DeletionGuard, journal, logging, preflight, FatalError,
+};
+
+pub(crate) const ARCHIVE_PREFIX: &str = "refs/archive/";
+
+/// Where to archive `branch_name` when run `run_id` deletes it: a name no
+/// other archive has, even one of a run in the same second.
+pub(crate) fn unused_refname(repo: &Repository, branch_name: &str, run_id: &str) -> String {
+    let base = format!("{ARCHIVE_PREFIX}{branch_name}/{run_id}");
+    std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{base}-{n}")))
+        .find(|refname| repo.find_reference(refname).is_err())
+        .expect("some suffix is unused")
+}
+
+/// The archives of `branch_name`, oldest first.
+fn archives_of(repo: &Repository, branch_name: &str) -> Vec<String> {
+    let prefix = format!("{ARCHIVE_PREFIX}{branch_name}/");
+    let mut archives = archived_refs(repo)
+        .into_iter()
+        // those of `<branch>/<more>` are another branch's
+        .filter(|refname| {
+            refname
+                .strip_prefix(&prefix)
+                .is_some_and(|run_id| !run_id.contains('/'))
+        })
+        .collect::<Vec<_>>();
+    // run ids sort by time
+    archives.sort_unstable();
+    archives
+}
+
+/// Restore the latest archive of `branch_name` in the repository at `path`,
+/// returning the commit it points to.
+///
+/// The archived ref is removed once the branch exists again; any earlier
+/// archives are kept. A dry run only says so.
+pub fn restore(
+    path: impl AsRef<Path>,
+    branch_name: &str,
+    dry_run: bool,
+) -> Result<String, FatalError> {
+    let logger = logging::logger();
+    let repo = ancestry::open_repository(path)?;
+    let archives = archives_of(&repo, branch_name);
+    let Some(archived) = archives.last() else {
+        return Err(FatalError::NotArchived {
+            branch_name: branch_name.to_owned(),
+            archived: archived_branches(&repo),
+        });
+    };
+    let oid = repo
+        .refname_to_id(archived)
+        .context(format!("resolve {archived}"))?;
+    let commit = repo.find_commit(oid).context("find archived commit")?;
+    let Some(guard) = DeletionGuard::new(dry_run) else {
+        slog::info!(logger, "would restore archived branch"; "branch name" => branch_name, "id" => %oid);
+        return Ok(oid.to_string());
+    };
+    let mutator = guard.mutator(&repo);
+    // an existing branch of the same name is left alone
+    mutator
+        .branch(branch_name, &commit, false)
+        .context(format!("restore branch {branch_name}"))?;
+    let reflog = journal::reflog_path(&preflight::common_dir(&repo), archived);
+    journal::prepend_reflog(&mutator, &reflog, &format!("refs/heads/{branch_name}"))?;
+    mutator
+        .delete_reference(archived)
+        .context(format!("remove {archived}"))?;
+    if archives.len() > 1 {
+        slog::info!(
+            logger, "earlier archives of the branch are kept";
+            "branch name" => branch_name,
+            "archives" => archives.len() - 1,
+        );
+    }
+    slog::info!(logger, "restored archived branch"; "branch name" => branch_name, "id" => %oid);
+    Ok(oid.to_string())
+}
+
+/// Every archived ref.
+fn archived_refs(repo: &Repository) -> Vec<String> {
+    let Ok(mut references) = repo.references_glob(&format!("{ARCHIVE_PREFIX}*")) else {
+        return Vec::new();
+    };
+    references
+        .names()
+        .filter_map(Result::ok)
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The names of the archived branches, sorted.
+fn archived_branches(repo: &Repository) -> Vec<String> {
+    let mut names = archived_refs(repo)
+        .iter()
+        .filter_map(|refname| refname.strip_prefix(ARCHIVE_PREFIX))
+        .filter_map(|archived| archived.rsplit_once('/'))
+        .map(|(branch_name, _)| branch_name.to_owned())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
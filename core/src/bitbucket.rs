@@ -0,0 +1,191 @@
+//! Looking up pull requests on Bitbucket Cloud, for repositories hosted there.
+//!
+//! Only what the cleaning policy needs is supported: the main branch, and the
+//! pull requests whose source is a branch. A repository, project or workspace
+//! access token is taken from `BITBUCKET_TOKEN`; without one, only public
+//! repositories can be read.
+
+use http::Request;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{policy::PullRequestFacts, FatalError, SmallStr};
+
+const API: &str = "https://api.bitbucket.org/2.0";
+const TOKEN_VAR: &str = "BITBUCKET_TOKEN";
+
+/// A repository on Bitbucket Cloud.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Repository {
+    pub workspace: String,
+    pub slug: String,
+}
+
+impl Repository {
+    /// Recognize a remote url as pointing at bitbucket.org.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let (host, path) = crate::forge::split_remote_url(url)?;
+        if host != "bitbucket.org" {
+            return None;
+        }
+        let (workspace, slug) = path.split_once('/')?;
+        (!workspace.is_empty() && !slug.is_empty() && !slug.contains('/')).then(|| Self {
+            workspace: workspace.to_owned(),
+            slug: slug.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryInfo {
+    mainbranch: Option<BranchInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchInfo {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    id: u64,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Paginated<T> {
+    values: Vec<T>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error: ErrorMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+/// A client for one repository's API.
+pub(crate) struct Bitbucket {
+    /// The repository's API endpoint.
+    base: String,
+    token: Option<String>,
+}
+
+impl Bitbucket {
+    pub fn new(repository: &Repository) -> Self {
+        let token = std::env::var(TOKEN_VAR)
+            .ok()
+            .filter(|token| !token.is_empty());
+        if let Some(token) = &token {
+            crate::redact::register(token);
+        }
+        Self {
+            base: format!(
+                "{API}/repositories/{}/{}",
+                utf8_percent_encode(&repository.workspace, NON_ALPHANUMERIC),
+                utf8_percent_encode(&repository.slug, NON_ALPHANUMERIC)
+            ),
+            token,
+        }
+    }
+
+    /// GET `url`, which is absolute, since pages link to the next one.
+    async fn get<T: DeserializeOwned>(&self, url: &str, context: &str) -> Result<T, FatalError> {
+        let error = |status: Option<u16>, message: String| FatalError::Bitbucket {
+            context: context.to_owned(),
+            status,
+            message,
+        };
+
+        let mut request = Request::get(url);
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {token}"));
+        }
+        let request = request
+            .body(String::new())
+            .map_err(|err| error(None, err.to_string()))?;
+        let (status, body) = crate::http::send(request)
+            .await
+            .map_err(|err| error(None, err.to_string()))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ErrorBody>(&body)
+                .map(|body| body.error.message)
+                .unwrap_or_else(|_| status.to_string());
+            return Err(error(Some(status.as_u16()), message));
+        }
+        serde_json::from_slice(&body).map_err(|err| error(None, err.to_string()))
+    }
+
+    pub async fn default_branch(&self) -> Result<Option<SmallStr>, FatalError> {
+        let info: RepositoryInfo = self.get(&self.base, "get repository").await?;
+        Ok(info
+            .mainbranch
+            .map(|branch| SmallStr::from_string(branch.name)))
+    }
+
+    /// All pull requests whose source is `branch_name`, in any state.
+    ///
+    /// Pull requests from forks are included: their source branch may share
+    /// the name, just as on GitHub.
+    pub async fn pull_requests(
+        &self,
+        branch_name: &str,
+    ) -> Result<Vec<PullRequestFacts>, FatalError> {
+        // the API only lists open pull requests unless asked for each state
+        let query = format!("source.branch.name = \"{}\"", quote(branch_name));
+        let mut next = Some(format!(
+            "{}/pullrequests?state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED&pagelen=50&fields=values.id,values.state,next&q={}",
+            self.base,
+            utf8_percent_encode(&query, NON_ALPHANUMERIC)
+        ));
+
+        let mut facts = Vec::new();
+        while let Some(url) = next {
+            let page: Paginated<PullRequest> =
+                self.get(&url, "list pull requests for branch").await?;
+            facts.extend(page.values.into_iter().map(|pr| PullRequestFacts {
+                number: pr.id,
+                open: pr.state == "OPEN",
+                merged: Some(pr.state == "MERGED"),
+            }));
+            next = page.next;
+        }
+        Ok(facts)
+    }
+}
+
+/// Escape `value` for use in a quoted string of Bitbucket's query language.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_bitbucket_urls() {
+        let repository = |workspace: &str, slug: &str| {
+            Some(Repository {
+                workspace: workspace.into(),
+                slug: slug.into(),
+            })
+        };
+        assert_eq!(
+            Repository::from_url("git@bitbucket.org:team/project.git"),
+            repository("team", "project")
+        );
+        assert_eq!(
+            Repository::from_url("https://someone@bitbucket.org/team/project.git"),
+            repository("team", "project")
+        );
+        assert_eq!(
+            Repository::from_url("git@github.com:coriolinus/git-clean.git"),
+            None
+        );
+    }
+}
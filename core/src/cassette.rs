@@ -0,0 +1,259 @@
+//! Recording a run's inputs, and replaying them.
+//!
+//! A recorded run keeps how many tokens it had, the local branches with their
+//! previous names, and every response GitHub gave. Replaying it serves those responses instead of asking GitHub again,
+//! so the same decisions are reached long after the branches and pull requests
+//! have moved on.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http::{Request, Response, StatusCode};
+use hyper::Body;
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+
+use crate::{FatalError, SmallStr};
+
+const RUN_FILE: &str = "run.json";
+const BRANCHES_FILE: &str = "branches.json";
+const RESPONSES_FILE: &str = "responses.jsonl";
+/// Where a recording keeps the snapshot of its decisions, for comparison.
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+fn io_error(context: &str, path: &Path) -> impl FnOnce(std::io::Error) -> FatalError {
+    let context = format!("{context} {}", path.display());
+    move |inner| FatalError::Cassette { context, inner }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Recorded {
+    method: String,
+    /// Path and query; the host is always GitHub's API.
+    uri: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Recorded {
+    fn key(method: &str, uri: &str) -> String {
+        format!("{method} {uri}")
+    }
+
+    fn into_response(self) -> Response<Body> {
+        let mut response = Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name, value);
+        }
+        response
+            .body(Body::from(self.body))
+            .unwrap_or_else(|_| Response::new(Body::empty()))
+    }
+}
+
+/// Local branches, each with the names it previously had.
+pub(crate) type Branches = Vec<(SmallStr, Vec<SmallStr>)>;
+
+/// Facts about the run itself.
+#[derive(Debug, Serialize, Deserialize)]
+struct Run {
+    /// How many tokens were configured; this decides which requests are made.
+    tokens: usize,
+}
+
+enum Mode {
+    Record(Mutex<File>),
+    /// Recorded responses by request, in the order they were given.
+    Replay(Mutex<HashMap<String, VecDeque<Recorded>>>),
+}
+
+/// A directory holding a run's recorded inputs.
+#[derive(Clone)]
+pub(crate) struct Cassette {
+    dir: PathBuf,
+    tokens: usize,
+    mode: Arc<Mode>,
+}
+
+impl Cassette {
+    /// Start recording into `dir`, creating it if need be.
+    pub fn record(dir: &Path, tokens: usize) -> Result<Self, FatalError> {
+        std::fs::create_dir_all(dir).map_err(io_error("create", dir))?;
+        let path = dir.join(RUN_FILE);
+        let file = File::create(&path).map_err(io_error("create", &path))?;
+        serde_json::to_writer_pretty(file, &Run { tokens })
+            .map_err(|err| io_error("write", &path)(err.into()))?;
+
+        let path = dir.join(RESPONSES_FILE);
+        let file = File::create(&path).map_err(io_error("create", &path))?;
+        Ok(Self {
+            dir: dir.to_owned(),
+            tokens,
+            mode: Arc::new(Mode::Record(Mutex::new(file))),
+        })
+    }
+
+    /// Load a recording from `dir` for replay.
+    pub fn replay(dir: &Path) -> Result<Self, FatalError> {
+        let path = dir.join(RUN_FILE);
+        let file = File::open(&path).map_err(io_error("open", &path))?;
+        let run = serde_json::from_reader::<_, Run>(file)
+            .map_err(|err| io_error("parse", &path)(err.into()))?;
+
+        let path = dir.join(RESPONSES_FILE);
+        let file = File::open(&path).map_err(io_error("open", &path))?;
+        let mut responses = HashMap::<_, VecDeque<_>>::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(io_error("read", &path))?;
+            let recorded = serde_json::from_str::<Recorded>(&line)
+                .map_err(|err| io_error("parse", &path)(err.into()))?;
+            responses
+                .entry(Recorded::key(&recorded.method, &recorded.uri))
+                .or_default()
+                .push_back(recorded);
+        }
+        Ok(Self {
+            dir: dir.to_owned(),
+            tokens: run.tokens,
+            mode: Arc::new(Mode::Replay(Mutex::new(responses))),
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(*self.mode, Mode::Replay(_))
+    }
+
+    /// How many tokens the recorded run had.
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.dir.join(SNAPSHOT_FILE)
+    }
+
+    /// Record the local branches, or recall the recorded ones.
+    pub fn branches(&self, current: Branches) -> Result<Branches, FatalError> {
+        let path = self.dir.join(BRANCHES_FILE);
+        match &*self.mode {
+            Mode::Record(_) => {
+                let file = File::create(&path).map_err(io_error("create", &path))?;
+                serde_json::to_writer_pretty(file, &current)
+                    .map_err(|err| io_error("write", &path)(err.into()))?;
+                Ok(current)
+            }
+            Mode::Replay(_) => {
+                let file = File::open(&path).map_err(io_error("open", &path))?;
+                serde_json::from_reader(file).map_err(|err| io_error("parse", &path)(err.into()))
+            }
+        }
+    }
+}
+
+/// Passes requests through a cassette, if there is one.
+#[derive(Clone)]
+pub(crate) struct TapeLayer(pub Option<Cassette>);
+
+impl<S> Layer<S> for TapeLayer {
+    type Service = Tape<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Tape {
+            inner,
+            cassette: self.0.clone(),
+        }
+    }
+}
+
+/// Records responses as they pass through, or serves them from a recording.
+#[derive(Clone)]
+pub(crate) struct Tape<S> {
+    inner: S,
+    cassette: Option<Cassette>,
+}
+
+impl<S> Service<Request<String>> for Tape<S>
+where
+    S: Service<Request<String>, Response = Response<Body>, Error = hyper::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = hyper::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<String>) -> Self::Future {
+        let method = req.method().to_string();
+        let uri = req
+            .uri()
+            .path_and_query()
+            .map(|path| path.as_str().to_owned())
+            .unwrap_or_default();
+
+        let Some(cassette) = self.cassette.clone() else {
+            return Box::pin(self.inner.call(req));
+        };
+
+        if let Mode::Replay(responses) = &*cassette.mode {
+            let key = Recorded::key(&method, &uri);
+            let mut responses = responses.lock().unwrap_or_else(|err| err.into_inner());
+            let recorded = responses.get_mut(&key).and_then(|queue| {
+                // the last response to a request stands in for any repeats
+                if queue.len() > 1 {
+                    queue.pop_front()
+                } else {
+                    queue.front().cloned()
+                }
+            });
+            let response = match recorded {
+                Some(recorded) => recorded.into_response(),
+                None => {
+                    let mut response = Response::new(Body::from(format!(
+                        r#"{{"message":"no recorded response for {key}"}}"#
+                    )));
+                    *response.status_mut() = StatusCode::BAD_GATEWAY;
+                    response
+                }
+            };
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let response = self.inner.call(req);
+        Box::pin(async move {
+            let (parts, body) = response.await?.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+            let recorded = Recorded {
+                method,
+                uri,
+                status: parts.status.as_u16(),
+                headers: parts
+                    .headers
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                    })
+                    .collect(),
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+            if let (Mode::Record(file), Ok(line)) =
+                (&*cassette.mode, serde_json::to_string(&recorded))
+            {
+                let mut file = file.lock().unwrap_or_else(|err| err.into_inner());
+                // a recording with gaps still replays what it has
+                let _ = writeln!(file, "{line}");
+            }
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
@@ -0,0 +1,195 @@
+//! A pool of GitHub clients, one per configured token.
+//!
+//! Search requests are heavily rate limited per token. Spreading them across
+//! several tokens (e.g. a personal token and a bot token), and failing over
+//! when one runs dry, lets very large runs finish.
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http::Uri;
+use octocrab::Octocrab;
+
+use crate::{cassette::Cassette, pulls::FoundPr, sso, ErrorKind, FatalError};
+
+/// Sentinel budget for a client whose remaining budget we don't know.
+const UNKNOWN: usize = usize::MAX;
+
+struct Client {
+    octocrab: Octocrab,
+    /// Search requests this client may still make before `reset`.
+    remaining: AtomicUsize,
+    /// When the search budget resets, in seconds since the unix epoch.
+    reset: AtomicUsize,
+}
+
+impl Client {
+    fn has_budget(&self) -> bool {
+        if self.remaining.load(Ordering::Relaxed) > 0 {
+            return true;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs() as usize)
+            .unwrap_or_default();
+        if now >= self.reset.load(Ordering::Relaxed) {
+            self.remaining.store(UNKNOWN, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn spend(&self) {
+        let _ = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| {
+                (remaining != UNKNOWN).then(|| remaining.saturating_sub(1))
+            });
+    }
+
+    fn exhaust(&self) {
+        self.remaining.store(0, Ordering::Relaxed);
+    }
+}
+
+pub(crate) struct ClientPool {
+    clients: Vec<Client>,
+    next: AtomicUsize,
+}
+
+impl ClientPool {
+    /// Build one client per token for the API at `api`, or a single
+    /// anonymous client if there are no tokens.
+    ///
+    /// If `trace` is set, every request is summarized to it. If `cassette` is
+    /// set, every client records to or replays from it.
+    pub fn new(
+        tokens: &[String],
+        api: &Uri,
+        trace: Option<&slog::Logger>,
+        cassette: Option<&Cassette>,
+    ) -> Result<Self, FatalError> {
+        for token in tokens {
+            crate::redact::register(token);
+        }
+        let tokens = if tokens.is_empty() {
+            vec![None]
+        } else {
+            tokens.iter().map(|token| Some(token.as_str())).collect()
+        };
+        let clients = tokens
+            .into_iter()
+            .map(|token| {
+                Ok(Client {
+                    octocrab: crate::http::build_client(token, api, trace, cassette)?,
+                    remaining: AtomicUsize::new(UNKNOWN),
+                    reset: AtomicUsize::new(0),
+                })
+            })
+            .collect::<Result<_, FatalError>>()?;
+        Ok(Self {
+            clients,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// A pool of just `octocrab`, which a caller built.
+    pub fn from_octocrab(octocrab: Octocrab) -> Self {
+        Self {
+            clients: vec![Client {
+                octocrab,
+                remaining: AtomicUsize::new(UNKNOWN),
+                reset: AtomicUsize::new(0),
+            }],
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The client for everything except PR lookups.
+    pub fn primary(&self) -> &Octocrab {
+        &self.clients[0].octocrab
+    }
+
+    /// Fetch each client's current search budget.
+    ///
+    /// Only worth doing when there is more than one client to choose from.
+    pub async fn fetch_budgets(&self, logger: &slog::Logger) {
+        if self.clients.len() < 2 {
+            return;
+        }
+        for (idx, client) in self.clients.iter().enumerate() {
+            match client.octocrab.ratelimit().get().await {
+                Ok(limits) => {
+                    let search = limits.resources.search;
+                    client.remaining.store(search.remaining, Ordering::Relaxed);
+                    client.reset.store(search.reset, Ordering::Relaxed);
+                    slog::debug!(
+                        logger, "token search budget";
+                        "token" => idx,
+                        "remaining" => search.remaining,
+                        "limit" => search.limit,
+                    );
+                }
+                Err(err) => {
+                    slog::warn!(logger, "failed to get token rate limit"; "token" => idx, "err" => %err);
+                }
+            }
+        }
+    }
+
+    /// Fail unless every token is SSO-authorized for the repository's
+    /// organization.
+    ///
+    /// Searches are spread across all of them, and one which isn't
+    /// authorized would quietly find no pull requests for its share.
+    pub async fn check_sso_authorized(
+        &self,
+        owner: &str,
+        repo_name: &str,
+        logger: &slog::Logger,
+    ) -> Result<(), FatalError> {
+        for (idx, client) in self.clients.iter().enumerate() {
+            let checked = sso::check_authorized(&client.octocrab, owner, repo_name).await;
+            if let Err(FatalError::SsoAuthorizationRequired { url }) = &checked {
+                slog::warn!(logger, "token is not authorized for SSO"; "token" => idx, "url" => url);
+            }
+            checked?;
+        }
+        Ok(())
+    }
+
+    /// Choose the next client with budget left, round-robin.
+    fn pick(&self) -> Option<&Client> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.clients.len())
+            .map(|offset| &self.clients[(start + offset) % self.clients.len()])
+            .find(|client| client.has_budget())
+    }
+
+    /// Get all PRs for a branch, failing over to another token if one hits
+    /// its rate limit.
+    pub async fn get_prs(
+        &self,
+        owner: &str,
+        repo_name: &str,
+        branch_name: &str,
+    ) -> Result<Vec<FoundPr>, FatalError> {
+        let mut last_err = None;
+        for _ in 0..self.clients.len() {
+            let Some(client) = self.pick() else {
+                break;
+            };
+            client.spend();
+            match crate::engine::get_prs(&client.octocrab, owner, repo_name, branch_name).await {
+                Err(err) if err.kind() == ErrorKind::RateLimited => {
+                    client.exhaust();
+                    last_err = Some(err);
+                }
+                result => return result,
+            }
+        }
+        Err(last_err.unwrap_or(FatalError::RateLimited))
+    }
+}
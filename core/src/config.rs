@@ -0,0 +1,584 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{secrets, Provider};
+
+/// The config format this build reads and writes.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Upgrades from each older config version, indexed by the version they
+/// upgrade from.
+///
+/// Each migration edits the raw table so that fields can be renamed or
+/// restructured without keeping the old shape around as a type.
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[
+    // 0 -> 1: the version field was introduced; nothing else changed
+    |_| {},
+    // 1 -> 2: tokens may be sealed; older versions would take them literally
+    |_| {},
+];
+
+/// Every key the current config format understands, for did-you-mean hints.
+const KNOWN_KEYS: &[&str] = &[
+    "version",
+    "personal_access_token",
+    "additional_tokens",
+    "encrypt_secrets",
+    "token_command",
+    "vault",
+    "aws_secrets_manager",
+    "api_base_url",
+    "protected_branches",
+    "repositories",
+];
+
+/// A key in the config file which git-clean does not recognize.
+///
+/// Usually a typo, which would otherwise quietly leave a setting at its default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    pub key: String,
+    pub suggestion: Option<&'static str>,
+}
+
+impl UnknownKey {
+    fn new(key: String) -> Self {
+        let suggestion = KNOWN_KEYS
+            .iter()
+            .map(|known| (strsim::damerau_levenshtein(&key, known), *known))
+            .filter(|(distance, _)| *distance <= 3)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known);
+        Self { key, suggestion }
+    }
+}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown config key `{}`", self.key)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, "; did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// The config format version; files without one predate versioning.
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub personal_access_token: String,
+    /// Further tokens to spread the rate limit across.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_tokens: Vec<String>,
+    /// Store tokens encrypted, under the key in [`secrets::key_path`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypt_secrets: bool,
+    /// Shell command printing the primary token, e.g. from a secret manager.
+    /// Takes precedence over `personal_access_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    /// A Vault secret holding the primary token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<VaultSource>,
+    /// An AWS Secrets Manager secret holding the primary token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws_secrets_manager: Option<AwsSecretSource>,
+    /// The GitHub Enterprise Server API the tokens belong to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base_url: Option<String>,
+    /// Names or glob patterns of branches never to consider for deletion,
+    /// such as `develop` or `release/*`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_branches: Vec<String>,
+    /// Repositories cleaned by `git-clean all`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<RegisteredRepository>,
+}
+
+/// A repository registered to be cleaned with the others, with settings of
+/// its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisteredRepository {
+    pub path: PathBuf,
+    /// The remote to look pull requests up by, if it has more than one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// The forge its remote is on, if its url doesn't say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// Names or glob patterns of branches never to delete in it, besides
+    /// those protected everywhere.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_branches: Vec<String>,
+}
+
+fn default_field() -> String {
+    "token".into()
+}
+
+/// Where in HashiCorp Vault the primary token is kept.
+///
+/// Only the KV version 2 secrets engine is supported. Vault's own token is
+/// taken from `VAULT_TOKEN`, or else from `~/.vault-token`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultSource {
+    /// The server's address; `VAULT_ADDR` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Where the KV engine is mounted.
+    #[serde(default = "VaultSource::default_mount")]
+    pub mount: String,
+    /// The secret's path within the mount.
+    pub path: String,
+    /// The secret's field holding the token.
+    #[serde(default = "default_field")]
+    pub field: String,
+}
+
+impl VaultSource {
+    fn default_mount() -> String {
+        "secret".into()
+    }
+}
+
+/// Where in AWS Secrets Manager the primary token is kept.
+///
+/// Credentials are taken from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+/// `AWS_SESSION_TOKEN`, as CI providers set them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwsSecretSource {
+    /// The secret's name or ARN.
+    pub secret_id: String,
+    /// `AWS_REGION` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// For secrets stored as JSON, the key holding the token. Without it, the
+    /// whole secret is the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mask = crate::redact::mask;
+        f.debug_struct("Config")
+            .field("version", &self.version)
+            .field("personal_access_token", &mask(&self.personal_access_token))
+            .field(
+                "additional_tokens",
+                &self
+                    .additional_tokens
+                    .iter()
+                    .map(|token| mask(token))
+                    .collect::<Vec<_>>(),
+            )
+            .field("encrypt_secrets", &self.encrypt_secrets)
+            .field("token_command", &self.token_command)
+            .field("vault", &self.vault)
+            .field("aws_secrets_manager", &self.aws_secrets_manager)
+            .field("api_base_url", &self.api_base_url)
+            .field("protected_branches", &self.protected_branches)
+            .field("repositories", &self.repositories)
+            .finish()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            personal_access_token: String::new(),
+            additional_tokens: Vec::new(),
+            encrypt_secrets: false,
+            token_command: None,
+            vault: None,
+            aws_secrets_manager: None,
+            api_base_url: None,
+            protected_branches: Vec::new(),
+            repositories: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Apply `f` to every token.
+    fn map_tokens(&self, f: impl Fn(&str) -> Result<String, Error>) -> Result<Self, Error> {
+        let map = |token: &str| {
+            if token.is_empty() {
+                Ok(String::new())
+            } else {
+                f(token)
+            }
+        };
+        Ok(Self {
+            personal_access_token: map(&self.personal_access_token)?,
+            additional_tokens: self
+                .additional_tokens
+                .iter()
+                .map(|token| map(token))
+                .collect::<Result<_, _>>()?,
+            ..self.clone()
+        })
+    }
+
+    /// Decrypt any sealed tokens, under the key at `key_path`.
+    fn unsealed(self, key_path: &Path) -> Result<Self, Error> {
+        let tokens = std::iter::once(&self.personal_access_token).chain(&self.additional_tokens);
+        if !tokens.into_iter().any(|token| secrets::is_sealed(token)) {
+            return Ok(self);
+        }
+        let key = secrets::load_key_at(key_path)?;
+        self.map_tokens(|token| {
+            if secrets::is_sealed(token) {
+                secrets::open(&key, token)
+            } else {
+                Ok(token.to_owned())
+            }
+        })
+    }
+
+    pub fn path() -> PathBuf {
+        dirs::config_dir()
+            .expect("platorm has a config dir")
+            .join("git-clean.toml")
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.save_at(Self::path())
+    }
+
+    /// Write the config to `path`.
+    ///
+    /// The file is replaced atomically, so a concurrent reader sees either the
+    /// old config or the new one, never a partial write.
+    pub fn save_at(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.save_with_key_at(path.as_ref(), &secrets::key_path())
+    }
+
+    /// Write the config to `path`, sealing its tokens under the key at
+    /// `key_path` if it is to be encrypted.
+    fn save_with_key_at(&self, path: &Path, key_path: &Path) -> Result<(), Error> {
+        let serialized = if self.encrypt_secrets {
+            let key = secrets::load_or_create_key_at(key_path)?;
+            let sealed = self.map_tokens(|token| secrets::seal(&key, token))?;
+            toml::to_string_pretty(&sealed)
+        } else {
+            toml::to_string_pretty(self)
+        }
+        .context("serialize config")?;
+        write_private(path, &serialized)
+    }
+
+    /// Load the config, change it, and save it again, holding a lock
+    /// throughout so that concurrent updates don't lose each other's changes.
+    pub fn update(change: impl FnOnce(&mut Self)) -> Result<(), Error> {
+        let mut lock_path = Self::path().into_os_string();
+        lock_path.push(".lock");
+        let lock = std::fs::File::create(lock_path).context("create config lock file")?;
+        lock.lock().context("lock config")?;
+
+        let mut config = Self::load_or_default()?;
+        change(&mut config);
+        config.save()
+        // the lock is released when `lock` is dropped
+    }
+
+    pub fn load() -> Result<Self, Error> {
+        Self::load_at(Self::path())
+    }
+
+    /// Load the config, upgrading it in place if it was written by an older
+    /// version of git-clean.
+    ///
+    /// The old file is kept alongside as `<name>.v<version>.bak`, with its
+    /// tokens sealed.
+    pub fn load_at(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::load_checked_at(path, false).map(|(config, _)| config)
+    }
+
+    pub fn load_checked(strict: bool) -> Result<(Self, Vec<UnknownKey>), Error> {
+        Self::load_checked_at(Self::path(), strict)
+    }
+
+    /// Load the config, also reporting any keys it does not recognize.
+    ///
+    /// In strict mode the first unknown key is an error instead.
+    pub fn load_checked_at(
+        path: impl AsRef<Path>,
+        strict: bool,
+    ) -> Result<(Self, Vec<UnknownKey>), Error> {
+        Self::load_checked_with_key_at(path.as_ref(), strict, &secrets::key_path())
+    }
+
+    /// Load the config at `path`, with its tokens sealed under the key at
+    /// `key_path`.
+    fn load_checked_with_key_at(
+        path: &Path,
+        strict: bool,
+        key_path: &Path,
+    ) -> Result<(Self, Vec<UnknownKey>), Error> {
+        let data = std::fs::read_to_string(path).context("read config data from file")?;
+        let mut table = toml::from_str::<toml::Table>(&data).context("deserialize config file")?;
+
+        let version = match table.get("version") {
+            None => 0,
+            Some(toml::Value::Integer(version)) => {
+                u32::try_from(*version).map_err(|_| Error::InvalidVersion)?
+            }
+            Some(_) => return Err(Error::InvalidVersion),
+        };
+        if version > CURRENT_VERSION {
+            return Err(Error::NewerVersion {
+                found: version,
+                supported: CURRENT_VERSION,
+            });
+        }
+        let migrate = version < CURRENT_VERSION;
+        let original = migrate.then(|| table.clone());
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut table);
+        }
+        table.insert("version".into(), i64::from(CURRENT_VERSION).into());
+
+        let mut unknown_keys = Vec::new();
+        let config = serde_ignored::deserialize::<_, _, Self>(toml::Value::Table(table), |key| {
+            unknown_keys.push(UnknownKey::new(key.to_string()));
+        })
+        .context("deserialize config file")?
+        .unsealed(key_path)?;
+        if strict {
+            if let Some(unknown_key) = unknown_keys.into_iter().next() {
+                return Err(Error::UnknownKey(unknown_key));
+            }
+            unknown_keys = Vec::new();
+        }
+        let Some(mut original) = original else {
+            return Ok((config, unknown_keys));
+        };
+
+        // the backup outlives any later `--encrypt-config`, so its tokens
+        // are always sealed
+        seal_table_tokens(&mut original, key_path)?;
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{version}.bak"));
+        let serialized = toml::to_string_pretty(&original).context("serialize config backup")?;
+        write_private(Path::new(&backup), &serialized)?;
+        config.save_with_key_at(path, key_path)?;
+
+        Ok((config, unknown_keys))
+    }
+
+    /// Load the config, or start afresh if there is none we can use.
+    ///
+    /// A config from a newer git-clean is still an error: overwriting it
+    /// would silently drop whatever settings it holds.
+    pub fn load_or_default() -> Result<Self, Error> {
+        match Self::load() {
+            Err(err @ Error::NewerVersion { .. }) => Err(err),
+            loaded => Ok(loaded.unwrap_or_default()),
+        }
+    }
+}
+
+/// Seal the tokens in a raw config table, under the key at `key_path`.
+fn seal_table_tokens(table: &mut toml::Table, key_path: &Path) -> Result<(), Error> {
+    let mut plaintext = Vec::new();
+    for (name, value) in table.iter_mut() {
+        let values = match (name.as_str(), value) {
+            ("personal_access_token", value) => vec![value],
+            ("additional_tokens", toml::Value::Array(tokens)) => tokens.iter_mut().collect(),
+            _ => continue,
+        };
+        plaintext.extend(values.into_iter().filter_map(|value| match value {
+            toml::Value::String(token) if !token.is_empty() && !secrets::is_sealed(token) => {
+                Some(token)
+            }
+            _ => None,
+        }));
+    }
+    if plaintext.is_empty() {
+        return Ok(());
+    }
+    let key = secrets::load_or_create_key_at(key_path)?;
+    for token in plaintext {
+        *token = secrets::seal(&key, token)?;
+    }
+    Ok(())
+}
+
+/// Atomically replace the file at `path` with `contents`, readable only by
+/// its owner.
+///
+/// A concurrent reader sees either the old file or the new one, never a
+/// partial write.
+fn write_private(path: &Path, contents: &str) -> Result<(), Error> {
+    use std::io::Write;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path).context("create config file")?;
+    writeln!(file, "{contents}").context("write config to file")?;
+    file.sync_all().context("flush config file")?;
+    std::fs::rename(&temp_path, path).context("replace config file")?;
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{context}")]
+    TomlSerialize {
+        context: String,
+        #[source]
+        inner: toml::ser::Error,
+    },
+    /// Only the parser's message is kept: its full error quotes the offending
+    /// line, which may well hold a token.
+    #[error("{context}: {message}")]
+    TomlDeserialize { context: String, message: String },
+    #[error("config file was written by a newer git-clean (format version {found}; this build supports up to {supported})")]
+    NewerVersion { found: u32, supported: u32 },
+    #[error("{0}")]
+    UnknownKey(UnknownKey),
+    #[error("{0}")]
+    Secret(String),
+    #[error("{0}")]
+    TokenSource(String),
+    #[error("config file version is not a non-negative integer")]
+    InvalidVersion,
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        inner: std::io::Error,
+    },
+}
+
+trait WithContext {
+    type Ok;
+    fn context(self, s: impl ToString) -> Result<Self::Ok, Error>;
+}
+
+impl<T> WithContext for Result<T, toml::ser::Error> {
+    type Ok = T;
+    fn context(self, s: impl ToString) -> Result<T, Error> {
+        self.map_err(|inner| Error::TomlSerialize {
+            context: s.to_string(),
+            inner,
+        })
+    }
+}
+
+impl<T> WithContext for Result<T, toml::de::Error> {
+    type Ok = T;
+
+    fn context(self, s: impl ToString) -> Result<T, Error> {
+        self.map_err(|inner| Error::TomlDeserialize {
+            context: s.to_string(),
+            message: inner.message().to_owned(),
+        })
+    }
+}
+
+impl<T> WithContext for Result<T, std::io::Error> {
+    type Ok = T;
+
+    fn context(self, s: impl ToString) -> Result<T, Error> {
+        self.map_err(|inner| Error::Io {
+            context: s.to_string(),
+            inner,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("git-clean-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scratch_config(name: &str, contents: &str) -> PathBuf {
+        let path = scratch_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn migrates_unversioned_config() {
+        let key_path = scratch_dir().join("migrate.key");
+        let path = scratch_config("unversioned.toml", "personal_access_token = \"abc\"\n");
+        let (config, _) = Config::load_checked_with_key_at(&path, false, &key_path).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.personal_access_token, "abc");
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".v0.bak");
+        let backup = std::fs::read_to_string(backup).unwrap();
+        assert!(
+            !backup.contains("abc"),
+            "backup holds the token in plaintext"
+        );
+        let (config, _) = Config::load_checked_with_key_at(&path, false, &key_path).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn rejects_newer_config() {
+        let path = scratch_config(
+            "newer.toml",
+            "version = 999\npersonal_access_token = \"abc\"\n",
+        );
+        assert!(matches!(
+            Config::load_at(&path),
+            Err(Error::NewerVersion { found: 999, .. })
+        ));
+    }
+
+    #[test]
+    fn seals_tokens() {
+        let key_path = scratch_dir().join("sealed.key");
+        let path = scratch_config("sealed.toml", "");
+        let config = Config {
+            personal_access_token: "abc".into(),
+            encrypt_secrets: true,
+            ..Config::default()
+        };
+        config.save_with_key_at(&path, &key_path).unwrap();
+
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("abc"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let (config, _) = Config::load_checked_with_key_at(&path, false, &key_path).unwrap();
+        assert_eq!(config.personal_access_token, "abc");
+    }
+
+    #[test]
+    fn suggests_known_keys() {
+        let path = scratch_config("typo.toml", "version = 2\npersonal_acess_token = \"abc\"\n");
+        assert!(matches!(
+            Config::load_checked_at(&path, true),
+            Err(Error::UnknownKey(UnknownKey {
+                suggestion: Some("personal_access_token"),
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+//! Asking before each deletion, for embedders who want a say in every branch.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use git2::{BranchType, Repository};
+
+use crate::{
+    report::{Confidence, Decision, Outcome, PrSummary, Reason},
+    FatalError, SmallStr,
+};
+
+/// A branch about to be deleted.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub branch_name: SmallStr,
+    pub confidence: Confidence,
+    /// The branch was created by a dependency bot.
+    pub bot: bool,
+    /// The pull requests found for the branch, if they were looked up.
+    pub pull_requests: Vec<PrSummary>,
+    /// When the branch's tip was committed.
+    pub last_commit: Option<SystemTime>,
+}
+
+/// What to do with a [`Candidate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    Delete,
+    /// Retain this branch, and carry on with the others.
+    Skip,
+    /// Delete nothing at all.
+    Abort,
+}
+
+/// Asked about each branch about to be deleted, one at a time.
+///
+/// Branches are only deleted once every one was asked about, so aborting
+/// leaves the repository as it was.
+#[derive(Clone)]
+pub struct ConfirmHook(Arc<dyn Fn(&Candidate) -> Confirmation + Send + Sync>);
+
+impl ConfirmHook {
+    pub fn new(confirm: impl Fn(&Candidate) -> Confirmation + Send + Sync + 'static) -> Self {
+        Self(Arc::new(confirm))
+    }
+}
+
+impl fmt::Debug for ConfirmHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConfirmHook")
+    }
+}
+
+/// Ask `hook` about each deletion among `decisions`, retaining the branches
+/// it skips.
+pub(crate) fn confirm(
+    repo: &Repository,
+    hook: &ConfirmHook,
+    decisions: &mut [Decision],
+    pull_requests: &HashMap<SmallStr, Vec<PrSummary>>,
+    logger: &slog::Logger,
+) -> Result<(), FatalError> {
+    for decision in decisions.iter_mut() {
+        let Outcome::Delete { confidence, bot } = decision.outcome else {
+            continue;
+        };
+        let candidate = Candidate {
+            branch_name: decision.branch_name.clone(),
+            confidence,
+            bot,
+            pull_requests: pull_requests
+                .get(&decision.branch_name)
+                .cloned()
+                .unwrap_or_default(),
+            last_commit: last_commit(repo, &decision.branch_name),
+        };
+        match (hook.0)(&candidate) {
+            Confirmation::Delete => {}
+            Confirmation::Skip => {
+                slog::info!(
+                    logger, "retaining branch after all";
+                    "branch name" => %decision.branch_name,
+                    "reason" => %Reason::Declined,
+                );
+                decision.outcome = Outcome::Retain(Reason::Declined);
+            }
+            Confirmation::Abort => return Err(FatalError::Aborted),
+        }
+    }
+    Ok(())
+}
+
+fn last_commit(repo: &Repository, branch_name: &str) -> Option<SystemTime> {
+    let commit = repo
+        .find_branch(branch_name, BranchType::Local)
+        .ok()?
+        .get()
+        .peel_to_commit()
+        .ok()?;
+    let seconds = u64::try_from(commit.time().seconds()).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
@@ -0,0 +1,224 @@
+//! Retire GitHub deployments and environments belonging to deleted branches.
+//!
+//! PR-preview setups commonly create one deployment environment per branch.
+//! Nothing removes those once the branch is gone, so the environment list
+//! grows without bound unless we clean up after ourselves.
+
+use http::StatusCode;
+use octocrab::{FromResponse, Octocrab};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+use crate::{error::ContextErr, guard::DeletionGuard, FatalError};
+
+const PER_PAGE: u8 = 100;
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    id: u64,
+    #[serde(rename = "ref")]
+    ref_: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Environment {
+    #[serde(default)]
+    protection_rules: Vec<IgnoredAny>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListDeployments<'a> {
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    ref_: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<&'a str>,
+    per_page: u8,
+    page: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateStatus {
+    state: &'static str,
+}
+
+/// Mark every deployment of `branch_name` inactive and, with
+/// `delete_environments`, delete the environment of the same name, if any.
+/// Without a `guard`, only say so.
+///
+/// An environment may be named like a branch without belonging to it, as
+/// `production` or `staging` often are, and deleting it takes its secrets and
+/// protection rules along. So only unprotected environments which nothing
+/// but this branch was ever deployed to are deleted.
+pub(crate) async fn clean_deployments(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+    branch_name: &str,
+    delete_environments: bool,
+    guard: Option<&DeletionGuard>,
+    logger: &slog::Logger,
+) -> Result<(), FatalError> {
+    let deployments_route = format!("/repos/{owner}/{repo_name}/deployments");
+    let deployments =
+        list_deployments(octocrab, &deployments_route, Some(branch_name), None).await?;
+
+    for deployment in deployments {
+        match guard {
+            Some(guard) => {
+                slog::info!(logger, "deactivating deployment"; "deployment" => deployment.id);
+                deactivate(octocrab, &deployments_route, deployment.id, guard).await?;
+            }
+            None => {
+                slog::info!(logger, "would deactivate deployment"; "deployment" => deployment.id)
+            }
+        }
+    }
+
+    if !delete_environments {
+        return Ok(());
+    }
+    let environment = utf8_percent_encode(branch_name, NON_ALPHANUMERIC);
+    let route = format!("/repos/{owner}/{repo_name}/environments/{environment}");
+    let response = octocrab
+        ._get(route.as_str())
+        .await
+        .context("look up environment for branch")?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(());
+    }
+    let response = octocrab::map_github_error(response)
+        .await
+        .context("look up environment for branch")?;
+    let found = Environment::from_response(response)
+        .await
+        .context("read environment for branch")?;
+    let deployments =
+        list_deployments(octocrab, &deployments_route, None, Some(branch_name)).await?;
+    if let Some(reason) = keep_environment(branch_name, &found, &deployments) {
+        slog::warn!(logger, "not deleting environment named after branch"; "reason" => reason);
+        return Ok(());
+    }
+    match guard {
+        Some(guard) => {
+            slog::info!(logger, "deleting environment");
+            delete_environment(octocrab, &route, guard).await?;
+        }
+        None => slog::info!(logger, "would delete environment"),
+    }
+
+    Ok(())
+}
+
+/// Why the environment named like `branch_name` doesn't belong to it alone,
+/// if it doesn't.
+fn keep_environment(
+    branch_name: &str,
+    environment: &Environment,
+    deployments: &[Deployment],
+) -> Option<&'static str> {
+    if !environment.protection_rules.is_empty() {
+        Some("it is protected")
+    } else if deployments
+        .iter()
+        .any(|deployment| deployment.ref_ != branch_name)
+    {
+        Some("other refs were deployed to it")
+    } else {
+        None
+    }
+}
+
+/// Every deployment of `ref_`, or to `environment`, however many pages they
+/// take.
+async fn list_deployments(
+    octocrab: &Octocrab,
+    deployments_route: &str,
+    ref_: Option<&str>,
+    environment: Option<&str>,
+) -> Result<Vec<Deployment>, FatalError> {
+    let mut deployments = Vec::new();
+    for page in 1.. {
+        let batch: Vec<Deployment> = octocrab
+            .get(
+                deployments_route,
+                Some(&ListDeployments {
+                    ref_,
+                    environment,
+                    per_page: PER_PAGE,
+                    page,
+                }),
+            )
+            .await
+            .context("list deployments")?;
+        let last = batch.len() < usize::from(PER_PAGE);
+        deployments.extend(batch);
+        if last {
+            break;
+        }
+    }
+    Ok(deployments)
+}
+
+async fn deactivate(
+    octocrab: &Octocrab,
+    deployments_route: &str,
+    id: u64,
+    _: &DeletionGuard,
+) -> Result<(), FatalError> {
+    let _: IgnoredAny = octocrab
+        .post(
+            format!("{deployments_route}/{id}/statuses"),
+            Some(&CreateStatus { state: "inactive" }),
+        )
+        .await
+        .context("deactivate deployment")?;
+    Ok(())
+}
+
+async fn delete_environment(
+    octocrab: &Octocrab,
+    route: &str,
+    _: &DeletionGuard,
+) -> Result<(), FatalError> {
+    let response = octocrab
+        ._delete(route, None::<&()>)
+        .await
+        .context("delete environment")?;
+    octocrab::map_github_error(response)
+        .await
+        .context("delete environment")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(ref_: &str) -> Deployment {
+        Deployment {
+            id: 1,
+            ref_: ref_.to_owned(),
+        }
+    }
+
+    #[test]
+    fn keeps_environments_not_the_branchs_alone() {
+        let unprotected: Environment = serde_json::from_str(r#"{"name": "preview"}"#).unwrap();
+        assert_eq!(keep_environment("preview", &unprotected, &[]), None);
+        assert_eq!(
+            keep_environment("preview", &unprotected, &[deployment("preview")]),
+            None
+        );
+        assert!(keep_environment(
+            "staging",
+            &unprotected,
+            &[deployment("staging"), deployment("main")]
+        )
+        .is_some());
+
+        let protected: Environment =
+            serde_json::from_str(r#"{"protection_rules": [{"type": "required_reviewers"}]}"#)
+                .unwrap();
+        assert!(keep_environment("production", &protected, &[]).is_some());
+    }
+}
@@ -0,0 +1,2560 @@
+//! Gathering facts about branches from git and GitHub, and acting on the
+//! decisions made about them.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use git2::{BranchType, Repository};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use http::Uri;
+use lazy_static::lazy_static;
+use octocrab::{Octocrab, Page};
+use regex::Regex;
+use slog::o;
+
+use crate::{
+    ancestry::{self, Landed},
+    archive,
+    bitbucket::{self, Bitbucket},
+    cassette::{Branches, Cassette},
+    clients::ClientPool,
+    confirm::{self, ConfirmHook},
+    deployments,
+    error::ContextErr,
+    forge::{ForgeProvider, Provider},
+    gitlab::{self, GitLab},
+    gone,
+    guard::{DeletionGuard, Mutator},
+    http::GITHUB_API,
+    journal, logging,
+    owners::Owners,
+    plan,
+    policy::{self, BranchFacts, Policy, PullRequestFacts, Snapshot},
+    preflight::{self, Obstacle},
+    progress::{self, ProgressEvent, ProgressHook},
+    pulls::{self, FoundPr, PullDetails},
+    references::{self, Mentions},
+    renames, report,
+    report::{BranchReport, CleanReport, Confidence, Decision, Outcome, PrSummary, Reason},
+    retarget, retry, reviews,
+    sampling::LogSampler,
+    siblings, stale_refs, unpushed, vetting, FatalError, SmallStr, Vetting,
+};
+
+/// The host of git remotes on the GitHub instance whose API is at `api`.
+pub(crate) fn git_host(api: &Uri) -> &str {
+    match api.host() {
+        Some("api.github.com") | None => "github.com",
+        Some(host) => host,
+    }
+}
+
+pub(crate) fn parse_git_url(url: &str, host: &str) -> Option<(SmallStr, SmallStr)> {
+    lazy_static! {
+        static ref SSH_RE: Regex =
+            Regex::new(r"^git@(?P<host>[^:]+):(?P<org>[-\w]+)/(?P<repo>[-\w]+)\.git$").unwrap();
+        static ref HTTP_RE: Regex =
+            Regex::new(r"^https://(?P<host>[^/]+)/(?P<org>[-\w]+)/(?P<repo>[-\w]+)\.git$").unwrap();
+    }
+
+    let captures = SSH_RE.captures(url).or_else(|| HTTP_RE.captures(url))?;
+    if captures.name("host")?.as_str() != host {
+        return None;
+    }
+    let org = SmallStr::from_str(captures.name("org")?.as_str());
+    let repo = SmallStr::from_str(captures.name("repo")?.as_str());
+
+    Some((org, repo))
+}
+
+/// Rewrite a GitHub remote url on `host` to point at a different repository,
+/// keeping its scheme.
+pub(crate) fn rewrite_git_url(
+    url: &str,
+    host: &str,
+    owner: &str,
+    repo_name: &str,
+) -> Option<String> {
+    if url.starts_with(&format!("git@{host}:")) {
+        Some(format!("git@{host}:{owner}/{repo_name}.git"))
+    } else if url.starts_with(&format!("https://{host}/")) {
+        Some(format!("https://{host}/{owner}/{repo_name}.git"))
+    } else {
+        None
+    }
+}
+
+/// What the GitHub API knows about a repository.
+pub(crate) struct RepoMetadata {
+    /// Current owner. Differs from the one we asked for if the repository was
+    /// transferred.
+    owner: SmallStr,
+    /// Current name. Differs from the one we asked for if the repository was
+    /// renamed.
+    repo_name: SmallStr,
+    pub default_branch: Option<SmallStr>,
+}
+
+impl RepoMetadata {
+    fn has_moved_from(&self, owner: &str, repo_name: &str) -> bool {
+        !self.owner.eq_ignore_ascii_case(owner) || !self.repo_name.eq_ignore_ascii_case(repo_name)
+    }
+}
+
+/// Get a repository's metadata.
+///
+/// GitHub redirects requests for renamed or transferred repositories, and
+/// octocrab follows the redirect, so this reports where the repository lives
+/// now.
+pub(crate) async fn get_repo_metadata(
+    octocrab: impl Deref<Target = Octocrab>,
+    owner: &str,
+    repo_name: &str,
+) -> Option<RepoMetadata> {
+    let repo = octocrab.repos(owner, repo_name).get().await.ok()?;
+    Some(RepoMetadata {
+        owner: repo
+            .owner
+            .map(|owner| SmallStr::from_string(owner.login))
+            .unwrap_or_else(|| SmallStr::from_str(owner)),
+        repo_name: SmallStr::from_string(repo.name),
+        default_branch: repo.default_branch.map(SmallStr::from_string),
+    })
+}
+
+/// The search query for pull requests whose head is `branch_name`.
+///
+/// The branch name is quoted, so that characters which mean something to the
+/// search syntax, such as `#`, `:` or parentheses, are taken literally; the
+/// client URL-encodes the whole query. GitHub's search ignores case, so `Fix`
+/// also finds pull requests from `fix`.
+pub(crate) fn pr_search_query(owner: &str, repo_name: &str, branch_name: &str) -> String {
+    let branch_name = branch_name.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("is:pr repo:{owner}/{repo_name} head:\"{branch_name}\"")
+}
+
+/// The first page of results for `query`, left as json to be read leniently.
+async fn search_prs(
+    octocrab: &Octocrab,
+    query: &str,
+    context: &str,
+) -> Result<Page<serde_json::Value>, FatalError> {
+    // Github API specifies a maximum of 100 items returned per page
+    octocrab
+        .get("/search/issues", Some(&[("q", query), ("per_page", "100")]))
+        .await
+        .context(context)
+}
+
+/// GitHub's search never returns more results than this.
+const MAX_SEARCH_RESULTS: usize = 1000;
+
+/// Every pull request whose head is `branch_name`.
+///
+/// Pages are followed by their `Link` header, and pull requests which turn up
+/// twice, as they may when results shift between pages, are listed once. Past
+/// the search's limit of 1000 results, as for a long-lived branch name reused
+/// for many pull requests, the open ones are searched for separately: only
+/// whether any is open matters, and those are few.
+pub(crate) async fn get_prs(
+    octocrab: impl Deref<Target = Octocrab>,
+    owner: &str,
+    repo_name: &str,
+    branch_name: &str,
+) -> Result<Vec<FoundPr>, FatalError> {
+    let query = pr_search_query(owner, repo_name, branch_name);
+    let first = search_prs(&octocrab, &query, "search for pull requests by branch").await?;
+    let (mut prs, complete) = all_results(&octocrab, first).await?;
+    if !complete {
+        let open = search_prs(
+            &octocrab,
+            &format!("{query} is:open"),
+            "search for open pull requests by branch",
+        )
+        .await?;
+        prs.extend(all_results(&octocrab, open).await?.0);
+    }
+    prs.sort_unstable_by_key(|pr| pr.number);
+    prs.dedup_by_key(|pr| pr.number);
+    Ok(prs)
+}
+
+/// The results on `page` and every page after it, and whether that is all
+/// there are.
+async fn all_results(
+    octocrab: &Octocrab,
+    mut page: Page<serde_json::Value>,
+) -> Result<(Vec<FoundPr>, bool), FatalError> {
+    let total = page.total_count.map(|total| total as usize);
+    let mut fetched = 0;
+    let mut seen = HashSet::new();
+    let mut prs = Vec::new();
+    loop {
+        fetched += page.items.len();
+        for pr in &page.items {
+            let pr = FoundPr::from_json(pr)?;
+            if seen.insert(pr.number) {
+                prs.push(pr);
+            }
+        }
+        // asking for a page past the limit is an error
+        if fetched >= MAX_SEARCH_RESULTS {
+            break;
+        }
+        match octocrab
+            .get_page::<serde_json::Value>(&page.next)
+            .await
+            .context("get next page of pull requests for a branch")?
+        {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+    let complete = total.is_none_or(|total| total <= fetched);
+    Ok((prs, complete))
+}
+
+/// Branches created by well-known dependency bots.
+pub const KNOWN_BOT_BRANCHES: &[&str] = &["dependabot/*", "renovate/*"];
+
+/// Settings which control a single cleaning run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Do not actually edit the repository.
+    pub dry_run: bool,
+    /// Deactivate the GitHub deployments of deleted branches.
+    pub clean_deployments: bool,
+    /// With [`Options::clean_deployments`], also delete environments named
+    /// after deleted branches, unless they are protected or other refs were
+    /// deployed to them.
+    pub delete_environments: bool,
+    /// Branch against which merged-ness is judged when the repository has no
+    /// remotes, or with [`Options::local_only`]. Defaults to the local branch
+    /// named like a remote's default branch, then `init.defaultBranch`, then
+    /// `main`, then `master`.
+    pub integration_branch: Option<String>,
+    /// Upload a report of the run to a secret GitHub gist.
+    pub gist: bool,
+    /// Refuse to delete anything unless the plan hash matches this one, as
+    /// printed by a dry run.
+    pub approve: Option<String>,
+    /// Only delete a branch if one of its closed pull requests was vetted
+    /// like this.
+    pub require_vetting: Option<Vetting>,
+    /// Only delete a branch if one of its pull requests was merged, rather
+    /// than closed without merging.
+    pub require_merged: bool,
+    /// Glob patterns of the branches to consider; if empty, all are.
+    ///
+    /// Other branches are neither looked up nor reported on.
+    pub include_branches: Vec<String>,
+    /// Glob patterns of branches never to consider, even if included, such
+    /// as `release/*`.
+    pub exclude_branches: Vec<String>,
+    /// Only consider the branches this owner owns, per the repository's
+    /// `.git-clean-owners`, such as `@payments-team`.
+    pub owner: Option<String>,
+    /// Glob patterns of bot-created branches.
+    ///
+    /// Bot branches whose pull requests are all closed are never worth
+    /// keeping, so they skip vetting and plan approval, and are counted
+    /// separately. Defaults to [`KNOWN_BOT_BRANCHES`].
+    pub bot_branches: Vec<String>,
+    /// Retain branches which are the head of an open PR on which we are a
+    /// requested reviewer.
+    pub keep_review_requests: bool,
+    /// If the repository was renamed or transferred, point the remote at its
+    /// new location.
+    pub fix_remote: bool,
+    /// Glob patterns of ref namespaces left behind by tools, to be cleaned
+    /// once stale. Nothing is cleaned if empty, the default.
+    ///
+    /// [`DEFAULT_STALE_REF_NAMESPACES`] is a good start.
+    pub stale_ref_namespaces: Vec<String>,
+    /// Refs in `stale_ref_namespaces` are stale once their commit is this old.
+    pub stale_ref_age: Duration,
+    /// If the remote default branch has changed, update the local default
+    /// branch, its tracking, and the remote HEAD to match.
+    pub retarget_default: bool,
+    /// Log a summary of every HTTP request made to GitHub.
+    pub trace_http: bool,
+    /// Write everything the policy was told about each branch to this file,
+    /// for replaying with [`Snapshot::decide`].
+    pub dump_snapshot: Option<PathBuf>,
+    /// Record the run's inputs into this directory, for `replay`.
+    pub record: Option<PathBuf>,
+    /// Replay the inputs recorded in this directory instead of asking GitHub.
+    ///
+    /// Nothing is modified, as if this were a dry run. The repository must
+    /// still have the recorded run's remote.
+    pub replay: Option<PathBuf>,
+    /// Only evaluate the branches listed in this file, if it exists, and
+    /// afterwards list there the branches which could not be evaluated.
+    pub retry_file: Option<PathBuf>,
+    /// Retain branches which are still mentioned in open issues or in the
+    /// repository's tracked files.
+    pub grep_references: bool,
+    /// Retain branches whose deletion we are less sure of than this, so that
+    /// unattended runs only delete what is certainly done with.
+    ///
+    /// At `medium`, closed pull requests are vetted for approval or checks
+    /// unless [`Options::require_vetting`] says otherwise.
+    pub min_confidence: Option<Confidence>,
+    /// Only evaluate this many branches, chosen at random, and estimate from
+    /// them how many of all the branches would be deleted.
+    ///
+    /// Nothing is modified, as if this were a dry run.
+    pub sample: Option<usize>,
+    /// Told about each branch as it is queued, decided and deleted.
+    pub on_progress: Option<ProgressHook>,
+    /// Asked before deleting each branch, unless this is a dry run. Skipped
+    /// branches are retained.
+    pub confirm: Option<ConfirmHook>,
+    /// Hosts of self-hosted GitLab instances, whose repositories are cleaned
+    /// based on merge requests. gitlab.com is always recognized.
+    pub gitlab_hosts: Vec<String>,
+    /// The GitHub API to use instead of github.com's, such as
+    /// `https://github.example.com/api/v3` for GitHub Enterprise Server.
+    /// Remotes on that host are then recognized.
+    pub api_base_url: Option<String>,
+    /// Associate pull requests with branches whose name differs from their
+    /// head only in case, as happens on case-insensitive filesystems.
+    ///
+    /// Otherwise each pull request's head is checked to be exactly the
+    /// branch's name, since GitHub's search ignores case.
+    pub case_insensitive_heads: bool,
+    /// Judge branches only by whether they are merged into the integration
+    /// branch, as for a repository without remotes, never asking a forge.
+    pub local_only: bool,
+    /// Instead, delete branches whose upstream was deleted from the remote and
+    /// pruned, never asking a forge.
+    pub gone: bool,
+    /// Delete even when that would leave only one local branch, which is
+    /// otherwise refused unless each deletion is confirmed.
+    pub allow_delete_all: bool,
+    /// Refuse to delete more than this many branches and stale refs in one
+    /// run, unless each deletion is confirmed.
+    pub max_delete: Option<usize>,
+    /// Only delete a branch with closed pull requests if its tip is the head
+    /// of one of them, or an ancestor of one, so that a branch reused after
+    /// its pull request closed is kept.
+    ///
+    /// Only GitHub's pull requests say what their heads were.
+    pub verify_pr_heads: bool,
+    /// Ignore pull requests closed longer ago than this, so that those of an
+    /// old branch of the same name don't doom one recreated since.
+    ///
+    /// Only GitHub's pull requests say when they were closed.
+    pub recent_prs_only: Option<Duration>,
+    /// Keep each deleted branch under `refs/archive/<branch>/<run id>`, from
+    /// where [`archive::restore`] can bring it back.
+    pub archive: bool,
+    /// Keep branches whose tip was committed less than this long ago,
+    /// whatever their pull requests say.
+    pub exclude_recent: Option<Duration>,
+    /// Log at most this many branches decided alike, such as retained for
+    /// unpushed commits, and then how many more there were. `None` logs
+    /// every branch.
+    pub log_sample: Option<usize>,
+    /// Look pull requests up by this remote, for repositories with more than
+    /// one; otherwise the only one is used.
+    pub remote: Option<String>,
+    /// Take the remote to be on this forge, whatever its url looks like.
+    pub provider: Option<Provider>,
+}
+
+/// How many branches decided alike are logged by default.
+pub const DEFAULT_LOG_SAMPLE: usize = 100;
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            clean_deployments: false,
+            delete_environments: false,
+            integration_branch: None,
+            gist: false,
+            approve: None,
+            require_vetting: None,
+            require_merged: false,
+            include_branches: Vec::new(),
+            exclude_branches: Vec::new(),
+            owner: None,
+            bot_branches: KNOWN_BOT_BRANCHES.iter().map(|&s| s.to_owned()).collect(),
+            keep_review_requests: false,
+            fix_remote: false,
+            stale_ref_namespaces: Vec::new(),
+            stale_ref_age: Duration::from_secs(30 * 24 * 60 * 60),
+            retarget_default: false,
+            trace_http: false,
+            dump_snapshot: None,
+            record: None,
+            replay: None,
+            retry_file: None,
+            grep_references: false,
+            min_confidence: None,
+            sample: None,
+            on_progress: None,
+            confirm: None,
+            gitlab_hosts: Vec::new(),
+            api_base_url: None,
+            case_insensitive_heads: false,
+            local_only: false,
+            gone: false,
+            allow_delete_all: false,
+            max_delete: None,
+            verify_pr_heads: true,
+            recent_prs_only: None,
+            archive: false,
+            exclude_recent: None,
+            log_sample: Some(DEFAULT_LOG_SAMPLE),
+            remote: None,
+            provider: None,
+        }
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, FatalError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|inner| FatalError::InvalidGlob {
+            pattern: pattern.clone(),
+            inner,
+        })?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|inner| FatalError::InvalidGlob {
+        pattern: patterns.join(", "),
+        inner,
+    })
+}
+
+/// Immutable state shared by every per-branch task in a single run.
+///
+/// Branch tasks each hold an `Arc` to this instead of cloning its fields, which
+/// matters on repositories with thousands of local branches.
+struct RunContext {
+    clients: Arc<ClientPool>,
+    owner: SmallStr,
+    repo_name: SmallStr,
+    default_branch: Option<SmallStr>,
+    require_vetting: Option<Vetting>,
+    policy: Policy,
+    bot_branches: GlobSet,
+    /// Heads of open PRs awaiting our review.
+    review_heads: HashSet<SmallStr>,
+    /// Where each branch is still mentioned, if that was looked up.
+    references: Mentions,
+    /// How branches reached the remote default branch, if that could be
+    /// checked. Branches which did not are left out.
+    landed: Option<HashMap<SmallStr, Landed>>,
+    case_insensitive_heads: bool,
+    verify_pr_heads: bool,
+    /// Pull requests closed before this are ignored.
+    closed_since: Option<SystemTime>,
+    on_progress: Option<ProgressHook>,
+    sampler: LogSampler,
+    logger: slog::Logger,
+}
+
+impl RunContext {
+    fn octocrab(&self) -> &Octocrab {
+        self.clients.primary()
+    }
+
+    fn is_default_branch(&self, branch_name: &str) -> bool {
+        self.default_branch
+            .as_ref()
+            .map(|default| default == branch_name)
+            .unwrap_or_default()
+    }
+
+    /// Those of `prs` which are open, or were closed recently enough.
+    fn recent(&self, mut prs: Vec<FoundPr>, logger: &slog::Logger) -> Vec<FoundPr> {
+        let Some(since) = self.closed_since else {
+            return prs;
+        };
+        prs.retain(|pr| {
+            // when it closed isn't known, it counts
+            let recent = pr.open
+                || pr
+                    .closed_at
+                    .is_none_or(|closed_at| SystemTime::from(closed_at) >= since);
+            if !recent {
+                slog::info!(logger, "ignoring pull request closed long ago"; "pr" => pr.number);
+            }
+            recent
+        });
+        prs
+    }
+
+    /// Those of `prs` whose head is `branch_name`, in the configured case
+    /// sensitivity.
+    ///
+    /// Each pull request is fetched, unless matching ignores case and neither
+    /// whether it was merged nor its head commit is needed.
+    async fn with_head(
+        &self,
+        prs: Vec<FoundPr>,
+        branch_name: &str,
+        logger: &slog::Logger,
+    ) -> Result<Vec<(FoundPr, Option<PullDetails>)>, FatalError> {
+        if self.case_insensitive_heads && !self.policy.require_merged && !self.verify_pr_heads {
+            // the search already matched heads ignoring case
+            return Ok(prs.into_iter().map(|pr| (pr, None)).collect());
+        }
+
+        let mut matching = Vec::with_capacity(prs.len());
+        for pr in prs {
+            let route = format!(
+                "/repos/{}/{}/pulls/{}",
+                self.owner, self.repo_name, pr.number
+            );
+            let pull: serde_json::Value = self
+                .octocrab()
+                .get(route, None::<&()>)
+                .await
+                .context("get head of pull request")?;
+            let pull = PullDetails::from_json(&pull);
+            match pull.head_ref.as_deref() {
+                // an unknown head is kept, as if matching ignored case
+                Some(head) if head != branch_name && !self.case_insensitive_heads => {
+                    slog::info!(
+                        logger, "ignoring pull request whose head differs in case";
+                        "pr" => pr.number,
+                        "head" => head,
+                    );
+                }
+                _ => matching.push((pr, Some(pull))),
+            }
+        }
+        Ok(matching)
+    }
+
+    /// Look up whatever else the policy needs to know about a branch.
+    ///
+    /// Also returns summaries of the pull requests found. On failure, hands
+    /// back the branch name along with the error.
+    async fn gather_facts(
+        &self,
+        mut facts: BranchFacts,
+        previous_names: &[SmallStr],
+        logger: &slog::Logger,
+    ) -> Result<(BranchFacts, Vec<PrSummary>), (SmallStr, FatalError)> {
+        if !self.policy.needs_pull_requests(&facts) {
+            return Ok((facts, Vec::new()));
+        }
+
+        let mut prs = Vec::new();
+        for name in std::iter::once(&facts.branch_name).chain(previous_names) {
+            if name != &facts.branch_name {
+                slog::debug!(logger, "also looking up prs for previous name"; "previous name" => %name);
+            }
+            let name_prs = match self
+                .clients
+                .get_prs(&self.owner, &self.repo_name, name)
+                .await
+            {
+                Ok(name_prs) => {
+                    self.with_head(self.recent(name_prs, logger), name, logger)
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            pulls::report_drift(logger);
+            match name_prs {
+                Ok(name_prs) => prs.extend(name_prs),
+                Err(err) => {
+                    slog::error!(logger, "failed to get prs for branch"; "err" => %err);
+                    return Err((facts.branch_name, err));
+                }
+            }
+        }
+        prs.sort_unstable_by_key(|(pr, _)| pr.number);
+        prs.dedup_by_key(|(pr, _)| pr.number);
+        facts.pull_requests = prs
+            .iter()
+            .map(|(pr, pull)| PullRequestFacts {
+                number: pr.number,
+                open: pr.open,
+                merged: pull.as_ref().and_then(|pull| pull.merged),
+            })
+            .collect();
+        let summaries = prs
+            .iter()
+            .map(|(pr, pull)| summarize(pr, pull.as_ref()))
+            .collect();
+        let prs = prs
+            .into_iter()
+            .map(|(pr, pull)| (pr.number, pull.and_then(|pull| pull.head_sha)))
+            .collect::<Vec<_>>();
+
+        if let Some(vetting) = self
+            .require_vetting
+            .filter(|_| self.policy.needs_vetting(&facts))
+        {
+            match vetting::is_vetted(vetting, self.octocrab(), &self.owner, &self.repo_name, &prs)
+                .await
+            {
+                Ok(vetted) => {
+                    if !vetted {
+                        slog::info!(
+                            logger, "no closed pull request was vetted";
+                            "require" => %vetting,
+                        );
+                    }
+                    facts.vetted = Some(vetted);
+                }
+                Err(err) => {
+                    slog::error!(logger, "failed to vet pull requests for branch"; "err" => %err);
+                    return Err((facts.branch_name, err));
+                }
+            }
+        }
+
+        Ok((facts, summaries))
+    }
+}
+
+/// What we know of a pull request from its search result, and from the pull
+/// request itself if it was fetched.
+fn summarize(pr: &FoundPr, pull: Option<&PullDetails>) -> PrSummary {
+    PrSummary {
+        number: pr.number,
+        open: pr.open,
+        title: pr.title.clone(),
+        url: pr.url.clone(),
+        author: pr.author.clone(),
+        closed_at: pr.closed_at.map(SystemTime::from),
+        merged_at: pull.and_then(|pull| pull.merged_at).map(SystemTime::from),
+        merged_by: pull.and_then(|pull| pull.merged_by.clone()),
+        head_sha: pull.and_then(|pull| pull.head_sha.clone()),
+    }
+}
+
+/// Clean up git branches.
+///
+/// For each local branch, it is in one of these states:
+///
+///   1. Not pushed to the remote.
+///   2. Pushed to the remote but 0 PRs created.
+///   3. Pushed to the remote with at least 1 PR created, and at least 1 PR is not closed.
+///   4. Pushed to the remote with at least 1 PR created, and all PRs are closed.
+///
+/// In states 1 - 3, we retain the branch: it is assumed to still be in development.
+/// However, in state 4, we delete the branch: it is no longer relevant.
+///
+/// Closing completed branches helps keep the local dev environment relevant.
+///
+/// Returns what was decided about each branch, and what became of it.
+///
+/// Nothing is logged unless a logger was set with
+/// [`set_logger`](crate::set_logger).
+pub async fn clean_branches(
+    path: impl AsRef<Path>,
+    options: &Options,
+    personal_access_tokens: Vec<String>,
+) -> Result<CleanReport, FatalError> {
+    clean_path(path, options, personal_access_tokens, logging::logger()).await
+}
+
+/// As [`clean_branches`], logging to `logger`.
+#[deprecated(
+    since = "1.1.0",
+    note = "set the logger once with `set_logger`, and call `clean_branches`"
+)]
+pub async fn clean_branches_logged(
+    path: impl AsRef<Path>,
+    options: &Options,
+    personal_access_tokens: Vec<String>,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    clean_path(path, options, personal_access_tokens, logger).await
+}
+
+async fn clean_path(
+    path: impl AsRef<Path>,
+    options: &Options,
+    personal_access_tokens: Vec<String>,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    let cassette = match (&options.replay, &options.record) {
+        (Some(dir), _) => Some(Cassette::replay(dir)?),
+        (None, Some(dir)) => Some(Cassette::record(dir, personal_access_tokens.len())?),
+        (None, None) => None,
+    };
+    let replaying = cassette.as_ref().is_some_and(Cassette::is_replay);
+    let mut options = options.clone();
+    let mut personal_access_tokens = personal_access_tokens;
+    match &cassette {
+        Some(cassette) if replaying => {
+            slog::info!(logger, "replaying recorded run; nothing will be modified");
+            options.dry_run = true;
+            options.fix_remote = false;
+            options.gist = false;
+            // replayed requests never leave the process, but must be made
+            // the same way as they were
+            personal_access_tokens = vec!["replay".to_owned(); cassette.tokens()];
+        }
+        Some(cassette) => {
+            options.dump_snapshot = options
+                .dump_snapshot
+                .or_else(|| Some(cassette.snapshot_path()));
+        }
+        None => {}
+    }
+    let authenticated = !personal_access_tokens.is_empty();
+    let http_logger = logger.new(o!("component" => "http"));
+    let api = api_base_uri(&options)?;
+    let clients = ClientPool::new(
+        &personal_access_tokens,
+        &api,
+        options.trace_http.then_some(&http_logger),
+        cassette.as_ref(),
+    )?;
+
+    let repo = ancestry::open_repository(path)?;
+    clean_repo(
+        &repo,
+        Arc::new(clients),
+        &api,
+        authenticated,
+        cassette,
+        &options,
+        &logger,
+    )
+    .await
+}
+
+/// Clean up the branches of each repository in `repositories`, one after
+/// another, as [`clean_branches`] would, sharing GitHub clients and so their
+/// rate limits.
+///
+/// Each repository comes with its own options, since git's config may differ
+/// between them; those whose API differs get clients of their own. One
+/// repository failing doesn't stop the rest, so each gets its own result, in
+/// the order given. `record` and `replay` are ignored with a warning.
+pub async fn clean_branches_many(
+    repositories: &[(PathBuf, Options)],
+    personal_access_tokens: Vec<String>,
+) -> Vec<Result<CleanReport, FatalError>> {
+    let logger = logging::logger();
+    let http_logger = logger.new(o!("component" => "http"));
+    let authenticated = !personal_access_tokens.is_empty();
+    let mut pools: HashMap<String, Arc<ClientPool>> = HashMap::new();
+    let mut reports = Vec::with_capacity(repositories.len());
+    for (path, options) in repositories {
+        let clean = async {
+            for (set, flag) in [
+                (options.record.is_some(), "record"),
+                (options.replay.is_some(), "replay"),
+            ] {
+                if set {
+                    slog::warn!(logger, "not supported with many repositories; ignoring"; "option" => flag);
+                }
+            }
+            let api = api_base_uri(options)?;
+            let clients = match pools.get(&api.to_string()) {
+                Some(clients) => Arc::clone(clients),
+                None => {
+                    let clients = Arc::new(ClientPool::new(
+                        &personal_access_tokens,
+                        &api,
+                        options.trace_http.then_some(&http_logger),
+                        None,
+                    )?);
+                    pools.insert(api.to_string(), Arc::clone(&clients));
+                    clients
+                }
+            };
+            let repo = ancestry::open_repository(path)?;
+            let logger = logger.new(o!("repository" => path.display().to_string()));
+            clean_repo(&repo, clients, &api, authenticated, None, options, &logger).await
+        };
+        reports.push(clean.await);
+    }
+    reports
+}
+
+/// Clean up the branches of an already-open repository, asking GitHub with
+/// `octocrab`.
+///
+/// As [`clean_branches`], for applications which already hold these
+/// handles and manage authentication themselves. The client is used as it
+/// is, so it is assumed to be authenticated, and `trace_http`, `record` and
+/// `replay` are ignored with a warning. `api_base_url` only serves to
+/// recognize the remote, and must match the client's.
+pub async fn clean_repository(
+    repo: &Repository,
+    octocrab: Octocrab,
+    options: &Options,
+) -> Result<CleanReport, FatalError> {
+    clean_open_repository(repo, octocrab, options, logging::logger()).await
+}
+
+/// As [`clean_repository`], logging to `logger`.
+#[deprecated(
+    since = "1.1.0",
+    note = "set the logger once with `set_logger`, and call `clean_repository`"
+)]
+pub async fn clean_repository_logged(
+    repo: &Repository,
+    octocrab: Octocrab,
+    options: &Options,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    clean_open_repository(repo, octocrab, options, logger).await
+}
+
+async fn clean_open_repository(
+    repo: &Repository,
+    octocrab: Octocrab,
+    options: &Options,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    for (set, flag) in [
+        (options.trace_http, "trace_http"),
+        (options.record.is_some(), "record"),
+        (options.replay.is_some(), "replay"),
+    ] {
+        if set {
+            slog::warn!(logger, "not supported with a caller's client; ignoring"; "option" => flag);
+        }
+    }
+    let api = api_base_uri(options)?;
+    clean_repo(
+        repo,
+        Arc::new(ClientPool::from_octocrab(octocrab)),
+        &api,
+        true,
+        None,
+        options,
+        &logger,
+    )
+    .await
+}
+
+/// The GitHub API to use, as configured.
+pub(crate) fn api_base_uri(options: &Options) -> Result<Uri, FatalError> {
+    options
+        .api_base_url
+        .as_deref()
+        .unwrap_or(GITHUB_API)
+        .parse::<Uri>()
+        .map_err(|_| {
+            FatalError::InvalidApiBaseUrl(options.api_base_url.clone().unwrap_or_default())
+        })
+}
+
+/// Clean up `repo`'s branches with GitHub clients which are ready to use.
+async fn clean_repo(
+    repo: &Repository,
+    clients: Arc<ClientPool>,
+    api: &Uri,
+    authenticated: bool,
+    cassette: Option<Cassette>,
+    options: &Options,
+    logger: &slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    let replaying = cassette.as_ref().is_some_and(Cassette::is_replay);
+    let mut options = options.clone();
+    if options.sample.is_some() {
+        options.dry_run = true;
+        options.gist = false;
+    }
+    let options = &options;
+    let guard = DeletionGuard::new(options.dry_run);
+    let mutator = guard.as_ref().map(|guard| guard.mutator(repo));
+    let logger = logger.clone();
+    let octocrab = clients.primary();
+
+    let remotes = repo.remotes().context("list remotes")?;
+    if options.local_only || options.gone {
+        return clean_branches_offline(repo, offline_mode(options), options, &logger);
+    }
+    let remote_name = match &options.remote {
+        Some(name) => remotes
+            .iter()
+            .flatten()
+            .find(|remote| remote == name)
+            .ok_or_else(|| FatalError::NoSuchRemote(name.clone()))?,
+        None if remotes.is_empty() => {
+            return clean_branches_offline(repo, "repository has no remotes", options, &logger);
+        }
+        None if remotes.len() != 1 => {
+            return Err(FatalError::WrongRemoteCount(remotes.len()));
+        }
+        None => remotes.get(0).ok_or(FatalError::InexpressableRemote)?,
+    };
+    let remote = repo
+        .find_remote(remote_name)
+        .context("get remote by name")?;
+    slog::trace!(logger, "got remote"; "name" => remote_name);
+
+    let remote_url = remote.url().ok_or(FatalError::RemoteUrlNotUtf8)?;
+    // a remote said to be on GitLab is, whatever its host
+    let mut gitlab_hosts = options.gitlab_hosts.clone();
+    if options.provider == Some(Provider::GitLab) {
+        gitlab_hosts
+            .extend(crate::forge::split_remote_url(remote_url).map(|(host, _)| host.to_owned()));
+    }
+    let project = gitlab::Project::from_url(remote_url, &gitlab_hosts)
+        .filter(|_| matches!(options.provider, None | Some(Provider::GitLab)));
+    let repository = bitbucket::Repository::from_url(remote_url)
+        .filter(|_| matches!(options.provider, None | Some(Provider::Bitbucket)));
+    match options.provider {
+        Some(provider @ Provider::GitLab) if project.is_none() => {
+            return Err(FatalError::RemoteUrlNotProvider(provider));
+        }
+        Some(provider @ Provider::Bitbucket) if repository.is_none() => {
+            return Err(FatalError::RemoteUrlNotProvider(provider));
+        }
+        _ => {}
+    }
+    if let Some(project) = project {
+        slog::info!(
+            logger, "remote is on GitLab; using merge requests";
+            "host" => &project.host,
+            "project" => &project.path,
+        );
+        let gitlab = GitLab::new(&project);
+        return clean_branches_with_forge(repo, Some(remote_name), &gitlab, options, &logger).await;
+    }
+    if let Some(repository) = repository {
+        slog::info!(
+            logger, "remote is on Bitbucket; using its pull requests";
+            "workspace" => &repository.workspace,
+            "repository" => &repository.slug,
+        );
+        let bitbucket = Bitbucket::new(&repository);
+        return clean_branches_with_forge(repo, Some(remote_name), &bitbucket, options, &logger)
+            .await;
+    }
+    let (mut owner, mut repo_name) =
+        parse_git_url(remote_url, git_host(api)).ok_or(FatalError::RemoteUrlNotGithub)?;
+    slog::trace!(logger, "parsed url"; "owner" => %owner, "repo" => %repo_name);
+
+    if authenticated {
+        clients
+            .check_sso_authorized(&owner, &repo_name, &logger)
+            .await?;
+    }
+
+    let metadata = get_repo_metadata(octocrab, &owner, &repo_name).await;
+    let default_branch = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.default_branch.clone());
+    if let Some(metadata) = metadata.filter(|metadata| metadata.has_moved_from(&owner, &repo_name))
+    {
+        slog::warn!(
+            logger, "repository has been renamed or transferred";
+            "from" => format!("{owner}/{repo_name}"),
+            "to" => format!("{}/{}", metadata.owner, metadata.repo_name),
+        );
+        if options.fix_remote {
+            if let Some(new_url) = rewrite_git_url(
+                remote_url,
+                git_host(api),
+                &metadata.owner,
+                &metadata.repo_name,
+            ) {
+                match &mutator {
+                    Some(mutator) => {
+                        mutator
+                            .remote_set_url(remote_name, &new_url)
+                            .context("update remote url")?;
+                        slog::info!(logger, "updated remote url"; "remote" => remote_name, "url" => new_url);
+                    }
+                    None => {
+                        slog::info!(logger, "would update remote url"; "remote" => remote_name, "url" => new_url)
+                    }
+                }
+            }
+        } else {
+            slog::warn!(logger, "pass --fix-remote to update the remote url");
+        }
+        owner = metadata.owner;
+        repo_name = metadata.repo_name;
+    }
+
+    let old_default = default_branch.as_ref().and_then(|default_branch| {
+        retarget::check(repo, remote_name, default_branch, &logger)
+            .map(|old_default| (old_default, default_branch))
+    });
+    if let Some((old_default, default_branch)) = old_default {
+        match mutator.as_ref().filter(|_| options.retarget_default) {
+            Some(mutator) => {
+                retarget::retarget(mutator, remote_name, default_branch, &old_default, &logger)?
+            }
+            None if options.retarget_default => {
+                slog::info!(
+                    logger,
+                    "would retarget the local repository to the new default branch"
+                )
+            }
+            None => slog::warn!(
+                logger,
+                "pass --retarget-default to update the local repository"
+            ),
+        }
+    }
+
+    let review_heads = if authenticated {
+        reviews::review_requested_heads(octocrab, &owner, &repo_name)
+            .await
+            .unwrap_or_else(|err| {
+                slog::warn!(logger, "failed to find pull requests awaiting review"; "err" => %err);
+                HashSet::new()
+            })
+    } else {
+        HashSet::new()
+    };
+    let mut branches = local_branches(repo)?;
+    if let Some(cassette) = &cassette {
+        branches = cassette.branches(branches)?;
+    }
+    let branch_count = select_branches(repo, &mut branches, options, &logger)?;
+    if options.case_insensitive_heads {
+        warn_case_collisions(&branches, &logger);
+    }
+    let landed = landed_in_default(
+        repo,
+        Some(remote_name),
+        default_branch.as_ref(),
+        &branches,
+        &logger,
+    );
+
+    let mut references = Mentions::new();
+    if options.grep_references {
+        let branch_names = branches
+            .iter()
+            .map(|(branch_name, _)| branch_name.clone())
+            .collect::<Vec<_>>();
+        references::in_tracked_files(repo, &branch_names, &mut references)?;
+        if let Err(err) =
+            references::in_open_issues(octocrab, &owner, &repo_name, &branch_names, &mut references)
+                .await
+        {
+            slog::warn!(logger, "failed to search open issues for branch names"; "err" => %err);
+        }
+    }
+    clients.fetch_budgets(&logger).await;
+    let ctx = Arc::new(RunContext {
+        clients,
+        owner,
+        repo_name,
+        default_branch,
+        require_vetting: options.require_vetting.or_else(|| {
+            (options.min_confidence == Some(Confidence::Medium))
+                .then_some(Vetting::ApprovalOrChecks)
+        }),
+        policy: policy_for(options),
+        bot_branches: build_globset(&options.bot_branches)?,
+        review_heads,
+        references,
+        landed,
+        case_insensitive_heads: options.case_insensitive_heads,
+        verify_pr_heads: options.verify_pr_heads,
+        closed_since: options
+            .recent_prs_only
+            .and_then(|age| SystemTime::now().checked_sub(age)),
+        on_progress: options.on_progress.clone(),
+        sampler: LogSampler::new(options.log_sample),
+        logger: logger.clone(),
+    });
+
+    // Construct a bunch of independent futures which determine whether we should delete a particular branch.
+    // Each future returns a `Decision` about whether the input branch should be deleted, and why.
+    // It then gets spawned onto Tokio, so we have proper parallelism as well as concurrency, and then collected
+    // into a `FuturesUnordered`.
+    progress::emit(&options.on_progress, || ProgressEvent::Started {
+        branches: branches.len(),
+    });
+    let mut join_handles = branches
+        .into_iter()
+        .map(|(branch_name, previous_names)| {
+            let ctx = Arc::clone(&ctx);
+            progress::emit(&ctx.on_progress, || ProgressEvent::Queued {
+                branch_name: branch_name.clone(),
+            });
+
+            tokio::spawn(async move {
+                let logger = ctx.logger.new(o!("branch name" => branch_name.to_string()));
+
+                let facts = BranchFacts {
+                    is_default: ctx.is_default_branch(&branch_name),
+                    review_requested: ctx.review_heads.contains(&branch_name),
+                    is_bot: ctx.bot_branches.is_match(branch_name.as_str()),
+                    references: ctx
+                        .references
+                        .get(&branch_name)
+                        .cloned()
+                        .unwrap_or_default(),
+                    merged: landed_as(&ctx.landed, &branch_name, Landed::Merged),
+                    squashed: landed_as(&ctx.landed, &branch_name, Landed::Squashed),
+                    ..BranchFacts::new(branch_name)
+                };
+                let (facts, decision, pull_requests) =
+                    match ctx.gather_facts(facts, &previous_names, &logger).await {
+                        Ok((facts, pull_requests)) => {
+                            let decision = policy::decide(&facts, &ctx.policy);
+                            (Some(facts), decision, pull_requests)
+                        }
+                        Err((branch_name, err)) => {
+                            (None, Decision::failed(branch_name, err), Vec::new())
+                        }
+                    };
+
+                ctx.sampler.log_decision(&decision, &logger);
+                progress::emit(&ctx.on_progress, || ProgressEvent::Decided {
+                    decision: decision.clone(),
+                    pull_requests: pull_requests.clone(),
+                });
+                (facts, decision, pull_requests)
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+
+    // This is the idiom for completing all futures from a `FuturesUnordered`: just keep getting the next
+    // complete one until no more can complete.
+    let mut decisions = Vec::new();
+    let mut pull_requests = HashMap::new();
+    let mut snapshot = Snapshot {
+        policy: ctx.policy.clone(),
+        branches: Vec::new(),
+    };
+    while let Some(handle_result) = join_handles.next().await {
+        let (facts, decision, summaries) = match handle_result {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                slog::warn!(
+                    logger, "task deciding whether to delete a branch did not complete";
+                    "is_cancelled" => err.is_cancelled(),
+                    "is_panic" => err.is_panic(),
+                );
+                continue;
+            }
+        };
+
+        pull_requests.insert(decision.branch_name.clone(), summaries);
+        decisions.push(decision);
+        snapshot.branches.extend(facts);
+    }
+    ctx.sampler.summarize(&logger);
+
+    // a sample says nothing about the branches left out of it
+    if let Some(path) = options
+        .retry_file
+        .as_ref()
+        .filter(|_| options.sample.is_none())
+    {
+        retry::save(path, &decisions, &logger);
+    }
+
+    if let Some(path) = &options.dump_snapshot {
+        write_snapshot(path, snapshot, &logger);
+    }
+
+    let mut review_branches = decisions
+        .iter()
+        .map(|decision| &decision.branch_name)
+        .filter(|branch_name| ctx.review_heads.contains(*branch_name))
+        .cloned()
+        .collect::<Vec<_>>();
+    review_branches.sort_unstable();
+    for branch_name in &review_branches {
+        slog::info!(logger, "local branch is awaiting my review"; "branch name" => %branch_name);
+    }
+
+    if options.sample.is_some() {
+        log_estimate(&decisions, branch_count, &logger);
+        return Ok(report_on(repo, true, &decisions, pull_requests));
+    }
+
+    if replaying {
+        // the recorded branches may be long gone, so there is no plan to check
+        slog::info!(
+            logger, "replayed decisions";
+            "would delete" => doomed_branches(&decisions).len(),
+            "branches" => decisions.len(),
+        );
+        return Ok(report_on(repo, true, &decisions, pull_requests));
+    }
+    let report = apply(repo, options, &mut decisions, pull_requests, &logger)?;
+    let doomed_branches = doomed_branches(&decisions);
+
+    if options.clean_deployments {
+        for branch_name in &doomed_branches {
+            let logger = logger.new(o!("branch name" => branch_name.to_string()));
+            if let Err(err) = deployments::clean_deployments(
+                ctx.octocrab(),
+                &ctx.owner,
+                &ctx.repo_name,
+                branch_name,
+                options.delete_environments,
+                guard.as_ref(),
+                &logger,
+            )
+            .await
+            {
+                slog::error!(
+                    logger, "failed to clean deployments for branch";
+                    "err" => %err,
+                );
+            }
+        }
+    }
+
+    if options.gist {
+        let title = format!("{}/{}", ctx.owner, ctx.repo_name);
+        let report = report::render_markdown(&title, &decisions, &review_branches, options.dry_run);
+        match ctx
+            .octocrab()
+            .gists()
+            .create()
+            .description(format!("git-clean report for {title}"))
+            .public(false)
+            .file("git-clean-report.md", report)
+            .send()
+            .await
+        {
+            Ok(gist) => slog::info!(logger, "uploaded report"; "url" => %gist.html_url),
+            Err(err) => slog::error!(logger, "failed to upload report to gist"; "err" => %err),
+        }
+    }
+
+    Ok(report)
+}
+
+/// The refs under [`Options::stale_ref_namespaces`] which have gone stale,
+/// if any are configured.
+fn find_stale_refs(
+    repo: &Repository,
+    options: &Options,
+    logger: &slog::Logger,
+) -> Result<Vec<String>, FatalError> {
+    if options.stale_ref_namespaces.is_empty() {
+        return Ok(Vec::new());
+    }
+    stale_refs::find(
+        repo,
+        &build_globset(&options.stale_ref_namespaces)?,
+        options.stale_ref_age,
+        logger,
+    )
+}
+
+/// The policy which `options` call for.
+fn policy_for(options: &Options) -> Policy {
+    Policy {
+        keep_review_requests: options.keep_review_requests,
+        require_vetting: options.require_vetting.is_some(),
+        keep_referenced: options.grep_references,
+        min_confidence: options.min_confidence,
+        require_merged: options.require_merged,
+    }
+}
+
+/// Every local branch, with the names it previously had.
+fn local_branches(repo: &Repository) -> Result<Branches, FatalError> {
+    Ok(repo
+        .branches(Some(BranchType::Local))
+        .context("list local branches")?
+        .filter_map(|maybe_branch| maybe_branch.ok())
+        .filter_map(|(branch, _branch_type)| branch.name().ok().flatten().map(SmallStr::from_str))
+        .map(|branch_name| {
+            let previous_names = renames::previous_names(repo, &branch_name);
+            (branch_name, previous_names)
+        })
+        .collect())
+}
+
+/// Which branches to consider at all, as [`Options::include_branches`],
+/// [`Options::exclude_branches`] and [`Options::owner`] say.
+struct BranchFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    owner: Option<(String, Owners)>,
+}
+
+impl BranchFilter {
+    fn new(repo: &Repository, options: &Options) -> Result<Self, FatalError> {
+        Ok(Self {
+            include: (!options.include_branches.is_empty())
+                .then(|| build_globset(&options.include_branches))
+                .transpose()?,
+            exclude: build_globset(&options.exclude_branches)?,
+            owner: match &options.owner {
+                Some(owner) => Some((owner.clone(), Owners::load(repo)?)),
+                None => None,
+            },
+        })
+    }
+
+    fn allows(&self, branch_name: &str) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(branch_name))
+            && !self.exclude.is_match(branch_name)
+            && self
+                .owner
+                .as_ref()
+                .is_none_or(|(owner, owners)| owners.owns(owner, branch_name))
+    }
+}
+
+/// Narrow `branches` down to those the [`BranchFilter`] allows, then to those
+/// in the retry file, and then to a sample, as configured.
+///
+/// Returns how many branches there were before sampling.
+fn select_branches(
+    repo: &Repository,
+    branches: &mut Branches,
+    options: &Options,
+    logger: &slog::Logger,
+) -> Result<usize, FatalError> {
+    let filter = BranchFilter::new(repo, options)?;
+    let before = branches.len();
+    branches.retain(|(branch_name, _)| filter.allows(branch_name));
+    if branches.len() < before {
+        slog::info!(
+            logger, "skipping branches filtered out by --include, --exclude or --owner";
+            "skipped" => before - branches.len(),
+            "branches" => branches.len(),
+        );
+    }
+    if let Some(retry) = options
+        .retry_file
+        .as_deref()
+        .and_then(|path| retry::load(path, logger))
+    {
+        branches.retain(|(branch_name, _)| retry.contains(branch_name));
+        slog::info!(logger, "only retrying branches from retry file"; "branches" => branches.len());
+    }
+    let branch_count = branches.len();
+    if let Some(sample) = options.sample.filter(|&sample| sample < branch_count) {
+        // hashing with a fresh random state is as good as a shuffle
+        let state = RandomState::new();
+        branches.sort_by_cached_key(|(branch_name, _)| state.hash_one(branch_name));
+        branches.truncate(sample);
+        slog::info!(
+            logger, "sampling branches";
+            "sampled" => sample,
+            "branches" => branch_count,
+        );
+    }
+    Ok(branch_count)
+}
+
+/// Warn about branches whose names differ only in case: ignoring case, they
+/// are all associated with the same pull requests.
+fn warn_case_collisions(branches: &Branches, logger: &slog::Logger) {
+    let mut by_folded_name = HashMap::<String, Vec<&SmallStr>>::new();
+    for (branch_name, _) in branches {
+        by_folded_name
+            .entry(branch_name.to_lowercase())
+            .or_default()
+            .push(branch_name);
+    }
+    let mut collisions = by_folded_name
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .collect::<Vec<_>>();
+    collisions.sort_unstable();
+    for mut names in collisions {
+        names.sort_unstable();
+        let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+        slog::warn!(
+            logger, "branches differ only in case, so share pull requests";
+            "branches" => names.join(", "),
+        );
+    }
+}
+
+/// How those of `branches` which reached the remote default branch did so,
+/// if that can be told.
+///
+/// It can't once the default branch was rebased or force-pushed: branches
+/// merged before that are no longer in its history, so only pull requests
+/// are evidence.
+fn landed_in_default(
+    repo: &Repository,
+    remote_name: Option<&str>,
+    default_branch: Option<&SmallStr>,
+    branches: &Branches,
+    logger: &slog::Logger,
+) -> Option<HashMap<SmallStr, Landed>> {
+    let remote_name = remote_name?;
+    let default_refname = format!("refs/remotes/{remote_name}/{}", default_branch?);
+    let integration_tip = (!repo.is_shallow())
+        .then(|| repo.refname_to_id(&default_refname).ok())
+        .flatten()?;
+    if ancestry::was_rewritten(repo, &default_refname) {
+        slog::warn!(
+            logger, "default branch was rebased or force-pushed; only pull requests say which branches landed";
+            "default branch" => &default_refname,
+        );
+        return None;
+    }
+    let mut patches = (!ancestry::is_partial_clone(repo))
+        .then(|| ancestry::PatchIndex::new(repo, integration_tip));
+    let mut landed = HashMap::new();
+    for (branch_name, _) in branches {
+        let Some(tip) = repo
+            .find_branch(branch_name, BranchType::Local)
+            .ok()
+            .and_then(|branch| branch.get().target())
+        else {
+            continue;
+        };
+        if ancestry::contains(repo, integration_tip, tip).unwrap_or_default() {
+            landed.insert(branch_name.clone(), Landed::Merged);
+        } else if patches
+            .as_mut()
+            .is_some_and(|patches| patches.squashed(tip))
+        {
+            landed.insert(branch_name.clone(), Landed::Squashed);
+        }
+    }
+    Some(landed)
+}
+
+/// Whether `branch_name` reached the default branch `how`, if that was
+/// checked.
+fn landed_as(
+    landed: &Option<HashMap<SmallStr, Landed>>,
+    branch_name: &SmallStr,
+    how: Landed,
+) -> Option<bool> {
+    landed
+        .as_ref()
+        .map(|landed| landed.get(branch_name) == Some(&how))
+}
+
+fn write_snapshot(path: &Path, mut snapshot: Snapshot, logger: &slog::Logger) {
+    snapshot
+        .branches
+        .sort_unstable_by(|a, b| a.branch_name.cmp(&b.branch_name));
+    match std::fs::File::create(path)
+        .map_err(serde_json::Error::io)
+        .and_then(|file| serde_json::to_writer_pretty(file, &snapshot))
+    {
+        Ok(()) => slog::info!(logger, "wrote snapshot"; "path" => %path.display()),
+        Err(err) => slog::error!(logger, "failed to write snapshot"; "err" => %err),
+    }
+}
+
+/// Estimate from a sample's decisions how many of all `branch_count`
+/// branches would be deleted.
+fn log_estimate(decisions: &[Decision], branch_count: usize, logger: &slog::Logger) {
+    let doomed = decisions
+        .iter()
+        .filter(|decision| decision.is_delete())
+        .count();
+    let estimate = match decisions.len() {
+        0 => 0,
+        sampled => doomed * branch_count / sampled,
+    };
+    slog::info!(
+        logger, "sampled decisions";
+        "would delete" => doomed,
+        "sampled" => decisions.len(),
+        "estimated deletions" => estimate,
+        "branches" => branch_count,
+    );
+}
+
+/// Clean up git branches, looking up their pull requests with `forge`.
+///
+/// Branches are judged as by [`clean_branches`], but only with what a
+/// [`ForgeProvider`] can tell: options which need GitHub, such as vetting,
+/// review requests, deployments and gists, are ignored with a warning.
+pub async fn clean_branches_with(
+    path: impl AsRef<Path>,
+    options: &Options,
+    forge: impl ForgeProvider,
+) -> Result<CleanReport, FatalError> {
+    clean_path_with(path, options, forge, logging::logger()).await
+}
+
+/// As [`clean_branches_with`], logging to `logger`.
+#[deprecated(
+    since = "1.1.0",
+    note = "set the logger once with `set_logger`, and call `clean_branches_with`"
+)]
+pub async fn clean_branches_with_logged(
+    path: impl AsRef<Path>,
+    options: &Options,
+    forge: impl ForgeProvider,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    clean_path_with(path, options, forge, logger).await
+}
+
+async fn clean_path_with(
+    path: impl AsRef<Path>,
+    options: &Options,
+    forge: impl ForgeProvider,
+    logger: slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    let repo = ancestry::open_repository(path)?;
+    if options.local_only || options.gone {
+        return clean_branches_offline(&repo, offline_mode(options), options, &logger);
+    }
+    let remotes = repo.remotes().context("list remotes")?;
+    // the remote only serves to tell which branches are merged
+    let remote_name = match remotes.len() {
+        1 => remotes.get(0),
+        _ => None,
+    };
+    clean_branches_with_forge(&repo, remote_name, &forge, options, &logger).await
+}
+
+/// Clean branches judged by the pull requests `forge` knows of.
+async fn clean_branches_with_forge(
+    repo: &Repository,
+    remote_name: Option<&str>,
+    forge: &dyn ForgeProvider,
+    options: &Options,
+    logger: &slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    /// How many branches' pull requests are looked up at once.
+    const CONCURRENCY: usize = 8;
+
+    let skipped = [
+        (options.require_vetting.is_some(), "--require-vetting"),
+        (options.keep_review_requests, "--keep-review-requests"),
+        (options.clean_deployments, "--clean-deployments"),
+        (options.recent_prs_only.is_some(), "--recent-prs-only"),
+        (options.gist, "--gist"),
+        (
+            options.record.is_some() || options.replay.is_some(),
+            "--record, --replay",
+        ),
+    ];
+    for (_, flag) in skipped.iter().filter(|(set, _)| *set) {
+        slog::warn!(logger, "not supported by this forge; ignoring"; "option" => flag);
+    }
+
+    let default_branch = forge.default_branch().await.unwrap_or_else(|err| {
+        slog::warn!(logger, "failed to get default branch"; "err" => %err);
+        None
+    });
+
+    let mut branches = local_branches(repo)?;
+    let branch_count = select_branches(repo, &mut branches, options, logger)?;
+    let landed = landed_in_default(
+        repo,
+        remote_name,
+        default_branch.as_ref(),
+        &branches,
+        logger,
+    );
+    let mut references = Mentions::new();
+    if options.grep_references {
+        let branch_names = branches
+            .iter()
+            .map(|(branch_name, _)| branch_name.clone())
+            .collect::<Vec<_>>();
+        references::in_tracked_files(repo, &branch_names, &mut references)?;
+    }
+    let policy = Policy {
+        keep_review_requests: false,
+        require_vetting: false,
+        ..policy_for(options)
+    };
+    let bot_branches = build_globset(&options.bot_branches)?;
+    let sampler = LogSampler::new(options.log_sample);
+
+    progress::emit(&options.on_progress, || ProgressEvent::Started {
+        branches: branches.len(),
+    });
+    let results = futures::stream::iter(branches)
+        .map(|(branch_name, previous_names)| {
+            progress::emit(&options.on_progress, || ProgressEvent::Queued {
+                branch_name: branch_name.clone(),
+            });
+            let logger = logger.new(o!("branch name" => branch_name.to_string()));
+            let mut facts = BranchFacts {
+                is_default: default_branch.as_ref() == Some(&branch_name),
+                is_bot: bot_branches.is_match(branch_name.as_str()),
+                references: references.get(&branch_name).cloned().unwrap_or_default(),
+                merged: landed_as(&landed, &branch_name, Landed::Merged),
+                squashed: landed_as(&landed, &branch_name, Landed::Squashed),
+                ..BranchFacts::new(branch_name)
+            };
+            let policy = &policy;
+            let sampler = &sampler;
+            async move {
+                let mut failure = None;
+                if policy.needs_pull_requests(&facts) {
+                    for name in std::iter::once(&facts.branch_name).chain(&previous_names) {
+                        match forge.pull_requests(name).await {
+                            Ok(prs) => facts.pull_requests.extend(prs),
+                            Err(err) => {
+                                slog::error!(logger, "failed to get prs for branch"; "err" => %err);
+                                failure = Some(err);
+                                break;
+                            }
+                        }
+                    }
+                    facts.pull_requests.sort_unstable_by_key(|pr| pr.number);
+                    facts.pull_requests.dedup_by_key(|pr| pr.number);
+                }
+                let pull_requests = facts
+                    .pull_requests
+                    .iter()
+                    .map(PrSummary::from)
+                    .collect::<Vec<_>>();
+                let (facts, decision) = match failure {
+                    Some(err) => (None, Decision::failed(facts.branch_name, err)),
+                    None => {
+                        let decision = policy::decide(&facts, policy);
+                        (Some(facts), decision)
+                    }
+                };
+                sampler.log_decision(&decision, &logger);
+                progress::emit(&options.on_progress, || ProgressEvent::Decided {
+                    decision: decision.clone(),
+                    pull_requests: pull_requests.clone(),
+                });
+                (facts, decision, pull_requests)
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut decisions = Vec::with_capacity(results.len());
+    let mut pull_requests = HashMap::new();
+    let mut snapshot = Snapshot {
+        policy,
+        branches: Vec::new(),
+    };
+    for (facts, decision, summaries) in results {
+        pull_requests.insert(decision.branch_name.clone(), summaries);
+        decisions.push(decision);
+        snapshot.branches.extend(facts);
+    }
+    sampler.summarize(logger);
+
+    if let Some(path) = options
+        .retry_file
+        .as_ref()
+        .filter(|_| options.sample.is_none())
+    {
+        retry::save(path, &decisions, logger);
+    }
+    if let Some(path) = &options.dump_snapshot {
+        write_snapshot(path, snapshot, logger);
+    }
+    if options.sample.is_some() {
+        log_estimate(&decisions, branch_count, logger);
+        return Ok(report_on(repo, true, &decisions, pull_requests));
+    }
+
+    apply(repo, options, &mut decisions, pull_requests, logger)
+}
+
+/// Why an offline run was asked for, when it was.
+fn offline_mode(options: &Options) -> &'static str {
+    if options.gone {
+        "cleaning branches whose upstream is gone"
+    } else {
+        "local-only mode"
+    }
+}
+
+/// Clean a repository which has no remotes, so has no forge to consult.
+///
+/// Branches are deleted when their tips are already contained in the
+/// integration branch.
+fn clean_branches_offline(
+    repo: &Repository,
+    why: &str,
+    options: &Options,
+    logger: &slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    slog::warn!(
+        logger, "{why}; using offline ancestry-based cleaning";
+        "skipped" => "pull request lookup, deployment cleanup",
+    );
+
+    if ancestry::is_partial_clone(repo) {
+        slog::info!(
+            logger, "repository is a partial clone; only commit history will be inspected";
+        );
+    }
+
+    if options.gist {
+        slog::warn!(logger, "not uploading report: no forge to upload it to");
+    }
+    if options.dump_snapshot.is_some() {
+        slog::warn!(
+            logger,
+            "not writing snapshot: offline decisions do not use the policy"
+        );
+    }
+
+    let filter = BranchFilter::new(repo, options)?;
+    let considered = |branch_name: &str| filter.allows(branch_name);
+    let mut decisions = if options.gone {
+        // the integration branch only raises confidence here
+        let integration =
+            ancestry::integration_branch(repo, options.integration_branch.as_deref()).ok();
+        gone::gone_branches(repo, integration.as_ref(), &considered, logger)?
+    } else {
+        let integration =
+            ancestry::integration_branch(repo, options.integration_branch.as_deref())?;
+        ancestry::merged_branches(repo, &integration, &considered, logger)?
+    };
+    if options.grep_references {
+        // with no forge, only the repository's own files can mention a branch
+        let doomed = doomed_branches(&decisions);
+        let mut references = Mentions::new();
+        references::in_tracked_files(repo, &doomed, &mut references)?;
+        for decision in &mut decisions {
+            if let Some(mentions) = references.remove(&decision.branch_name) {
+                let reason = Reason::Referenced(mentions);
+                slog::info!(
+                    logger, "retaining branch after all";
+                    "branch name" => %decision.branch_name,
+                    "reason" => %reason,
+                );
+                decision.outcome = Outcome::Retain(reason);
+            }
+        }
+    }
+
+    progress::emit(&options.on_progress, || ProgressEvent::Started {
+        branches: decisions.len(),
+    });
+    for decision in &decisions {
+        progress::emit(&options.on_progress, || ProgressEvent::Queued {
+            branch_name: decision.branch_name.clone(),
+        });
+        progress::emit(&options.on_progress, || ProgressEvent::Decided {
+            decision: decision.clone(),
+            pull_requests: Vec::new(),
+        });
+    }
+
+    apply(repo, options, &mut decisions, HashMap::new(), logger)
+}
+
+fn doomed_branches(decisions: &[Decision]) -> Vec<SmallStr> {
+    decisions
+        .iter()
+        .filter(|decision| decision.is_delete())
+        .map(|decision| decision.branch_name.clone())
+        .collect()
+}
+
+/// Delete the doomed branches, and the stale refs
+/// [`Options::stale_ref_namespaces`] asks for, unless this is a dry run or
+/// the plan was not approved.
+///
+/// With [`Options::confirm`], each branch is asked about first, and those
+/// skipped are retained instead; stale refs, which can't be asked about, are
+/// then left alone. Bot branches are not part of the plan which needs
+/// approval.
+fn apply(
+    repo: &Repository,
+    options: &Options,
+    decisions: &mut [Decision],
+    pull_requests: HashMap<SmallStr, Vec<PrSummary>>,
+    logger: &slog::Logger,
+) -> Result<CleanReport, FatalError> {
+    let guard = DeletionGuard::new(options.dry_run);
+    let preflight = preflight::Preflight::new(repo);
+    retain_checked_out(&preflight, decisions, logger);
+    retain_unpushed(repo, options, decisions, &pull_requests, logger);
+    if let Some(age) = options.exclude_recent {
+        retain_recent(repo, age, decisions, logger);
+    }
+    if let (Some(hook), false) = (&options.confirm, options.dry_run) {
+        confirm::confirm(repo, hook, decisions, &pull_requests, logger)?;
+    }
+    let decisions = &*decisions;
+    let report = report_on(repo, options.dry_run, decisions, pull_requests);
+    let (bot_branches, reviewed_branches): (Vec<_>, Vec<_>) = decisions
+        .iter()
+        .filter(|decision| decision.is_delete())
+        .partition(|decision| decision.is_bot());
+    let reviewed_branches = reviewed_branches
+        .into_iter()
+        .map(|decision| decision.branch_name.clone())
+        .collect::<Vec<_>>();
+
+    let doomed_branches = doomed_branches(decisions);
+    let obstacles = preflight.check_all(&doomed_branches);
+    for (branch_name, obstacle) in &obstacles {
+        slog::warn!(
+            logger, "planned deletion would fail";
+            "branch name" => %branch_name,
+            "reason" => %obstacle,
+        );
+    }
+    for (branch_name, sibling) in siblings::shared_branches(repo, &doomed_branches) {
+        slog::warn!(
+            logger, "branch also exists in a clone which shares objects with this one";
+            "branch name" => %branch_name,
+            "clone" => %sibling.display(),
+        );
+    }
+    let deletes_all = deletes_all_but_one(repo, &doomed_branches)?;
+    if deletes_all {
+        slog::warn!(
+            logger, "plan deletes every local branch but one; check the default branch and policy";
+            "branches" => doomed_branches.len(),
+        );
+    }
+    let mut stale_refs = find_stale_refs(repo, options, logger)?;
+    if options.confirm.is_some() && !options.dry_run && !stale_refs.is_empty() {
+        slog::info!(
+            logger, "not cleaning stale refs while confirming each deletion";
+            "refs" => stale_refs.len(),
+        );
+        stale_refs.clear();
+    }
+    let planned = doomed_branches.len() + stale_refs.len();
+    let too_many = options.max_delete.filter(|&max| planned > max);
+    if let Some(max) = too_many {
+        slog::warn!(
+            logger, "plan deletes more branches than --max-delete allows";
+            "branches" => doomed_branches.len(),
+            "stale refs" => stale_refs.len(),
+            "max" => max,
+        );
+    }
+
+    let Some(guard) = guard else {
+        let plan_hash = plan::hash(repo, &reviewed_branches, &stale_refs)?;
+        slog::info!(
+            logger, "planned deletions";
+            "branches" => reviewed_branches.len(),
+            "bot branches" => bot_branches.len(),
+            "stale refs" => stale_refs.len(),
+            "would fail" => obstacles.len(),
+            "plan hash" => &plan_hash,
+        );
+        return Ok(report);
+    };
+
+    if !obstacles.is_empty() {
+        return Err(FatalError::PreflightFailed(
+            obstacles
+                .iter()
+                .map(|(branch_name, obstacle)| format!("{branch_name} ({obstacle})"))
+                .collect(),
+        ));
+    }
+    // each deletion confirmed is as explicit as it gets
+    if deletes_all && !options.allow_delete_all && options.confirm.is_none() {
+        return Err(FatalError::WouldDeleteAll(doomed_branches.len()));
+    }
+    if let Some(max) = too_many.filter(|_| options.confirm.is_none()) {
+        return Err(FatalError::TooManyDeletions { planned, max });
+    }
+    // signing needs the key, so is only done when asked to check
+    if let Some(approved) = &options.approve {
+        plan::check_approval(
+            approved,
+            &plan::hash(repo, &reviewed_branches, &stale_refs)?,
+        )?;
+    }
+    // journaled with the branches, for `git-clean recover --pr`
+    let pull_request_numbers = report
+        .branches
+        .iter()
+        .map(|branch| {
+            let refname = format!("refs/heads/{}", branch.decision.branch_name);
+            (
+                refname,
+                branch.pull_requests.iter().map(|pr| pr.number).collect(),
+            )
+        })
+        .collect();
+    let mutator = guard.mutator(repo);
+    delete_in_transaction(
+        &mutator,
+        &doomed_branches,
+        &pull_request_numbers,
+        options.archive,
+        logger,
+    )?;
+    if !stale_refs.is_empty() {
+        stale_refs::delete(&mutator, &stale_refs, logger)?;
+        slog::info!(logger, "cleaned stale refs"; "refs" => stale_refs.len());
+    }
+    for branch_name in &doomed_branches {
+        progress::emit(&options.on_progress, || ProgressEvent::Deleted {
+            branch_name: branch_name.clone(),
+        });
+    }
+    slog::info!(
+        logger, "deleted branches";
+        "branches" => reviewed_branches.len(),
+        "bot branches" => bot_branches.len(),
+    );
+    Ok(report)
+}
+
+/// Retain the doomed branches which are checked out, here or in another
+/// worktree, since deleting them would fail.
+fn retain_checked_out(
+    preflight: &preflight::Preflight,
+    decisions: &mut [Decision],
+    logger: &slog::Logger,
+) {
+    for decision in decisions.iter_mut().filter(|decision| decision.is_delete()) {
+        let reason = match preflight.check(&decision.branch_name) {
+            Some(Obstacle::CheckedOut) => Reason::CheckedOut(None),
+            Some(Obstacle::CheckedOutInWorktree(worktree)) => Reason::CheckedOut(Some(worktree)),
+            _ => continue,
+        };
+        slog::info!(
+            logger, "retaining branch after all";
+            "branch name" => %decision.branch_name,
+            "reason" => %reason,
+        );
+        decision.outcome = Outcome::Retain(reason);
+    }
+}
+
+/// Retain the doomed branches with commits which were never pushed, which
+/// deleting them would lose, and, as [`Options::verify_pr_heads`] asks,
+/// those which moved on from their closed pull requests.
+fn retain_unpushed(
+    repo: &Repository,
+    options: &Options,
+    decisions: &mut [Decision],
+    pull_requests: &HashMap<SmallStr, Vec<PrSummary>>,
+    logger: &slog::Logger,
+) {
+    for decision in decisions.iter_mut().filter(|decision| decision.is_delete()) {
+        let prs = pull_requests
+            .get(&decision.branch_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let reason = match unpushed::count(repo, &decision.branch_name, prs) {
+            Some(commits @ 1..) => Reason::Unpushed(commits),
+            _ if options.verify_pr_heads
+                && unpushed::on_pull_request_head(repo, &decision.branch_name, prs)
+                    == Some(false) =>
+            {
+                Reason::TipMoved
+            }
+            _ => continue,
+        };
+        slog::info!(
+            logger, "retaining branch after all";
+            "branch name" => %decision.branch_name,
+            "reason" => %reason,
+        );
+        decision.outcome = Outcome::Retain(reason);
+    }
+}
+
+/// Retain the doomed branches whose tip was committed less than `age` ago,
+/// since work closed and reopened that recently is likely still going on.
+fn retain_recent(
+    repo: &Repository,
+    age: Duration,
+    decisions: &mut [Decision],
+    logger: &slog::Logger,
+) {
+    let now = SystemTime::now();
+    for decision in decisions.iter_mut().filter(|decision| decision.is_delete()) {
+        let Ok(commit) = repo
+            .revparse_single(&format!("refs/heads/{}", decision.branch_name))
+            .and_then(|object| object.peel_to_commit())
+        else {
+            continue;
+        };
+        let committed = SystemTime::UNIX_EPOCH
+            + Duration::from_secs(commit.time().seconds().try_into().unwrap_or_default());
+        // a commit from the future is as recent as it gets
+        let since = now.duration_since(committed).unwrap_or_default();
+        if since >= age {
+            continue;
+        }
+        let reason = Reason::RecentCommit(since);
+        slog::info!(
+            logger, "retaining branch after all";
+            "branch name" => %decision.branch_name,
+            "reason" => %reason,
+        );
+        decision.outcome = Outcome::Retain(reason);
+    }
+}
+
+/// Whether deleting `doomed_branches` would leave at most one local branch,
+/// the default, which more often means the default branch or the policy is
+/// wrong than that all work is done.
+///
+/// Deleting one or two is fine: a finished feature or two are often all
+/// there is besides the default branch.
+fn deletes_all_but_one(
+    repo: &Repository,
+    doomed_branches: &[SmallStr],
+) -> Result<bool, FatalError> {
+    const MIN_DOOMED: usize = 3;
+
+    let local = repo
+        .branches(Some(BranchType::Local))
+        .context("list local branches")?
+        .count();
+    Ok(doomed_branches.len() >= MIN_DOOMED && local - doomed_branches.len() <= 1)
+}
+
+/// Report `decisions` with the pull requests found for each, and the tips of
+/// the branches to be deleted, while they still exist.
+fn report_on(
+    repo: &Repository,
+    dry_run: bool,
+    decisions: &[Decision],
+    mut pull_requests: HashMap<SmallStr, Vec<PrSummary>>,
+) -> CleanReport {
+    // a broken owners file already failed the run, if it mattered
+    let owners = Owners::load(repo).unwrap_or_default();
+    let tip = |branch_name: &str| {
+        let commit = repo
+            .find_branch(branch_name, BranchType::Local)
+            .ok()?
+            .get()
+            .peel_to_commit()
+            .ok()?;
+        Some(commit.id().to_string())
+    };
+    CleanReport {
+        dry_run,
+        branches: decisions
+            .iter()
+            .map(|decision| BranchReport {
+                owners: owners.of(&decision.branch_name).to_vec(),
+                decision: decision.clone(),
+                pull_requests: pull_requests
+                    .remove(&decision.branch_name)
+                    .unwrap_or_default(),
+                tip: decision
+                    .is_delete()
+                    .then(|| tip(&decision.branch_name))
+                    .flatten(),
+            })
+            .collect(),
+    }
+}
+
+/// Delete the named local branches of the repository at `path`, chosen
+/// elsewhere, such as from the decisions of a dry run.
+///
+/// As when cleaning, either every branch is deleted or none are, and nothing
+/// is deleted if any of them can't be, such as the checked-out branch, or
+/// shouldn't be: those [`Options::exclude_branches`] protects, the
+/// integration branch and the remotes' default branches. The same limits
+/// as when cleaning apply: [`Options::max_delete`],
+/// [`Options::allow_delete_all`] and [`Options::approve`], against the hash
+/// of these branches. With [`Options::archive`], each is kept under
+/// `refs/archive/`. A dry run only checks that they could be.
+pub fn delete_branches(
+    path: impl AsRef<Path>,
+    branch_names: &[SmallStr],
+    options: &Options,
+) -> Result<(), FatalError> {
+    let logger = logging::logger();
+    let repo = ancestry::open_repository(path)?;
+    let excluded = build_globset(&options.exclude_branches)?;
+    let mut defaults = ancestry::remote_defaults(&repo);
+    if let Ok(Some(integration)) = ancestry::integration_branch(&repo, None)
+        .map(|branch| branch.name().ok().flatten().map(str::to_owned))
+    {
+        defaults.push(integration);
+    }
+    defaults.extend(options.integration_branch.clone());
+    let protected = branch_names
+        .iter()
+        .filter(|branch_name| {
+            excluded.is_match(branch_name.as_str())
+                || defaults
+                    .iter()
+                    .any(|default| default == branch_name.as_str())
+        })
+        .map(|branch_name| branch_name.to_string())
+        .collect::<Vec<_>>();
+    if !protected.is_empty() {
+        return Err(FatalError::BranchesProtected(protected));
+    }
+    let obstacles = preflight::Preflight::new(&repo).check_all(branch_names);
+    if !obstacles.is_empty() {
+        return Err(FatalError::PreflightFailed(
+            obstacles
+                .iter()
+                .map(|(branch_name, obstacle)| format!("{branch_name} ({obstacle})"))
+                .collect(),
+        ));
+    }
+    let deletes_all = deletes_all_but_one(&repo, branch_names)?;
+    let too_many = options.max_delete.filter(|&max| branch_names.len() > max);
+    let Some(guard) = DeletionGuard::new(options.dry_run) else {
+        slog::info!(
+            logger, "would delete branches";
+            "branches" => branch_names.len(),
+            "plan hash" => plan::hash(&repo, branch_names, &[])?,
+        );
+        return Ok(());
+    };
+    if deletes_all && !options.allow_delete_all {
+        return Err(FatalError::WouldDeleteAll(branch_names.len()));
+    }
+    if let Some(max) = too_many {
+        return Err(FatalError::TooManyDeletions {
+            planned: branch_names.len(),
+            max,
+        });
+    }
+    if let Some(approved) = &options.approve {
+        plan::check_approval(approved, &plan::hash(&repo, branch_names, &[])?)?;
+    }
+    delete_in_transaction(
+        &guard.mutator(&repo),
+        branch_names,
+        &HashMap::new(),
+        options.archive,
+        &logger,
+    )?;
+    slog::info!(logger, "deleted branches"; "branches" => branch_names.len());
+    Ok(())
+}
+
+/// Delete all of the named local branches in a single ref transaction.
+///
+/// Either every branch is deleted or none are: if the process dies or any ref
+/// cannot be locked, the repository is left exactly as it was. Archived
+/// branches are archived in the same transaction. The numbers of their pull
+/// requests, by ref, are journaled with them.
+fn delete_in_transaction(
+    mutator: &Mutator,
+    branch_names: &[SmallStr],
+    pull_requests: &HashMap<String, Vec<u64>>,
+    archive: bool,
+    logger: &slog::Logger,
+) -> Result<(), FatalError> {
+    if branch_names.is_empty() {
+        return Ok(());
+    }
+    let repo = mutator.repo();
+
+    let refnames = branch_names
+        .iter()
+        .map(|branch_name| format!("refs/heads/{branch_name}"))
+        .collect::<Vec<_>>();
+    let run_id = journal::record(mutator, &refnames, pull_requests)?;
+    slog::info!(
+        logger, "journaled branches before deleting them";
+        "run id" => &run_id,
+        "recover with" => format!("git-clean recover --from {run_id}"),
+    );
+
+    let mut transaction = mutator
+        .transaction()
+        .map_err(FatalError::DeletionTransaction)?;
+    let mut archived = Vec::new();
+    for (branch_name, refname) in branch_names.iter().zip(&refnames) {
+        if archive {
+            let archive_refname = archive::unused_refname(repo, branch_name, &run_id);
+            repo.refname_to_id(refname)
+                .and_then(|oid| {
+                    transaction.lock_ref(&archive_refname)?;
+                    transaction.set_target(&archive_refname, oid, None, "git-clean: archive")
+                })
+                .map_err(FatalError::DeletionTransaction)?;
+            archived.push((refname, archive_refname));
+        }
+        transaction
+            .lock_ref(refname)
+            .and_then(|()| transaction.remove(refname))
+            .map_err(FatalError::DeletionTransaction)?;
+    }
+    transaction
+        .commit()
+        .map_err(FatalError::DeletionTransaction)?;
+    // as git does, lest a new branch of the same name inherit the old one's
+    // reflog; the journal keeps a copy
+    for refname in &refnames {
+        if let Err(err) = mutator.reflog_delete(refname) {
+            slog::warn!(logger, "failed to remove reflog of deleted branch"; "ref" => refname, "err" => %err);
+        }
+    }
+    if archive {
+        for (refname, archive_refname) in &archived {
+            // the archived ref has the branch's history, for `git reflog`
+            journal::restore_reflog(mutator, &run_id, refname, archive_refname)?;
+        }
+        slog::info!(
+            logger, "archived branches";
+            "branches" => branch_names.len(),
+            "restore with" => "git-clean restore <branch>",
+        );
+    }
+
+    // `Branch::delete` would also have removed the branch's config section.
+    // Config is not covered by the ref transaction, so this is best-effort.
+    if let Ok(mut config) = mutator.config() {
+        for branch_name in branch_names {
+            let pattern = format!("^branch\\.{}\\.", regex::escape(branch_name));
+            let keys = config
+                .entries(Some(&pattern))
+                .map(|entries| {
+                    let mut keys = Vec::new();
+                    let _ = entries.for_each(|entry| {
+                        if let Some(name) = entry.name() {
+                            keys.push(name.to_owned());
+                        }
+                    });
+                    keys
+                })
+                .unwrap_or_default();
+            for key in keys {
+                if let Err(err) = config.remove(&key) {
+                    slog::warn!(
+                        logger, "failed to remove config for deleted branch";
+                        "key" => key,
+                        "err" => %err,
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // use std::io::Write;
+
+    use futures::future::BoxFuture;
+
+    use super::*;
+    use crate::testing::{commit, scratch_repo};
+
+    // this can go wrong if someone ever creates another PR with that name
+    // in that repo, but for now we'll assume that won't happen
+    //
+    // We ignore this test by default because it requires a network connection
+    // and can be a little slow / use up the API rate limit (60/hr).
+    #[tokio::test]
+    #[ignore]
+    async fn get_pr_by_branch_name() {
+        let octocrab = octocrab::instance();
+
+        let prs = get_prs(octocrab, "coriolinus", "counter-rs", "index")
+            .await
+            .unwrap();
+
+        assert_eq!(prs.len(), 1);
+        assert_eq!(prs[0].number, 9);
+    }
+
+    #[test]
+    fn quotes_branch_names_in_searches() {
+        let query = |branch_name| pr_search_query("owner", "repo", branch_name);
+        assert_eq!(
+            query("fix/login"),
+            r#"is:pr repo:owner/repo head:"fix/login""#
+        );
+        assert_eq!(
+            query("issue#12"),
+            r#"is:pr repo:owner/repo head:"issue#12""#
+        );
+        assert_eq!(
+            query("a(b)-OR-c:d"),
+            r#"is:pr repo:owner/repo head:"a(b)-OR-c:d""#
+        );
+        assert_eq!(
+            query(r#"say-"hi""#),
+            r#"is:pr repo:owner/repo head:"say-\"hi\"""#
+        );
+    }
+
+    #[test]
+    fn recognizes_enterprise_remotes() {
+        let api: Uri = "https://github.example.com/api/v3".parse().unwrap();
+        let host = git_host(&api);
+        assert_eq!(
+            parse_git_url("git@github.example.com:team/repo.git", host),
+            Some(("team".into(), "repo".into()))
+        );
+        assert_eq!(parse_git_url("git@github.com:team/repo.git", host), None);
+        assert_eq!(
+            parse_git_url("https://github.example.com/team/repo.git", host),
+            Some(("team".into(), "repo".into()))
+        );
+        assert_eq!(
+            parse_git_url("https://github.example.com.evil/team/repo.git", host),
+            None
+        );
+        assert_eq!(git_host(&Uri::from_static(GITHUB_API)), "github.com");
+    }
+
+    /// Pretends that `done` has a closed pull request, and nothing else has
+    /// any.
+    struct MockForge;
+
+    impl ForgeProvider for MockForge {
+        fn default_branch(&self) -> BoxFuture<'_, Result<Option<SmallStr>, FatalError>> {
+            Box::pin(async { Ok(Some("main".into())) })
+        }
+
+        fn pull_requests<'a>(
+            &'a self,
+            branch_name: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<PullRequestFacts>, FatalError>> {
+            Box::pin(async move {
+                Ok(match branch_name {
+                    "done" => vec![PullRequestFacts {
+                        number: 1,
+                        open: false,
+                        merged: Some(true),
+                    }],
+                    _ => Vec::new(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn cleans_with_any_forge() {
+        let (dir, repo) = scratch_repo("forge");
+        let commit = repo
+            .find_commit(repo.refname_to_id("refs/heads/main").unwrap())
+            .unwrap();
+        for branch_name in ["done", "wip"] {
+            repo.branch(branch_name, &commit, false).unwrap();
+        }
+
+        let report = clean_branches_with(&dir, &Options::default(), MockForge)
+            .await
+            .unwrap();
+        let deleted = report.deleted().collect::<Vec<_>>();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].decision.branch_name, "done");
+        assert_eq!(deleted[0].tip, Some(commit.id().to_string()));
+        assert_eq!(deleted[0].pull_requests[0].number, 1);
+
+        let remaining = repo
+            .branches(Some(BranchType::Local))
+            .unwrap()
+            .map(|branch| branch.unwrap().0.name().unwrap().unwrap().to_owned())
+            .collect::<HashSet<_>>();
+        assert_eq!(remaining, HashSet::from(["main".into(), "wip".into()]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_pull_requests_once_the_default_branch_was_rewritten() {
+        let (dir, repo) = scratch_repo("rewritten-default");
+        repo.remote("origin", "https://example.com/team/repo.git")
+            .unwrap();
+        let base = repo
+            .find_commit(repo.refname_to_id("refs/heads/main").unwrap())
+            .unwrap();
+        repo.branch("done", &base, false).unwrap();
+        repo.reference("refs/remotes/origin/main", base.id(), false, "fetch")
+            .unwrap();
+        // `landed` is squash-merged into the default branch, but only after
+        // that was force-pushed onto history of its own
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let mut tree = repo.treebuilder(Some(&base.tree().unwrap())).unwrap();
+        tree.insert("b", repo.blob(b"b\n").unwrap(), 0o100644)
+            .unwrap();
+        let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+        repo.commit(
+            Some("refs/heads/landed"),
+            &signature,
+            &signature,
+            "b",
+            &tree,
+            &[&base],
+        )
+        .unwrap();
+        let unrelated = repo
+            .commit(None, &signature, &signature, "rewritten", &tree, &[])
+            .unwrap();
+        repo.reference(
+            "refs/remotes/origin/main",
+            unrelated,
+            true,
+            "fetch: forced-update",
+        )
+        .unwrap();
+        let squashed = repo
+            .commit(None, &signature, &signature, "squashed b", &tree, &[&base])
+            .unwrap();
+        repo.reference(
+            "refs/remotes/origin/main",
+            squashed,
+            true,
+            "fetch: forced-update",
+        )
+        .unwrap();
+
+        let report = clean_branches_with(&dir, &Options::default(), MockForge)
+            .await
+            .unwrap();
+        let deleted = report
+            .deleted()
+            .map(|branch| branch.decision.branch_name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(deleted, ["done"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn limits_deleting_chosen_branches() {
+        let (dir, repo) = scratch_repo("chosen");
+        let commit = repo
+            .find_commit(repo.refname_to_id("refs/heads/main").unwrap())
+            .unwrap();
+        let chosen = ["a", "b", "c"].map(SmallStr::from);
+        for branch_name in &chosen {
+            repo.branch(branch_name, &commit, false).unwrap();
+        }
+
+        let options = Options {
+            allow_delete_all: true,
+            max_delete: Some(2),
+            ..Options::default()
+        };
+        assert!(matches!(
+            delete_branches(&dir, &chosen, &options),
+            Err(FatalError::TooManyDeletions { planned: 3, max: 2 })
+        ));
+        let options = Options {
+            allow_delete_all: false,
+            max_delete: None,
+            ..options
+        };
+        assert!(matches!(
+            delete_branches(&dir, &chosen, &options),
+            Err(FatalError::WouldDeleteAll(3))
+        ));
+        assert!(repo.find_branch("a", BranchType::Local).is_ok());
+
+        delete_branches(&dir, &chosen[..2], &options).unwrap();
+        assert!(repo.find_branch("a", BranchType::Local).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cleans_stale_refs_as_part_of_the_plan() {
+        let (dir, repo) = scratch_repo("stale");
+        let signature =
+            git2::Signature::new("test", "test@example.com", &git2::Time::new(0, 0)).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let refname = "refs/original/refs/heads/main";
+        repo.commit(Some(refname), &signature, &signature, "old", &tree, &[])
+            .unwrap();
+
+        let options = Options {
+            local_only: true,
+            stale_ref_namespaces: vec!["refs/original/**".into()],
+            max_delete: Some(0),
+            ..Options::default()
+        };
+        let err = clean_branches_with(&dir, &options, MockForge)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FatalError::TooManyDeletions { planned: 1, max: 0 }
+        ));
+        assert!(repo.find_reference(refname).is_ok());
+
+        let options = Options {
+            max_delete: None,
+            ..options
+        };
+        clean_branches_with(&dir, &options, MockForge)
+            .await
+            .unwrap();
+        assert!(repo.find_reference(refname).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn archives_without_overwriting_and_restores_the_latest() {
+        let (dir, repo) = scratch_repo("archive");
+        let options = Options {
+            archive: true,
+            ..Options::default()
+        };
+        let first = commit(&repo, "refs/heads/topic", "first");
+        delete_branches(&dir, &["topic".into()], &options).unwrap();
+        let second = commit(&repo, "refs/heads/topic", "second");
+        delete_branches(&dir, &["topic".into()], &options).unwrap();
+
+        let archives = || {
+            let mut archives = repo
+                .references_glob("refs/archive/topic/*")
+                .unwrap()
+                .names()
+                .map(|name| name.unwrap().to_owned())
+                .collect::<Vec<_>>();
+            archives.sort_unstable();
+            archives
+        };
+        assert_eq!(archives().len(), 2);
+        assert!(repo.find_branch("topic", BranchType::Local).is_err());
+        // the branch's reflog went with it, and is kept by its archive
+        assert!(repo.reflog("refs/heads/topic").unwrap().is_empty());
+        assert!(!repo.reflog(&archives()[1]).unwrap().is_empty());
+
+        archive::restore(&dir, "topic", false).unwrap();
+        assert_eq!(repo.refname_to_id("refs/heads/topic").unwrap(), second);
+        assert!(!repo.reflog("refs/heads/topic").unwrap().is_empty());
+        let earlier = archives();
+        assert_eq!(earlier.len(), 1);
+        assert_eq!(repo.refname_to_id(&earlier[0]).unwrap(), first);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn journals_deleted_branches_for_recovery() {
+        let (dir, repo) = scratch_repo("journal");
+        let tip = commit(&repo, "refs/heads/topic", "topic");
+        commit(&repo, "refs/heads/other", "other");
+        delete_branches(&dir, &["topic".into(), "other".into()], &Options::default()).unwrap();
+        assert!(repo.find_branch("topic", BranchType::Local).is_err());
+        assert!(repo.reflog("refs/heads/topic").unwrap().is_empty());
+
+        let journal = preflight::common_dir(&repo)
+            .join("git-clean")
+            .join("journal");
+        let run_id = std::fs::read_dir(journal)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .file_name()
+            .into_string()
+            .unwrap();
+        let recovered = journal::recover(&dir, &run_id, Some("top*"), None, false).unwrap();
+        assert_eq!(recovered, ["refs/heads/topic"]);
+        assert_eq!(repo.refname_to_id("refs/heads/topic").unwrap(), tip);
+        assert!(!repo.reflog("refs/heads/topic").unwrap().is_empty());
+        assert!(repo.find_branch("other", BranchType::Local).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
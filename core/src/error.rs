@@ -0,0 +1,294 @@
+use std::fmt;
+
+/// Broad category of a failure, for callers which need to react to failures
+/// rather than just display them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The token is missing, invalid, or lacks a permission or authorization.
+    Auth,
+    /// The forge refused the request because of rate limiting.
+    RateLimited,
+    /// The forge could not be reached, or responded unexpectedly.
+    Network,
+    /// The local repository is not in a state we can work with.
+    GitState,
+    /// A configured policy or safeguard prevented the operation.
+    Policy,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::Auth => "auth",
+            ErrorKind::RateLimited => "rate-limited",
+            ErrorKind::Network => "network",
+            ErrorKind::GitState => "git-state",
+            ErrorKind::Policy => "policy",
+        })
+    }
+}
+
+/// Classify an error returned by the GitHub API.
+#[cfg(feature = "engine")]
+fn github_error_kind(err: &octocrab::Error) -> ErrorKind {
+    let octocrab::Error::GitHub { source, .. } = err else {
+        return ErrorKind::Network;
+    };
+    let message = source.message.to_lowercase();
+    if message.contains("rate limit") {
+        ErrorKind::RateLimited
+    } else if message.contains("credentials")
+        || message.contains("authentication")
+        || message.contains("not accessible")
+        || message.contains("saml")
+    {
+        ErrorKind::Auth
+    } else {
+        ErrorKind::Network
+    }
+}
+
+/// An error which aborts a whole run.
+///
+/// Errors affecting only a single branch don't abort the run: they are
+/// recorded against that branch as a [`BranchIssue`] instead.
+#[cfg(feature = "engine")]
+#[derive(Debug, thiserror::Error)]
+pub enum FatalError {
+    #[error("{context}")]
+    Git {
+        context: String,
+        #[source]
+        inner: git2::Error,
+    },
+    #[error("{context}")]
+    Github {
+        context: String,
+        #[source]
+        inner: octocrab::Error,
+    },
+    #[error("wrong number of remotes: expected 1, have {0}")]
+    WrongRemoteCount(usize),
+    #[error("inexpressable remote: remote name was not utf-8")]
+    InexpressableRemote,
+    #[error("remote url not utf-8")]
+    RemoteUrlNotUtf8,
+    #[error("remote url not recognized as github, gitlab or bitbucket")]
+    RemoteUrlNotGithub,
+    #[error("remote url not recognized as a {0} repository")]
+    RemoteUrlNotProvider(crate::forge::Provider),
+    #[error("no remote named {0}")]
+    NoSuchRemote(String),
+    #[error("integration branch not found: {0}")]
+    IntegrationBranchNotFound(String),
+    #[error("no integration branch: configure one, or create `main` or `master`")]
+    NoIntegrationBranch,
+    #[error(
+        "plan {actual} does not match approved plan {approved}; run a dry run again and review it"
+    )]
+    PlanNotApproved { approved: String, actual: String },
+    #[error("invalid glob pattern: {pattern}")]
+    InvalidGlob {
+        pattern: String,
+        #[source]
+        inner: globset::Error,
+    },
+    #[error("token is not authorized for this organization's SAML SSO; authorize it at {url}")]
+    SsoAuthorizationRequired { url: String },
+    #[error("{} planned deletions would fail, so none were made: {}", .0.len(), .0.join(", "))]
+    PreflightFailed(Vec<String>),
+    #[error("personal access token contains invalid characters")]
+    InvalidToken,
+    #[error("every token has hit its rate limit")]
+    RateLimited,
+    #[error("failed to apply branch deletions; no branches were deleted")]
+    DeletionTransaction(#[source] git2::Error),
+    #[error("branch name not utf-8")]
+    BranchNameNotUtf8,
+    #[error("invalid GitHub API base url: {0}")]
+    InvalidApiBaseUrl(String),
+    #[error("invalid git config `{key}`: {message}")]
+    InvalidGitConfig { key: String, message: String },
+    #[error("{context}: {message}")]
+    GitLab {
+        context: String,
+        /// The response's status, if there was a response.
+        status: Option<u16>,
+        message: String,
+    },
+    #[error("{context}: {message}")]
+    Bitbucket {
+        context: String,
+        /// The response's status, if there was a response.
+        status: Option<u16>,
+        message: String,
+    },
+    #[error("aborted; no branches were deleted")]
+    Aborted,
+    #[error(
+        "refusing to delete all {0} branches besides the default branch; no branches were deleted"
+    )]
+    WouldDeleteAll(usize),
+    #[error("refusing to delete {planned} branches, more than the maximum of {max}; no branches were deleted")]
+    TooManyDeletions { planned: usize, max: usize },
+    #[error("unexpected response from GitHub: {0}")]
+    UnexpectedResponse(String),
+    #[error("{context}")]
+    Cassette {
+        context: String,
+        #[source]
+        inner: std::io::Error,
+    },
+    #[error("{context}")]
+    Journal {
+        context: String,
+        #[source]
+        inner: std::io::Error,
+    },
+    #[error("no journaled run {run_id}; journaled runs: {}", if .runs.is_empty() { "none".to_owned() } else { .runs.join(", ") })]
+    NoSuchRun { run_id: String, runs: Vec<String> },
+    #[error("branch {branch_name} is not archived; archived branches: {}", if .archived.is_empty() { "none".to_owned() } else { .archived.join(", ") })]
+    NotArchived {
+        branch_name: String,
+        archived: Vec<String>,
+    },
+    #[error("the commits of {} were pruned, and no remote has them; nothing was recovered", .0.join(", "))]
+    CommitsPruned(Vec<String>),
+    #[error("refusing to delete protected, excluded or default branches: {}; no branches were deleted", .0.join(", "))]
+    BranchesProtected(Vec<String>),
+    #[error("{0} needs a personal access token, to know who you are")]
+    TokenRequired(&'static str),
+    #[error("failed to read the config file")]
+    ConfigFile(#[source] crate::config::Error),
+}
+
+#[cfg(feature = "engine")]
+impl FatalError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FatalError::Github { inner, .. } => github_error_kind(inner),
+            FatalError::GitLab { status, .. } | FatalError::Bitbucket { status, .. } => {
+                match status {
+                    Some(401 | 403) => ErrorKind::Auth,
+                    Some(429) => ErrorKind::RateLimited,
+                    _ => ErrorKind::Network,
+                }
+            }
+            FatalError::UnexpectedResponse(_) => ErrorKind::Network,
+            FatalError::SsoAuthorizationRequired { .. }
+            | FatalError::InvalidToken
+            | FatalError::TokenRequired(_) => ErrorKind::Auth,
+            FatalError::RateLimited => ErrorKind::RateLimited,
+            FatalError::PlanNotApproved { .. }
+            | FatalError::Aborted
+            | FatalError::WouldDeleteAll(_)
+            | FatalError::TooManyDeletions { .. }
+            | FatalError::BranchesProtected(_)
+            | FatalError::InvalidGlob { .. }
+            | FatalError::InvalidApiBaseUrl(_)
+            | FatalError::InvalidGitConfig { .. }
+            | FatalError::ConfigFile(_) => ErrorKind::Policy,
+            FatalError::Git { .. }
+            | FatalError::WrongRemoteCount(_)
+            | FatalError::InexpressableRemote
+            | FatalError::RemoteUrlNotUtf8
+            | FatalError::RemoteUrlNotGithub
+            | FatalError::RemoteUrlNotProvider(_)
+            | FatalError::NoSuchRemote(_)
+            | FatalError::IntegrationBranchNotFound(_)
+            | FatalError::NoIntegrationBranch
+            | FatalError::PreflightFailed(_)
+            | FatalError::DeletionTransaction(_)
+            | FatalError::BranchNameNotUtf8
+            | FatalError::Cassette { .. }
+            | FatalError::Journal { .. }
+            | FatalError::NoSuchRun { .. }
+            | FatalError::CommitsPruned(_)
+            | FatalError::NotArchived { .. } => ErrorKind::GitState,
+        }
+    }
+}
+
+/// A problem which prevented a decision about a single branch.
+///
+/// The branch is retained, and the run carries on with the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchIssue {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl BranchIssue {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The error message, including its chain of causes.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+#[cfg(feature = "engine")]
+impl From<FatalError> for BranchIssue {
+    fn from(err: FatalError) -> Self {
+        let mut message = err.to_string();
+        use std::error::Error as _;
+
+        let mut source = err.source();
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        Self {
+            kind: err.kind(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for BranchIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.kind)
+    }
+}
+
+impl std::error::Error for BranchIssue {}
+
+/// Convert a library error into our error type, with context
+#[cfg(feature = "engine")]
+pub trait ContextErr {
+    type Ok;
+    fn context<S>(self, s: S) -> Result<Self::Ok, FatalError>
+    where
+        S: ToString;
+}
+
+#[cfg(feature = "engine")]
+impl<T> ContextErr for Result<T, git2::Error> {
+    type Ok = T;
+    fn context<S>(self, s: S) -> Result<<Self as ContextErr>::Ok, FatalError>
+    where
+        S: ToString,
+    {
+        self.map_err(|inner| FatalError::Git {
+            context: s.to_string(),
+            inner,
+        })
+    }
+}
+
+#[cfg(feature = "engine")]
+impl<T> ContextErr for Result<T, octocrab::Error> {
+    type Ok = T;
+    fn context<S>(self, s: S) -> Result<<Self as ContextErr>::Ok, FatalError>
+    where
+        S: ToString,
+    {
+        self.map_err(|inner| FatalError::Github {
+            context: s.to_string(),
+            inner,
+        })
+    }
+}
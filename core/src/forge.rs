@@ -0,0 +1,128 @@
+//! Where branches' pull requests are looked up, for embedders who bring
+//! their own: a mock for tests, or a forge we don't know about.
+
+use std::{fmt, str::FromStr};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{bitbucket::Bitbucket, gitlab::GitLab, policy::PullRequestFacts, FatalError, SmallStr};
+
+/// A forge which knows a repository's default branch and its pull requests.
+///
+/// Pass one to [`clean_branches_with`](crate::clean_branches_with). The
+/// futures it returns are boxed so that it can be used as a trait object;
+/// `Box::pin(async move { ... })` makes one.
+pub trait ForgeProvider: Send + Sync {
+    /// The repository's default branch, which is never deleted.
+    fn default_branch(&self) -> BoxFuture<'_, Result<Option<SmallStr>, FatalError>>;
+
+    /// Every pull request, open or closed, whose head is `branch_name`.
+    fn pull_requests<'a>(
+        &'a self,
+        branch_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<PullRequestFacts>, FatalError>>;
+}
+
+impl<T: ForgeProvider + ?Sized> ForgeProvider for &T {
+    fn default_branch(&self) -> BoxFuture<'_, Result<Option<SmallStr>, FatalError>> {
+        (**self).default_branch()
+    }
+
+    fn pull_requests<'a>(
+        &'a self,
+        branch_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<PullRequestFacts>, FatalError>> {
+        (**self).pull_requests(branch_name)
+    }
+}
+
+/// A forge git-clean knows how to ask, to say which one hosts a remote when
+/// its url doesn't, such as a self-hosted GitLab on an unusual host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl Provider {
+    const VARIANTS: &'static [(&'static str, Provider)] = &[
+        ("github", Provider::GitHub),
+        ("gitlab", Provider::GitLab),
+        ("bitbucket", Provider::Bitbucket),
+    ];
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = Self::VARIANTS
+            .iter()
+            .find(|(_, variant)| variant == self)
+            .expect("every variant is listed");
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::VARIANTS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, variant)| *variant)
+            .ok_or_else(|| "expected one of: github, gitlab, bitbucket".to_owned())
+    }
+}
+
+/// Split a remote url, in any of the forms git accepts, into its host and
+/// the repository's path on it, without any `.git` suffix.
+pub(crate) fn split_remote_url(url: &str) -> Option<(&str, &str)> {
+    let (host, path) = if let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("ssh://"))
+    {
+        let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+        let (host, path) = rest.split_once('/')?;
+        // an ssh port is not the API's port
+        let host = host.split_once(':').map_or(host, |(host, _)| host);
+        (host, path)
+    } else {
+        // scp-like: git@host:path
+        let (user_host, path) = url.split_once(':')?;
+        let host = user_host
+            .rsplit_once('@')
+            .map_or(user_host, |(_, host)| host);
+        (host, path)
+    };
+    Some((host, path.trim_end_matches('/').trim_end_matches(".git")))
+}
+
+impl ForgeProvider for GitLab {
+    fn default_branch(&self) -> BoxFuture<'_, Result<Option<SmallStr>, FatalError>> {
+        Box::pin(GitLab::default_branch(self))
+    }
+
+    fn pull_requests<'a>(
+        &'a self,
+        branch_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<PullRequestFacts>, FatalError>> {
+        Box::pin(self.merge_requests(branch_name))
+    }
+}
+
+impl ForgeProvider for Bitbucket {
+    fn default_branch(&self) -> BoxFuture<'_, Result<Option<SmallStr>, FatalError>> {
+        Box::pin(Bitbucket::default_branch(self))
+    }
+
+    fn pull_requests<'a>(
+        &'a self,
+        branch_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<PullRequestFacts>, FatalError>> {
+        Box::pin(Bitbucket::pull_requests(self, branch_name))
+    }
+}
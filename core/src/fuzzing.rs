@@ -0,0 +1,33 @@
+//! Parsers exercised by the fuzz targets in `fuzz/`.
+//!
+//! Only built with `--cfg fuzzing`, as cargo-fuzz passes, so this is no part
+//! of the API.
+
+use crate::{bitbucket, engine, forge, gitlab};
+
+pub fn split_remote_url(url: &str) -> Option<(&str, &str)> {
+    forge::split_remote_url(url)
+}
+
+/// The owner and name of a GitHub repository on `host`.
+pub fn parse_git_url(url: &str, host: &str) -> Option<(String, String)> {
+    engine::parse_git_url(url, host).map(|(owner, repo)| (owner.to_string(), repo.to_string()))
+}
+
+pub fn rewrite_git_url(url: &str, host: &str, owner: &str, repo_name: &str) -> Option<String> {
+    engine::rewrite_git_url(url, host, owner, repo_name)
+}
+
+/// The host and path of a GitLab project.
+pub fn gitlab_project(url: &str, hosts: &[String]) -> Option<(String, String)> {
+    gitlab::Project::from_url(url, hosts).map(|project| (project.host, project.path))
+}
+
+/// The workspace and slug of a Bitbucket repository.
+pub fn bitbucket_repository(url: &str) -> Option<(String, String)> {
+    bitbucket::Repository::from_url(url).map(|repository| (repository.workspace, repository.slug))
+}
+
+pub fn pr_search_query(owner: &str, repo_name: &str, branch_name: &str) -> String {
+    engine::pr_search_query(owner, repo_name, branch_name)
+}
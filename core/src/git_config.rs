@@ -0,0 +1,232 @@
+//! Defaults from git's own config, in its `[git-clean]` section.
+//!
+//! git resolves `includeIf "gitdir:..."` while reading config for a
+//! repository, so a section included only for `~/work/` applies to every
+//! repository there, without a file in each:
+//!
+//! ```text
+//! [includeIf "gitdir:~/work/"]
+//!     path = ~/.gitconfig-work
+//! ```
+//!
+//! with `~/.gitconfig-work` holding, say:
+//!
+//! ```text
+//! [git-clean]
+//!     apiBaseUrl = https://github.example.com/api/v3
+//!     minConfidence = medium
+//!     requireMerged = true
+//! ```
+//!
+//! `apiBaseUrl` and `gitlabHost` are only taken from the system, XDG and
+//! global config, and what they include: a cloned repository's own
+//! `.git/config` could otherwise send the user's tokens to a server of its
+//! choosing.
+
+use std::{path::Path, str::FromStr};
+
+use git2::{Config, ConfigLevel, ErrorCode};
+
+use crate::{ancestry, error::ContextErr, logging, FatalError, Options};
+
+impl Options {
+    /// Fill in what git's config says for the repository at `path`, under
+    /// `[git-clean]`.
+    ///
+    /// Options which are already set are left as they are, except for
+    /// `apiBaseUrl`: config for a repository is more specific than the
+    /// library's caller, which usually takes it from the user's config file.
+    /// It is ignored, with a warning, where the repository itself sets it, as
+    /// is `gitlabHost`. Flags can only be turned on, and `gitlabHost`,
+    /// `include` and `exclude`, which may be given more than once, add to the
+    /// lists already given.
+    pub fn apply_git_config(&mut self, path: impl AsRef<Path>) -> Result<(), FatalError> {
+        let logger = logging::logger();
+        let repo = ancestry::open_repository(path)?;
+        let config = repo
+            .config()
+            .and_then(|mut config| config.snapshot())
+            .context("read git config")?;
+
+        if let Some(url) = outside_repo(&config, "git-clean.apiBaseUrl", &logger)?.pop() {
+            slog::debug!(logger, "default from git config"; "key" => "apiBaseUrl", "value" => &url);
+            self.api_base_url = Some(url);
+        }
+        if self.min_confidence.is_none() {
+            self.min_confidence = parse(&config, "git-clean.minConfidence")?;
+        }
+        if self.require_vetting.is_none() {
+            self.require_vetting = parse(&config, "git-clean.requireVetting")?;
+        }
+        if self.integration_branch.is_none() {
+            self.integration_branch =
+                get(&config, "git-clean.integrationBranch", Config::get_string)?;
+        }
+        for (key, flag) in [
+            ("git-clean.requireMerged", &mut self.require_merged),
+            (
+                "git-clean.keepReviewRequests",
+                &mut self.keep_review_requests,
+            ),
+            ("git-clean.grepReferences", &mut self.grep_references),
+        ] {
+            *flag |= get(&config, key, Config::get_bool)?.unwrap_or_default();
+        }
+        let gitlab_hosts = outside_repo(&config, "git-clean.gitlabHost", &logger)?;
+        for (values, given) in [
+            (&mut self.gitlab_hosts, gitlab_hosts),
+            (
+                &mut self.include_branches,
+                get_all(&config, "git-clean.include")?,
+            ),
+            (
+                &mut self.exclude_branches,
+                get_all(&config, "git-clean.exclude")?,
+            ),
+        ] {
+            for value in given {
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every value of `key` set outside of the repository, in order, so that
+/// the last one given is last.
+///
+/// Tokens are sent wherever `apiBaseUrl` points, and to the hosts
+/// `gitlabHost` names, so a repository must not be able to choose those.
+fn outside_repo(
+    config: &Config,
+    key: &str,
+    logger: &slog::Logger,
+) -> Result<Vec<String>, FatalError> {
+    let mut values = Vec::new();
+    let pattern = format!("^{}$", key.to_ascii_lowercase().replace('.', "\\."));
+    let entries = config.entries(Some(&pattern)).context("read git config")?;
+    entries
+        .for_each(|entry| match entry.level() {
+            ConfigLevel::ProgramData
+            | ConfigLevel::System
+            | ConfigLevel::XDG
+            | ConfigLevel::Global => values.extend(entry.value().map(str::to_owned)),
+            _ => {
+                slog::warn!(
+                    logger, "ignoring setting made by the repository's own git config";
+                    "key" => key,
+                    "value" => entry.value(),
+                    "hint" => "set it in the global config, or with an includeIf there",
+                );
+            }
+        })
+        .context("read git config")?;
+    Ok(values)
+}
+
+/// Every value of `key`, which may be given more than once.
+fn get_all(config: &Config, key: &str) -> Result<Vec<String>, FatalError> {
+    let mut values = Vec::new();
+    if let Ok(entries) = config.multivar(key, None) {
+        entries
+            .for_each(|entry| {
+                if let Some(value) = entry.value() {
+                    values.push(value.to_owned());
+                }
+            })
+            .context("read git config")?;
+    }
+    Ok(values)
+}
+
+/// The value of `key`, or `None` if it isn't set.
+fn get<T>(
+    config: &Config,
+    key: &str,
+    read: impl Fn(&Config, &str) -> Result<T, git2::Error>,
+) -> Result<Option<T>, FatalError> {
+    match read(config, key) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(FatalError::InvalidGitConfig {
+            key: key.to_owned(),
+            message: err.message().to_owned(),
+        }),
+    }
+}
+
+fn parse<T: FromStr<Err = String>>(config: &Config, key: &str) -> Result<Option<T>, FatalError> {
+    get(config, key, Config::get_string)?
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|message| FatalError::InvalidGitConfig {
+                    key: key.to_owned(),
+                    message,
+                })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Repository;
+
+    use super::*;
+
+    #[test]
+    fn reads_partial_clones() {
+        let dir = std::env::temp_dir().join(format!("git-clean-partial-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_i32("core.repositoryformatversion", 1).unwrap();
+        config.set_str("extensions.partialclone", "origin").unwrap();
+        config
+            .set_str("git-clean.integrationBranch", "trunk")
+            .unwrap();
+        drop(repo);
+
+        let mut options = Options::default();
+        options.apply_git_config(&dir).unwrap();
+        assert_eq!(options.integration_branch.as_deref(), Some("trunk"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ignores_token_destinations_set_by_the_repository() {
+        let dir = std::env::temp_dir().join(format!("git-clean-levels-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let global = dir.join("global");
+        let local = dir.join("local");
+        std::fs::write(
+            &global,
+            "[git-clean]\n\tapiBaseUrl = https://github.example.com/api/v3\n\tgitlabHost = code.example.com\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &local,
+            "[git-clean]\n\tapiBaseUrl = https://attacker.example\n\tgitlabHost = gitlab.attacker.example\n",
+        )
+        .unwrap();
+        let mut config = Config::new().unwrap();
+        config
+            .add_file(&global, ConfigLevel::Global, false)
+            .unwrap();
+        config.add_file(&local, ConfigLevel::Local, false).unwrap();
+
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        assert_eq!(
+            outside_repo(&config, "git-clean.apiBaseUrl", &logger).unwrap(),
+            ["https://github.example.com/api/v3"]
+        );
+        assert_eq!(
+            outside_repo(&config, "git-clean.gitlabHost", &logger).unwrap(),
+            ["code.example.com"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
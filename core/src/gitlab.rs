@@ -0,0 +1,186 @@
+//! Looking up merge requests on GitLab, for repositories hosted there.
+//!
+//! Only what the cleaning policy needs is supported: the default branch, and
+//! the merge requests whose source is a branch. GitLab's own token is taken
+//! from `GITLAB_TOKEN`; without one, only public projects can be read.
+
+use http::Request;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{policy::PullRequestFacts, FatalError, SmallStr};
+
+const TOKEN_VAR: &str = "GITLAB_TOKEN";
+
+/// A project on a GitLab instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Project {
+    pub host: String,
+    /// The full path of the project, including any subgroups.
+    pub path: String,
+}
+
+impl Project {
+    /// Recognize a remote url as pointing at GitLab: either gitlab.com or
+    /// one of `hosts`.
+    ///
+    /// Nothing is guessed from a host's name, since `GITLAB_TOKEN` is sent to
+    /// whichever host is recognized.
+    pub fn from_url(url: &str, hosts: &[String]) -> Option<Self> {
+        let (host, path) = crate::forge::split_remote_url(url)?;
+        let is_gitlab = host == "gitlab.com" || hosts.iter().any(|known| known == host);
+        (is_gitlab && path.contains('/')).then(|| Self {
+            host: host.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectInfo {
+    default_branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    message: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+/// A client for one project's API.
+pub(crate) struct GitLab {
+    /// The project's API endpoint.
+    base: String,
+    token: Option<String>,
+}
+
+impl GitLab {
+    pub fn new(project: &Project) -> Self {
+        let token = std::env::var(TOKEN_VAR)
+            .ok()
+            .filter(|token| !token.is_empty());
+        if let Some(token) = &token {
+            crate::redact::register(token);
+        }
+        Self {
+            base: format!(
+                "https://{}/api/v4/projects/{}",
+                project.host,
+                utf8_percent_encode(&project.path, NON_ALPHANUMERIC)
+            ),
+            token,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, context: &str) -> Result<T, FatalError> {
+        let error = |status: Option<u16>, message: String| FatalError::GitLab {
+            context: context.to_owned(),
+            status,
+            message,
+        };
+
+        let mut request = Request::get(format!("{}{path}", self.base));
+        if let Some(token) = &self.token {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+        let request = request
+            .body(String::new())
+            .map_err(|err| error(None, err.to_string()))?;
+        let (status, body) = crate::http::send(request)
+            .await
+            .map_err(|err| error(None, err.to_string()))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ErrorBody>(&body)
+                .ok()
+                .and_then(|body| {
+                    body.error
+                        .or_else(|| body.message.map(|message| message.to_string()))
+                })
+                .unwrap_or_else(|| status.to_string());
+            return Err(error(Some(status.as_u16()), message));
+        }
+        serde_json::from_slice(&body).map_err(|err| error(None, err.to_string()))
+    }
+
+    pub async fn default_branch(&self) -> Result<Option<SmallStr>, FatalError> {
+        let info: ProjectInfo = self.get("", "get project").await?;
+        Ok(info.default_branch.map(SmallStr::from_string))
+    }
+
+    /// All merge requests whose source is `branch_name`.
+    ///
+    /// Merge requests from forks are included: their source branch may share
+    /// the name, just as on GitHub.
+    pub async fn merge_requests(
+        &self,
+        branch_name: &str,
+    ) -> Result<Vec<PullRequestFacts>, FatalError> {
+        let mut facts = Vec::new();
+        for page in 1.. {
+            let merge_requests: Vec<MergeRequest> = self
+                .get(
+                    &format!(
+                        "/merge_requests?state=all&per_page=100&page={page}&source_branch={}",
+                        utf8_percent_encode(branch_name, NON_ALPHANUMERIC)
+                    ),
+                    "list merge requests for branch",
+                )
+                .await?;
+            let done = merge_requests.len() < 100;
+            facts.extend(merge_requests.into_iter().map(|mr| PullRequestFacts {
+                number: mr.iid,
+                // `locked` is a merge in progress
+                open: matches!(mr.state.as_str(), "opened" | "locked"),
+                merged: Some(mr.state == "merged"),
+            }));
+            if done {
+                break;
+            }
+        }
+        Ok(facts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_gitlab_urls() {
+        let project = |host: &str, path: &str| {
+            Some(Project {
+                host: host.into(),
+                path: path.into(),
+            })
+        };
+        assert_eq!(
+            Project::from_url("git@gitlab.com:group/sub/project.git", &[]),
+            project("gitlab.com", "group/sub/project")
+        );
+        assert_eq!(
+            Project::from_url("https://gitlab.com/group/project", &[]),
+            project("gitlab.com", "group/project")
+        );
+        assert_eq!(
+            Project::from_url(
+                "ssh://git@code.example.com:2222/team/project.git",
+                &["code.example.com".into()]
+            ),
+            project("code.example.com", "team/project")
+        );
+        assert_eq!(
+            Project::from_url("git@github.com:coriolinus/git-clean.git", &[]),
+            None
+        );
+        assert_eq!(
+            Project::from_url("https://gitlab.example.net/group/project", &[]),
+            None
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Branches whose upstream is gone: deleted on the remote, as GitHub does
+//! after merging when asked to, and since pruned locally.
+//!
+//! This is the `[gone]` of `git branch -vv`. Remote-tracking branches only
+//! disappear when pruned, so it needs a `git fetch --prune` to be current.
+
+use git2::{Branch, BranchType, ErrorCode, Oid, Repository};
+
+use crate::{
+    ancestry::{self, PatchIndex},
+    error::ContextErr,
+    report::{Confidence, Decision, Reason},
+    FatalError, SmallStr,
+};
+
+/// Decide for each local branch whether its upstream is gone, in which case
+/// it is deleted.
+///
+/// Branches whose work has also landed in `integration` are deleted with high
+/// confidence; the rest with low, since a remote branch may be deleted
+/// without being merged. But those with commits on no remote-tracking branch
+/// and not in `integration` are retained: they were committed after the
+/// remote branch was deleted, or it was deleted unmerged, and would be lost.
+pub(crate) fn gone_branches(
+    repo: &Repository,
+    integration: Option<&Branch>,
+    considered: &dyn Fn(&str) -> bool,
+    logger: &slog::Logger,
+) -> Result<Vec<Decision>, FatalError> {
+    let integration_name = integration.and_then(|branch| branch.name().ok().flatten());
+    let integration_tip = integration
+        .and_then(|branch| branch.get().target())
+        .filter(|_| !repo.is_shallow());
+    let mut patches = integration_tip
+        .filter(|_| !ancestry::is_partial_clone(repo))
+        .map(|tip| PatchIndex::new(repo, tip));
+
+    let mut decisions = Vec::new();
+    for (branch, _branch_type) in repo
+        .branches(Some(BranchType::Local))
+        .context("list local branches")?
+        .filter_map(Result::ok)
+    {
+        let (Some(branch_name), Some(refname)) =
+            (branch.name().ok().flatten(), branch.get().name())
+        else {
+            continue;
+        };
+        if Some(branch_name) == integration_name || !considered(branch_name) {
+            continue;
+        }
+        let branch_name_small = SmallStr::from_str(branch_name);
+
+        let upstream = match repo.branch_upstream_name(refname) {
+            Ok(upstream) => upstream,
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                slog::debug!(logger, "retaining branch"; "branch name" => branch_name, "reason" => "no upstream");
+                decisions.push(Decision::retain(branch_name_small, Reason::NoUpstream));
+                continue;
+            }
+            Err(err) => return Err(err).context("get upstream of branch"),
+        };
+        let Some(upstream) = upstream.as_str() else {
+            continue;
+        };
+        if repo.find_reference(upstream).is_ok() {
+            slog::debug!(logger, "retaining branch"; "branch name" => branch_name, "reason" => "upstream exists");
+            decisions.push(Decision::retain(branch_name_small, Reason::UpstreamExists));
+            continue;
+        }
+
+        let landed = match (integration_tip, branch.get().target()) {
+            (Some(integration_tip), Some(tip)) => {
+                ancestry::contains(repo, integration_tip, tip)?
+                    || patches
+                        .as_mut()
+                        .is_some_and(|patches| patches.squashed(tip))
+            }
+            _ => false,
+        };
+        let confidence = if landed {
+            Confidence::High
+        } else {
+            let tip = branch.get().target();
+            match tip
+                .map(|tip| only_local(repo, tip, integration_tip))
+                .transpose()?
+            {
+                Some(commits @ 1..) => {
+                    let reason = Reason::Unpushed(commits);
+                    slog::info!(
+                        logger, "retaining branch whose upstream is gone";
+                        "branch name" => branch_name,
+                        "reason" => %reason,
+                    );
+                    decisions.push(Decision::retain(branch_name_small, reason));
+                    continue;
+                }
+                _ => Confidence::Low,
+            }
+        };
+        slog::info!(
+            logger, "deleting branch whose upstream is gone";
+            "branch name" => branch_name,
+            "upstream" => upstream,
+        );
+        decisions.push(Decision::delete(branch_name_small, confidence));
+    }
+
+    Ok(decisions)
+}
+
+/// How many commits from `tip` are on no remote-tracking branch, and not in
+/// `integration_tip` either.
+fn only_local(
+    repo: &Repository,
+    tip: Oid,
+    integration_tip: Option<Oid>,
+) -> Result<usize, FatalError> {
+    let mut walk = repo.revwalk().context("walk local commits")?;
+    walk.push(tip).context("walk local commits")?;
+    walk.hide_glob("refs/remotes/*")
+        .context("walk local commits")?;
+    if let Some(integration_tip) = integration_tip {
+        walk.hide(integration_tip).context("walk local commits")?;
+    }
+    Ok(walk.count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{commit, scratch_repo},
+        Outcome,
+    };
+
+    /// Give `branch_name` an upstream of the same name on `origin`, which
+    /// doesn't exist.
+    fn track_gone(repo: &Repository, branch_name: &str) {
+        let mut config = repo.config().unwrap();
+        config
+            .set_str(&format!("branch.{branch_name}.remote"), "origin")
+            .unwrap();
+        config
+            .set_str(
+                &format!("branch.{branch_name}.merge"),
+                &format!("refs/heads/{branch_name}"),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn retains_commits_on_no_remote() {
+        let (dir, repo) = scratch_repo("gone");
+        repo.remote("origin", "https://example.com/team/repo.git")
+            .unwrap();
+        let main = repo.refname_to_id("refs/heads/main").unwrap();
+        for branch_name in ["landed", "pushed", "unpushed"] {
+            repo.reference(&format!("refs/heads/{branch_name}"), main, false, "")
+                .unwrap();
+            track_gone(&repo, branch_name);
+        }
+        // still on another remote branch, which may be merged yet
+        let pushed = commit(&repo, "refs/heads/pushed", "pushed");
+        repo.reference("refs/remotes/origin/other/pushed", pushed, false, "")
+            .unwrap();
+        // committed after the remote branch was deleted
+        commit(&repo, "refs/heads/unpushed", "unpushed");
+
+        let integration = repo.find_branch("main", BranchType::Local).unwrap();
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let decisions = gone_branches(&repo, Some(&integration), &|_| true, &logger).unwrap();
+        let outcome = |branch_name: &str| {
+            decisions
+                .iter()
+                .find(|decision| decision.branch_name == branch_name)
+                .map(|decision| decision.outcome.clone())
+                .unwrap()
+        };
+        assert!(matches!(
+            outcome("landed"),
+            Outcome::Delete {
+                confidence: Confidence::High,
+                ..
+            }
+        ));
+        assert!(matches!(
+            outcome("pushed"),
+            Outcome::Delete {
+                confidence: Confidence::Low,
+                ..
+            }
+        ));
+        assert!(matches!(
+            outcome("unpushed"),
+            Outcome::Retain(Reason::Unpushed(1))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! Permission to modify anything, which a dry run never has.
+//!
+//! Planning only ever sees the [`Repository`]. Modifying it takes a
+//! [`Mutator`], which only a [`DeletionGuard`] hands out, and modifying what
+//! the forge holds takes the guard itself, so that a dry run leaving
+//! everything as it was doesn't rest on each of them remembering to check.
+
+use git2::{Branch, Commit, Config, Oid, Reference, Repository, Transaction};
+
+/// Permission to modify the repository, or what the forge holds for it.
+#[derive(Debug)]
+pub(crate) struct DeletionGuard(());
+
+impl DeletionGuard {
+    /// Permission, unless this is a dry run.
+    pub(crate) fn new(dry_run: bool) -> Option<Self> {
+        (!dry_run).then_some(Self(()))
+    }
+
+    /// A handle to modify `repo` through.
+    pub(crate) fn mutator<'repo>(&self, repo: &'repo Repository) -> Mutator<'repo> {
+        Mutator { repo }
+    }
+}
+
+/// A repository which may be modified.
+///
+/// Every change git-clean makes to a repository goes through one of these,
+/// including those to the files it keeps in the git directory, such as the
+/// journal.
+pub(crate) struct Mutator<'repo> {
+    repo: &'repo Repository,
+}
+
+impl<'repo> Mutator<'repo> {
+    /// The repository, to read from.
+    pub fn repo(&self) -> &'repo Repository {
+        self.repo
+    }
+
+    pub fn remote_set_url(&self, remote_name: &str, url: &str) -> Result<(), git2::Error> {
+        self.repo.remote_set_url(remote_name, url)
+    }
+
+    pub fn reference(
+        &self,
+        refname: &str,
+        oid: Oid,
+        force: bool,
+        log_message: &str,
+    ) -> Result<Reference<'repo>, git2::Error> {
+        self.repo.reference(refname, oid, force, log_message)
+    }
+
+    pub fn reference_symbolic(
+        &self,
+        refname: &str,
+        target: &str,
+        force: bool,
+        log_message: &str,
+    ) -> Result<Reference<'repo>, git2::Error> {
+        self.repo
+            .reference_symbolic(refname, target, force, log_message)
+    }
+
+    /// Delete the ref `refname`, and its reflog.
+    pub fn delete_reference(&self, refname: &str) -> Result<(), git2::Error> {
+        self.repo.find_reference(refname)?.delete()?;
+        self.repo.reflog_delete(refname)
+    }
+
+    pub fn reflog_delete(&self, refname: &str) -> Result<(), git2::Error> {
+        self.repo.reflog_delete(refname)
+    }
+
+    pub fn transaction(&self) -> Result<Transaction<'repo>, git2::Error> {
+        self.repo.transaction()
+    }
+
+    pub fn branch(
+        &self,
+        branch_name: &str,
+        target: &Commit<'_>,
+        force: bool,
+    ) -> Result<Branch<'repo>, git2::Error> {
+        self.repo.branch(branch_name, target, force)
+    }
+
+    pub fn rename_branch(
+        &self,
+        mut branch: Branch<'repo>,
+        new_name: &str,
+    ) -> Result<Branch<'repo>, git2::Error> {
+        branch.rename(new_name, false)
+    }
+
+    pub fn set_upstream(
+        &self,
+        branch: &mut Branch<'repo>,
+        upstream: &str,
+    ) -> Result<(), git2::Error> {
+        branch.set_upstream(Some(upstream))
+    }
+
+    /// The repository's config, to change.
+    pub fn config(&self) -> Result<Config, git2::Error> {
+        self.repo.config()
+    }
+}
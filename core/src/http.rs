@@ -0,0 +1,183 @@
+//! The HTTP stack underneath our GitHub clients.
+//!
+//! octocrab's default stack can't be extended, so we assemble an equivalent
+//! one ourselves. That lets us send a descriptive `User-Agent`, and log a
+//! summary of every request when asked to.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http::{
+    header::{AUTHORIZATION, USER_AGENT},
+    HeaderValue, Request, Response, StatusCode, Uri,
+};
+use hyper::client::HttpConnector;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use octocrab::{
+    service::middleware::{
+        base_uri::BaseUriLayer, extra_headers::ExtraHeadersLayer, retry::RetryConfig,
+    },
+    AuthState, Octocrab, OctocrabBuilder,
+};
+use tower::{retry::RetryLayer, Layer, Service};
+use tower_http::follow_redirect::FollowRedirectLayer;
+
+use crate::{
+    cassette::{Cassette, TapeLayer},
+    FatalError,
+};
+
+pub(crate) const GITHUB_API: &str = "https://api.github.com";
+
+/// The `User-Agent` we identify ourselves with.
+const GIT_CLEAN_USER_AGENT: &str = concat!(
+    "git-clean/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/coriolinus/git-clean)"
+);
+
+/// How often a request is retried on server errors, as octocrab does.
+const RETRIES: usize = 3;
+
+/// Log a one-line summary of each request and its response.
+#[derive(Clone)]
+struct TraceLayer {
+    logger: Option<slog::Logger>,
+}
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = Trace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Trace {
+            inner,
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Trace<S> {
+    inner: S,
+    logger: Option<slog::Logger>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Trace<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: std::fmt::Display,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        // Only the method and path are ever logged: headers carry the token,
+        // and queries can be long.
+        let method = req.method().clone();
+        let path = req.uri().path().to_owned();
+        let logger = self.logger.clone();
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = response.await;
+            if let Some(logger) = logger {
+                match &response {
+                    Ok(response) => {
+                        let header = |name: &str| {
+                            response
+                                .headers()
+                                .get(name)
+                                .and_then(|value| value.to_str().ok())
+                                .unwrap_or("-")
+                                .to_owned()
+                        };
+                        slog::info!(
+                            logger, "http";
+                            "method" => %method,
+                            "path" => &path,
+                            "status" => response.status().as_u16(),
+                            "rate limit remaining" => header("x-ratelimit-remaining"),
+                            "rate limit resource" => header("x-ratelimit-resource"),
+                        );
+                    }
+                    Err(err) => {
+                        slog::info!(
+                            logger, "http";
+                            "method" => %method,
+                            "path" => &path,
+                            "err" => %err,
+                        );
+                    }
+                }
+            }
+            response
+        })
+    }
+}
+
+fn connector() -> HttpsConnector<HttpConnector> {
+    HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build()
+}
+
+/// Send a one-off request to something other than GitHub, such as a secret
+/// store or GitLab, returning the status and body of its response.
+pub(crate) async fn send(
+    mut request: Request<String>,
+) -> Result<(StatusCode, Vec<u8>), hyper::Error> {
+    request
+        .headers_mut()
+        .insert(USER_AGENT, HeaderValue::from_static(GIT_CLEAN_USER_AGENT));
+    let client = hyper::Client::builder().build::<_, String>(connector());
+    let (parts, body) = client.request(request).await?.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    Ok((parts.status, body.to_vec()))
+}
+
+/// Build a client for the GitHub API at `api`.
+///
+/// If `trace` is set, every request is summarized to it. If `cassette` is set,
+/// responses are recorded to it or replayed from it.
+pub(crate) fn build_client(
+    token: Option<&str>,
+    api: &Uri,
+    trace: Option<&slog::Logger>,
+    cassette: Option<&Cassette>,
+) -> Result<Octocrab, FatalError> {
+    let client = hyper::Client::builder().build::<_, String>(connector());
+
+    let mut headers = vec![(USER_AGENT, HeaderValue::from_static(GIT_CLEAN_USER_AGENT))];
+    if let Some(token) = token {
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(|_| FatalError::InvalidToken)?;
+        value.set_sensitive(true);
+        headers.push((AUTHORIZATION, value));
+    }
+
+    let octocrab = OctocrabBuilder::new_empty()
+        .with_service(client)
+        .with_layer(&TapeLayer(cassette.cloned()))
+        .with_layer(&RetryLayer::new(RetryConfig::Simple(RETRIES)))
+        .with_layer(&TraceLayer {
+            logger: trace.cloned(),
+        })
+        .with_layer(&FollowRedirectLayer::new())
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(headers)))
+        .with_layer(&BaseUriLayer::new(api.clone()))
+        .with_auth(AuthState::None)
+        .build()
+        .unwrap_or_else(|never| match never {});
+    Ok(octocrab)
+}
@@ -0,0 +1,298 @@
+//! A record of every ref about to be deleted, for recovering them exactly.
+//!
+//! Before a deletion, the refs being removed are written to
+//! `.git/git-clean/journal/<run id>/refs`, in the format of `packed-refs`,
+//! next to a copy of `packed-refs` as it was, the refs' reflogs, which git
+//! removes along with them, and the numbers of their pull requests, in
+//! `pulls`, a line of `<ref> <number>...` each. Deleted commits stay in the object store
+//! until `git gc` prunes them, two weeks later by default, so until then
+//! [`recover`] can put the refs back, with their reflogs. After that, it
+//! fetches them by id from the remotes, which still have any that were
+//! pushed.
+
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use chrono::Utc;
+use git2::{Oid, Repository};
+use globset::Glob;
+
+use crate::{
+    ancestry,
+    error::ContextErr,
+    guard::{DeletionGuard, Mutator},
+    logging, preflight, FatalError,
+};
+
+fn io_error(context: &str, path: &Path) -> impl FnOnce(std::io::Error) -> FatalError {
+    let context = format!("{context} {}", path.display());
+    move |inner| FatalError::Journal { context, inner }
+}
+
+fn journal_dir(repo: &Repository) -> PathBuf {
+    // shared by every worktree, like the refs themselves
+    preflight::common_dir(repo)
+        .join("git-clean")
+        .join("journal")
+}
+
+/// Journal `refnames`, with the numbers of any of their pull requests in
+/// `pull_requests`, before they are deleted, returning the run's id.
+///
+/// Refs deleted within the same second are journaled under the same id.
+pub(crate) fn record(
+    mutator: &Mutator,
+    refnames: &[String],
+    pull_requests: &HashMap<String, Vec<u64>>,
+) -> Result<String, FatalError> {
+    let repo = mutator.repo();
+    let run_id = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let dir = journal_dir(repo).join(&run_id);
+    fs::create_dir_all(&dir).map_err(io_error("create", &dir))?;
+
+    let packed_refs = preflight::common_dir(repo).join("packed-refs");
+    let copy = dir.join("packed-refs");
+    if packed_refs.exists() && !copy.exists() {
+        fs::copy(&packed_refs, &copy).map_err(io_error("copy", &packed_refs))?;
+    }
+
+    let mut lines = String::new();
+    let mut pulls = String::new();
+    for refname in refnames {
+        let oid = repo
+            .refname_to_id(refname)
+            .context(format!("resolve {refname}"))?;
+        lines.push_str(&format!("{oid} {refname}\n"));
+        if let Some(numbers) = pull_requests
+            .get(refname)
+            .filter(|numbers| !numbers.is_empty())
+        {
+            pulls.push_str(refname);
+            for number in numbers {
+                pulls.push_str(&format!(" {number}"));
+            }
+            pulls.push('\n');
+        }
+
+        let reflog = reflog_path(&preflight::common_dir(repo), refname);
+        if reflog.exists() {
+            let copy = reflog_path(&dir, refname);
+            if let Some(parent) = copy.parent() {
+                fs::create_dir_all(parent).map_err(io_error("create", parent))?;
+            }
+            fs::copy(&reflog, &copy).map_err(io_error("copy", &reflog))?;
+        }
+    }
+    append(&dir.join("refs"), &lines)?;
+    if !pulls.is_empty() {
+        append(&dir.join("pulls"), &pulls)?;
+    }
+    Ok(run_id)
+}
+
+fn append(path: &Path, contents: &str) -> Result<(), FatalError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(io_error("write", path))
+}
+
+pub(crate) fn reflog_path(dir: &Path, refname: &str) -> PathBuf {
+    dir.join("logs").join(refname)
+}
+
+/// Put the reflog of `refname`, as journaled by run `run_id`, in front of
+/// that of `to`, so that `git reflog` shows its history once more.
+pub(crate) fn restore_reflog(
+    mutator: &Mutator,
+    run_id: &str,
+    refname: &str,
+    to: &str,
+) -> Result<(), FatalError> {
+    let journaled = reflog_path(&journal_dir(mutator.repo()).join(run_id), refname);
+    prepend_reflog(mutator, &journaled, to)
+}
+
+/// Put the reflog entries in the file at `from`, if any, in front of those
+/// of `to`.
+pub(crate) fn prepend_reflog(mutator: &Mutator, from: &Path, to: &str) -> Result<(), FatalError> {
+    let Ok(mut entries) = fs::read_to_string(from) else {
+        return Ok(());
+    };
+    let path = reflog_path(&preflight::common_dir(mutator.repo()), to);
+    entries.push_str(&fs::read_to_string(&path).unwrap_or_default());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error("create", parent))?;
+    }
+    fs::write(&path, entries).map_err(io_error("write", &path))
+}
+
+/// Restore the refs journaled by run `run_id` in the repository at `path`,
+/// returning those restored.
+///
+/// Only the refs whose names, without `refs/heads/`, match the glob
+/// `matching`, and which were the head of `pull_request`, are restored, if
+/// either is given. Refs which exist again are left as they are, with a
+/// warning. A dry run only says which would be restored.
+pub fn recover(
+    path: impl AsRef<Path>,
+    run_id: &str,
+    matching: Option<&str>,
+    pull_request: Option<u64>,
+    dry_run: bool,
+) -> Result<Vec<String>, FatalError> {
+    let logger = logging::logger();
+    let repo = ancestry::open_repository(path)?;
+    let dir = journal_dir(&repo).join(run_id);
+    if !dir.is_dir() {
+        return Err(FatalError::NoSuchRun {
+            run_id: run_id.to_owned(),
+            runs: runs(&repo),
+        });
+    }
+    let matcher = matching
+        .map(|pattern| {
+            Glob::new(pattern)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|inner| FatalError::InvalidGlob {
+                    pattern: pattern.to_owned(),
+                    inner,
+                })
+        })
+        .transpose()?;
+    let pulls = pull_request.map(|_| pulls(&dir)).transpose()?;
+    let path = dir.join("refs");
+    let lines = fs::read_to_string(&path).map_err(io_error("read", &path))?;
+
+    let mut journaled = Vec::new();
+    for line in lines.lines() {
+        let Some((oid, refname)) = line.split_once(' ') else {
+            continue;
+        };
+        let name = refname.strip_prefix("refs/heads/").unwrap_or(refname);
+        if matcher
+            .as_ref()
+            .is_some_and(|matcher| !matcher.is_match(name))
+        {
+            continue;
+        }
+        if let (Some(pulls), Some(number)) = (&pulls, pull_request) {
+            if !pulls
+                .get(refname)
+                .is_some_and(|numbers| numbers.contains(&number))
+            {
+                continue;
+            }
+        }
+        let oid = Oid::from_str(oid).context(format!("parse journaled id of {refname}"))?;
+        if repo.find_reference(refname).is_ok() {
+            slog::warn!(logger, "not recovering ref which exists again"; "ref" => refname);
+            continue;
+        }
+        journaled.push((oid, refname));
+    }
+
+    let Some(guard) = DeletionGuard::new(dry_run) else {
+        for (oid, refname) in &journaled {
+            if repo.find_commit(*oid).is_err() {
+                slog::info!(logger, "would fetch pruned commit"; "ref" => refname, "id" => %oid);
+            }
+            slog::info!(logger, "would recover ref"; "ref" => refname, "id" => %oid);
+        }
+        return Ok(journaled
+            .into_iter()
+            .map(|(_, refname)| refname.to_owned())
+            .collect());
+    };
+    let mutator = guard.mutator(&repo);
+    // check them all first, so as never to leave a ref pointing at nothing,
+    // nor to recover only some of them
+    let pruned = journaled
+        .iter()
+        .filter(|(oid, refname)| !has_or_fetch(&mutator, *oid, refname, &logger))
+        .map(|(_, refname)| refname.to_string())
+        .collect::<Vec<_>>();
+    if !pruned.is_empty() {
+        return Err(FatalError::CommitsPruned(pruned));
+    }
+
+    let mut restored = Vec::new();
+    for (oid, refname) in journaled {
+        mutator
+            .reference(refname, oid, false, "git-clean: recover")
+            .context(format!("recover {refname}"))?;
+        restore_reflog(&mutator, run_id, refname, refname)?;
+        slog::info!(logger, "recovered ref"; "ref" => refname, "id" => %oid);
+        restored.push(refname.to_owned());
+    }
+    Ok(restored)
+}
+
+/// The numbers of the pull requests of the refs journaled in `dir`.
+fn pulls(dir: &Path) -> Result<HashMap<String, Vec<u64>>, FatalError> {
+    let path = dir.join("pulls");
+    let lines = match fs::read_to_string(&path) {
+        Ok(lines) => lines,
+        // runs which deleted no branches with pull requests have none
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(io_error("read", &path)(err)),
+    };
+    Ok(lines
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let refname = fields.next()?.to_owned();
+            Some((refname, fields.filter_map(|n| n.parse().ok()).collect()))
+        })
+        .collect())
+}
+
+/// Whether the object store has the commit `oid`, fetching it by id from each
+/// remote in turn if `git gc` has pruned it.
+fn has_or_fetch(mutator: &Mutator, oid: Oid, refname: &str, logger: &slog::Logger) -> bool {
+    let repo = mutator.repo();
+    if repo.find_commit(oid).is_ok() {
+        return true;
+    }
+    let remotes = repo.remotes().ok();
+    for remote in remotes.iter().flat_map(|remotes| remotes.iter().flatten()) {
+        slog::info!(
+            logger, "fetching pruned commit";
+            "ref" => refname,
+            "id" => %oid,
+            "remote" => remote,
+        );
+        // git2 can't authenticate as the user's git would, so let git do it
+        let fetched = Command::new("git")
+            .arg("--git-dir")
+            .arg(repo.path())
+            .args(["fetch", "--quiet", "--no-write-fetch-head", remote])
+            .arg(oid.to_string())
+            .stdin(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if fetched && repo.find_commit(oid).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// The ids of journaled runs, oldest first.
+fn runs(repo: &Repository) -> Vec<String> {
+    let mut runs = fs::read_dir(journal_dir(repo))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    runs.sort_unstable();
+    runs
+}
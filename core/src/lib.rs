@@ -0,0 +1,142 @@
+//! Clean outdated local git branches.
+//!
+//! With the default `engine` feature, [`clean_branches`] does the whole job:
+//! it inspects a local repository, asks GitHub about each branch's pull
+//! requests, and deletes the stale ones. Without it, only the policy remains:
+//! [`decide`] what to do with branches from facts gathered elsewhere. That part
+//! depends on neither git2 nor tokio, so builds for `wasm32`.
+
+mod error;
+#[cfg(feature = "engine")]
+pub use error::FatalError;
+pub use error::{BranchIssue, ErrorKind};
+
+mod policy;
+mod report;
+
+#[cfg(feature = "engine")]
+mod ancestry;
+#[cfg(feature = "engine")]
+pub mod archive;
+#[cfg(feature = "engine")]
+mod bitbucket;
+#[cfg(feature = "engine")]
+mod cassette;
+#[cfg(feature = "engine")]
+mod clients;
+#[cfg(feature = "engine")]
+pub(crate) mod config;
+#[cfg(feature = "engine")]
+mod confirm;
+#[cfg(feature = "engine")]
+mod deployments;
+#[cfg(feature = "engine")]
+mod engine;
+#[cfg(feature = "engine")]
+mod forge;
+#[cfg(all(fuzzing, feature = "engine"))]
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(feature = "engine")]
+mod git_config;
+#[cfg(feature = "engine")]
+mod gitlab;
+#[cfg(feature = "engine")]
+mod gone;
+#[cfg(feature = "engine")]
+mod guard;
+#[cfg(feature = "engine")]
+mod http;
+#[cfg(feature = "engine")]
+pub mod journal;
+#[cfg(feature = "engine")]
+mod logging;
+#[cfg(feature = "engine")]
+mod mine;
+#[cfg(feature = "engine")]
+mod owners;
+#[cfg(feature = "engine")]
+mod plan;
+#[cfg(feature = "engine")]
+mod preflight;
+#[cfg(feature = "engine")]
+mod progress;
+#[cfg(feature = "engine")]
+mod pulls;
+#[cfg(feature = "engine")]
+pub mod redact;
+#[cfg(feature = "engine")]
+mod references;
+#[cfg(feature = "engine")]
+pub mod registry;
+#[cfg(feature = "engine")]
+mod renames;
+#[cfg(feature = "engine")]
+mod retarget;
+#[cfg(feature = "engine")]
+mod retry;
+#[cfg(feature = "engine")]
+mod reviews;
+#[cfg(feature = "engine")]
+mod sampling;
+#[cfg(feature = "engine")]
+pub mod secrets;
+#[cfg(feature = "aws-secrets-manager")]
+mod secrets_manager;
+#[cfg(feature = "engine")]
+pub mod settings;
+#[cfg(feature = "engine")]
+mod siblings;
+#[cfg(feature = "engine")]
+mod sso;
+#[cfg(feature = "engine")]
+mod stale_refs;
+#[cfg(all(test, feature = "engine"))]
+mod testing;
+#[cfg(feature = "engine")]
+pub mod token;
+#[cfg(feature = "engine")]
+mod unpushed;
+#[cfg(feature = "vault")]
+mod vault;
+#[cfg(feature = "engine")]
+mod vetting;
+
+#[cfg(feature = "engine")]
+pub use ancestry::open_repository;
+#[cfg(feature = "engine")]
+pub use confirm::{Candidate, ConfirmHook, Confirmation};
+#[cfg(feature = "engine")]
+#[allow(deprecated)]
+pub use engine::{
+    clean_branches, clean_branches_logged, clean_branches_many, clean_branches_with,
+    clean_branches_with_logged, clean_repository, clean_repository_logged, delete_branches,
+    Options, DEFAULT_LOG_SAMPLE, KNOWN_BOT_BRANCHES,
+};
+#[cfg(feature = "engine")]
+pub use forge::{ForgeProvider, Provider};
+#[cfg(feature = "engine")]
+pub use logging::set_logger;
+#[cfg(feature = "engine")]
+pub use mine::{my_pull_requests, Checkout, MyPullRequest, PullRequestMap};
+pub use policy::{decide, BranchFacts, Policy, PullRequestFacts, Snapshot};
+#[cfg(feature = "engine")]
+pub use progress::{ProgressEvent, ProgressHook};
+pub use report::{
+    render_markdown, BranchReport, CleanReport, Confidence, Decision, Outcome, PrSummary, Reason,
+};
+#[cfg(feature = "engine")]
+pub use stale_refs::DEFAULT_STALE_REF_NAMESPACES;
+#[cfg(feature = "engine")]
+pub use vetting::Vetting;
+
+// `clean_repository` and `set_logger` take their types, so callers can use
+// the same versions
+#[cfg(feature = "engine")]
+pub use {git2, octocrab, slog};
+
+// All the strings we really expect to deal with (owner, repo, branch name, etc)
+// are likely shorter than 128 bytes. However, we have to copy them fairly
+// frequently. We can reduce heap allocations and improve performance by storing
+// the string inline, only going to the heap on overflow.
+pub type SmallStr = smallstr::SmallString<[u8; 128]>;
@@ -0,0 +1,23 @@
+//! Where the library logs: nowhere, unless an application asks.
+//!
+//! Callers shouldn't have to build a logger just to clean some branches, so
+//! none is taken as a parameter. Applications which want the logs pass one to
+//! [`set_logger`] once, before running.
+
+use std::sync::RwLock;
+
+static LOGGER: RwLock<Option<slog::Logger>> = RwLock::new(None);
+
+/// Log everything from now on to `logger`, replacing any set before.
+pub fn set_logger(logger: slog::Logger) {
+    *LOGGER.write().unwrap_or_else(|err| err.into_inner()) = Some(logger);
+}
+
+/// The logger set with [`set_logger`], or one which discards everything.
+pub(crate) fn logger() -> slog::Logger {
+    LOGGER
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+        .unwrap_or_else(|| slog::Logger::root(slog::Discard, slog::o!()))
+}
@@ -0,0 +1,171 @@
+//! My open pull requests, and the local branches for them, for
+//! `git-clean prs`: the other direction of the association cleaning uses.
+
+use std::path::Path;
+
+use futures::StreamExt;
+use git2::BranchType;
+
+use crate::{
+    ancestry,
+    clients::ClientPool,
+    engine::{api_base_uri, get_repo_metadata, git_host, parse_git_url},
+    error::ContextErr,
+    logging,
+    preflight::{Obstacle, Preflight},
+    FatalError, Options, PrSummary, SmallStr,
+};
+
+/// Where a local branch is checked out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checkout {
+    MainWorktree,
+    /// The named linked worktree.
+    Worktree(String),
+}
+
+/// One of my open pull requests.
+#[derive(Debug, Clone)]
+pub struct MyPullRequest {
+    pub pull_request: PrSummary,
+    /// The name of its head branch.
+    pub head: SmallStr,
+    /// Whether there is a local branch of that name.
+    pub local: bool,
+    /// Where that branch is checked out, if anywhere.
+    pub checked_out: Option<Checkout>,
+}
+
+/// My open pull requests and the local branches, matched by head name.
+#[derive(Debug, Clone)]
+pub struct PullRequestMap {
+    /// By number.
+    pub pull_requests: Vec<MyPullRequest>,
+    /// Local branches which are the head of no pull request, open or closed,
+    /// by anyone; the default branch is left out.
+    pub without_pull_requests: Vec<SmallStr>,
+}
+
+/// Match my open pull requests in the GitHub repository of the local one at
+/// `path` to its local branches, and find the local branches with none.
+///
+/// Who "I" am is the owner of the first token, so at least one is needed.
+pub async fn my_pull_requests(
+    path: impl AsRef<Path>,
+    options: &Options,
+    personal_access_tokens: Vec<String>,
+) -> Result<PullRequestMap, FatalError> {
+    /// How many branches' pull requests are looked up at once.
+    const CONCURRENCY: usize = 8;
+
+    if personal_access_tokens.is_empty() {
+        return Err(FatalError::TokenRequired("listing my pull requests"));
+    }
+    let logger = logging::logger();
+    let api = api_base_uri(options)?;
+    let clients = ClientPool::new(&personal_access_tokens, &api, None, None)?;
+    let octocrab = clients.primary();
+
+    let repo = ancestry::open_repository(path)?;
+    let remotes = repo.remotes().context("list remotes")?;
+    if remotes.len() != 1 {
+        return Err(FatalError::WrongRemoteCount(remotes.len()));
+    }
+    let remote_name = remotes.get(0).ok_or(FatalError::InexpressableRemote)?;
+    let remote = repo
+        .find_remote(remote_name)
+        .context("get remote by name")?;
+    let remote_url = remote.url().ok_or(FatalError::RemoteUrlNotUtf8)?;
+    let (owner, repo_name) =
+        parse_git_url(remote_url, git_host(&api)).ok_or(FatalError::RemoteUrlNotGithub)?;
+    let default_branch = get_repo_metadata(octocrab, &owner, &repo_name)
+        .await
+        .and_then(|metadata| metadata.default_branch);
+
+    let page = octocrab
+        .search()
+        .issues_and_pull_requests(&format!(
+            "is:pr is:open repo:{owner}/{repo_name} author:@me"
+        ))
+        .per_page(100)
+        .send()
+        .await
+        .context("search for my open pull requests")?;
+    let issues = octocrab
+        .all_pages(page)
+        .await
+        .context("get rest of pages for my open pull requests")?;
+
+    let mut local = Vec::new();
+    for branch in repo
+        .branches(Some(BranchType::Local))
+        .context("list local branches")?
+    {
+        let (branch, _) = branch.context("read local branch")?;
+        if let Some(name) = branch.name().context("read branch name")? {
+            local.push(SmallStr::from_str(name));
+        }
+    }
+    let preflight = Preflight::new(&repo);
+
+    let mut pull_requests = Vec::with_capacity(issues.len());
+    for issue in issues {
+        let pr = octocrab
+            .pulls(&*owner, &*repo_name)
+            .get(issue.number)
+            .await
+            .context("get my open pull request")?;
+        let head = SmallStr::from_string(pr.head.ref_field);
+        let checked_out = match preflight.check(&head) {
+            Some(Obstacle::CheckedOut) => Some(Checkout::MainWorktree),
+            Some(Obstacle::CheckedOutInWorktree(worktree)) => Some(Checkout::Worktree(worktree)),
+            _ => None,
+        };
+        pull_requests.push(MyPullRequest {
+            pull_request: PrSummary {
+                number: pr.number,
+                open: true,
+                title: pr.title,
+                url: pr.html_url.map(|url| url.to_string()),
+                author: pr.user.map(|user| user.login),
+                closed_at: None,
+                merged_at: None,
+                merged_by: None,
+                head_sha: Some(pr.head.sha),
+            },
+            local: local.contains(&head),
+            head,
+            checked_out,
+        });
+    }
+    pull_requests.sort_unstable_by_key(|mine| mine.pull_request.number);
+
+    let unmatched = local
+        .into_iter()
+        .filter(|name| Some(name) != default_branch.as_ref())
+        .filter(|name| !pull_requests.iter().any(|mine| mine.head == *name));
+    let mut without_pull_requests = futures::stream::iter(unmatched)
+        .map(|name| async {
+            match clients.get_prs(&owner, &repo_name, &name).await {
+                Ok(prs) => prs.is_empty().then_some(Ok(name)),
+                Err(err) => Some(Err(err)),
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .filter_map(|found| async { found })
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    without_pull_requests.sort_unstable();
+    slog::debug!(
+        logger, "matched my pull requests to local branches";
+        "pull requests" => pull_requests.len(),
+        "branches without" => without_pull_requests.len(),
+    );
+
+    Ok(PullRequestMap {
+        pull_requests,
+        without_pull_requests,
+    })
+}
@@ -0,0 +1,103 @@
+//! Who owns which branches, from a `.git-clean-owners` file at the top of
+//! the working tree, for cleaning only some team's branches in a monorepo.
+//!
+//! Like `CODEOWNERS`, each line is a pattern followed by its owners, and the
+//! last matching line wins:
+//!
+//! ```text
+//! # pattern       owners
+//! payments/       @payments-team
+//! search/*        @search-team @alice
+//! ```
+//!
+//! A pattern ending in `/` matches every branch under it; others are globs.
+
+use std::fs;
+
+use git2::Repository;
+use globset::{Glob, GlobMatcher};
+
+use crate::FatalError;
+
+pub(crate) const OWNERS_FILE: &str = ".git-clean-owners";
+
+#[derive(Debug, Default)]
+pub(crate) struct Owners {
+    rules: Vec<(GlobMatcher, Vec<String>)>,
+}
+
+impl Owners {
+    /// The repository's owners file, or no owners if it has none.
+    pub fn load(repo: &Repository) -> Result<Self, FatalError> {
+        let Some(text) = repo
+            .workdir()
+            .and_then(|workdir| fs::read_to_string(workdir.join(OWNERS_FILE)).ok())
+        else {
+            return Ok(Self::default());
+        };
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, FatalError> {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(pattern) = words.next() else {
+                continue;
+            };
+            let glob = match pattern.strip_suffix('/') {
+                Some(prefix) => format!("{prefix}/**"),
+                None => pattern.to_owned(),
+            };
+            let matcher = Glob::new(&glob)
+                .map_err(|inner| FatalError::InvalidGlob {
+                    pattern: pattern.to_owned(),
+                    inner,
+                })?
+                .compile_matcher();
+            rules.push((matcher, words.map(normalize).collect()));
+        }
+        Ok(Self { rules })
+    }
+
+    /// The owners of `branch_name`; none if no line matches it.
+    pub fn of(&self, branch_name: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.is_match(branch_name))
+            .map(|(_, owners)| owners.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Whether `owner`, given with or without its `@`, owns `branch_name`.
+    pub fn owns(&self, owner: &str, branch_name: &str) -> bool {
+        let owner = normalize(owner);
+        self.of(branch_name).contains(&owner)
+    }
+}
+
+fn normalize(owner: &str) -> String {
+    owner.trim_start_matches('@').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_line_wins() {
+        let owners = Owners::parse(
+            "# monorepo\n\
+             payments/ @Payments-Team\n\
+             payments/legacy-* @alice  # until it's gone\n\
+             search/* @search-team\n",
+        )
+        .unwrap();
+        assert!(owners.owns("payments-team", "payments/refunds/fix"));
+        assert!(owners.owns("@alice", "payments/legacy-export"));
+        assert!(!owners.owns("payments-team", "payments/legacy-export"));
+        assert!(owners.of("docs/typo").is_empty());
+    }
+}
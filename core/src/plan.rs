@@ -0,0 +1,102 @@
+//! Fingerprints of planned deletions, so that a reviewed plan can be applied
+//! without risk of it having drifted in the meantime.
+//!
+//! The fingerprint is signed: an HMAC under the local secrets key, the one
+//! [`secrets`](crate::secrets) seals tokens with. Only someone with that key
+//! can produce a hash `--approve` accepts, so an approval can't be made up
+//! without a dry run on this machine.
+
+use git2::{BranchType, Oid, Repository};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{error::ContextErr, secrets, FatalError, SmallStr};
+
+/// Number of hex digits of the digest we show; short enough to paste around.
+const HASH_LEN: usize = 16;
+
+/// Sign the set of branches and stale refs to be deleted, along with their
+/// tips.
+///
+/// The hash changes if any branch or ref is added to or dropped from the plan,
+/// or if any doomed branch gains or loses commits. The key is created if need
+/// be.
+pub(crate) fn hash(
+    repo: &Repository,
+    branch_names: &[SmallStr],
+    stale_refs: &[String],
+) -> Result<String, FatalError> {
+    let mut entries = branch_names
+        .iter()
+        .map(|branch_name| {
+            let tip = repo
+                .find_branch(branch_name, BranchType::Local)
+                .context("find branch to hash")?
+                .get()
+                .peel_to_commit()
+                .context("resolve branch tip to hash")?
+                .id();
+            Ok((format!("refs/heads/{branch_name}"), tip))
+        })
+        .collect::<Result<Vec<_>, FatalError>>()?;
+    for refname in stale_refs {
+        let tip = repo
+            .refname_to_id(refname)
+            .context("resolve stale ref to hash")?;
+        entries.push((refname.clone(), tip));
+    }
+    let key = secrets::load_or_create_key().map_err(FatalError::ConfigFile)?;
+    Ok(sign(&key, entries))
+}
+
+fn sign(key: &[u8], mut entries: Vec<(String, Oid)>) -> String {
+    entries.sort_unstable();
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any length");
+    // keep these signatures apart from anything else signed with the key
+    mac.update(b"git-clean plan\0");
+    for (refname, tip) in entries {
+        mac.update(refname.as_bytes());
+        mac.update(&[0]);
+        mac.update(tip.as_bytes());
+    }
+    let mut digest = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    digest.truncate(HASH_LEN);
+    digest
+}
+
+/// Ensure that the plan about to be applied is the one which was approved.
+pub(crate) fn check_approval(approved: &str, actual: &str) -> Result<(), FatalError> {
+    if approved.trim().eq_ignore_ascii_case(actual) {
+        return Ok(());
+    }
+    Err(FatalError::PlanNotApproved {
+        approved: approved.to_owned(),
+        actual: actual.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_depends_on_key_and_plan() {
+        let branch = "refs/heads/feature".to_owned();
+        let tip = Oid::from_str("b06e617d862c53e4574a127109e27e35028cf1d0").unwrap();
+        let signed = sign(&[1; 32], vec![(branch.clone(), tip)]);
+        assert_eq!(signed.len(), HASH_LEN);
+        assert_eq!(signed, sign(&[1; 32], vec![(branch.clone(), tip)]));
+        assert_ne!(signed, sign(&[2; 32], vec![(branch.clone(), tip)]));
+        assert_ne!(signed, sign(&[1; 32], vec![(branch.clone(), Oid::zero())]));
+        let stale = "refs/original/refs/heads/feature".to_owned();
+        assert_ne!(
+            signed,
+            sign(&[1; 32], vec![(branch, tip), (stale, Oid::zero())])
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! The cleaning policy: what to do with a branch, given facts already gathered
+//! about it.
+//!
+//! Nothing here touches git or the network. The engine gathers the facts and
+//! asks the policy which further facts would change its mind, so that it only
+//! makes the API calls it needs to.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    report::{Confidence, Decision, Reason},
+    SmallStr,
+};
+
+/// Settings which change what is decided about a branch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Policy {
+    /// Retain branches which are the head of an open PR on which we are a
+    /// requested reviewer.
+    #[serde(default)]
+    pub keep_review_requests: bool,
+    /// Only delete a branch if one of its closed pull requests was vetted.
+    #[serde(default)]
+    pub require_vetting: bool,
+    /// Retain branches which are still mentioned in open issues or tracked
+    /// files.
+    #[serde(default)]
+    pub keep_referenced: bool,
+    /// Retain branches whose deletion we are less sure of than this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<Confidence>,
+    /// Only delete a branch if one of its pull requests was merged, rather
+    /// than closed without merging.
+    #[serde(default)]
+    pub require_merged: bool,
+}
+
+/// What we know about one pull request whose head is the branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestFacts {
+    pub number: u64,
+    pub open: bool,
+    /// Whether it was merged, if that was looked up.
+    #[serde(default)]
+    pub merged: Option<bool>,
+}
+
+/// What we know about one local branch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchFacts {
+    pub branch_name: SmallStr,
+    /// The branch is the remote's default branch.
+    #[serde(default)]
+    pub is_default: bool,
+    /// The branch is the head of an open PR awaiting our review.
+    #[serde(default)]
+    pub review_requested: bool,
+    /// The branch was created by a dependency bot.
+    #[serde(default)]
+    pub is_bot: bool,
+    /// Pull requests with this branch as their head, under its current or any
+    /// previous name.
+    #[serde(default)]
+    pub pull_requests: Vec<PullRequestFacts>,
+    /// Whether any closed pull request was vetted, if that was looked up.
+    pub vetted: Option<bool>,
+    /// Whether the branch's tip is in the default branch, if that was checked.
+    #[serde(default)]
+    pub merged: Option<bool>,
+    /// Whether the branch's changes are in the default branch under other
+    /// commits, as after a squash merge, if that was checked.
+    #[serde(default)]
+    pub squashed: Option<bool>,
+    /// Where the branch is still mentioned, if that was looked up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+}
+
+impl BranchFacts {
+    pub fn new(branch_name: SmallStr) -> Self {
+        Self {
+            branch_name,
+            is_default: false,
+            review_requested: false,
+            is_bot: false,
+            pull_requests: Vec::new(),
+            vetted: None,
+            merged: None,
+            squashed: None,
+            references: Vec::new(),
+        }
+    }
+}
+
+/// Everything the policy was told during a run.
+///
+/// Deciding again from a snapshot reproduces the run's decisions without git
+/// or the network, which makes for reproducible bug reports and lets the
+/// policy be tuned offline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub policy: Policy,
+    pub branches: Vec<BranchFacts>,
+}
+
+impl Snapshot {
+    pub fn decide(&self) -> Vec<Decision> {
+        self.branches
+            .iter()
+            .map(|facts| decide(facts, &self.policy))
+            .collect()
+    }
+}
+
+/// Whether a branch's pull requests mark it as stale.
+///
+/// - If there are no PRs for this branch, it is for local development and not
+///   deleted.
+/// - If it has been pushed to the remote but no PRs have been created, then a
+///   PR is likely coming, so it is not deleted.
+/// - If it has been pushed to the remote and at least one PR referencing it
+///   exists, then it is active, and not deleted.
+/// - If it has been pushed to the remote and at least one PR referencing it
+///   exists and all such PRs are closed, then it is stale, and is deleted.
+fn all_closed(pull_requests: &[PullRequestFacts]) -> bool {
+    // if there are no prs associated with this branch, then we shouldn't
+    // close it; it's local
+    if pull_requests.is_empty() {
+        return false;
+    }
+
+    // otherwise, if all prs associated with this branch are closed, then
+    // whether or not they're merged, they're no longer relevant.
+    !pull_requests.iter().any(|pr| pr.open)
+}
+
+impl Policy {
+    /// Whether the branch's pull requests could change the decision.
+    ///
+    /// A squash-merged branch still needs them: its changes having landed
+    /// doesn't make an open pull request for it, such as a backport's, done.
+    pub fn needs_pull_requests(&self, facts: &BranchFacts) -> bool {
+        !(facts.is_default || self.keep_review_requests && facts.review_requested)
+    }
+
+    /// Whether vetting the branch's closed pull requests could change the
+    /// decision.
+    pub fn needs_vetting(&self, facts: &BranchFacts) -> bool {
+        // vetting is what lifts an unmerged branch to medium confidence
+        let for_confidence =
+            self.min_confidence == Some(Confidence::Medium) && facts.merged != Some(true);
+        (self.require_vetting || for_confidence)
+            && !facts.is_bot
+            && all_closed(&facts.pull_requests)
+    }
+}
+
+/// How sure we are that a branch whose pull requests are all closed is done
+/// with.
+fn confidence(facts: &BranchFacts) -> Confidence {
+    if facts.is_bot || facts.merged == Some(true) || facts.squashed == Some(true) {
+        Confidence::High
+    } else if facts.vetted == Some(true) {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    }
+}
+
+/// Decide what to do with a branch.
+///
+/// Facts which [`Policy::needs_vetting`] asked for but which are missing count
+/// against deletion.
+pub fn decide(facts: &BranchFacts, policy: &Policy) -> Decision {
+    let branch_name = facts.branch_name.clone();
+
+    if facts.is_default {
+        return Decision::retain(branch_name, Reason::DefaultBranch);
+    }
+    if policy.keep_review_requests && facts.review_requested {
+        return Decision::retain(branch_name, Reason::ReviewRequested);
+    }
+
+    // a squash merge stands in for a missing pull request, never for an open one
+    let squashed = facts.squashed == Some(true);
+    if facts.pull_requests.iter().any(|pr| pr.open) {
+        return Decision::retain(branch_name, Reason::OpenPullRequest);
+    }
+    if !squashed && !all_closed(&facts.pull_requests) {
+        return Decision::retain(branch_name, Reason::NoPullRequests);
+    }
+
+    if policy.keep_referenced && !facts.references.is_empty() {
+        return Decision::retain(branch_name, Reason::Referenced(facts.references.clone()));
+    }
+    if facts.is_bot {
+        return Decision::delete_bot(branch_name);
+    }
+    if policy.require_vetting && !squashed && facts.vetted != Some(true) {
+        return Decision::retain(branch_name, Reason::NotVetted);
+    }
+    if policy.require_merged
+        && !squashed
+        && !facts.pull_requests.iter().any(|pr| pr.merged == Some(true))
+    {
+        return Decision::retain(branch_name, Reason::ClosedUnmerged);
+    }
+
+    let confidence = confidence(facts);
+    if policy.min_confidence.is_some_and(|min| confidence < min) {
+        return Decision::retain(branch_name, Reason::LowConfidence(confidence));
+    }
+    Decision::delete(branch_name, confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+
+    fn closed_pr() -> PullRequestFacts {
+        PullRequestFacts {
+            number: 1,
+            open: false,
+            merged: None,
+        }
+    }
+
+    #[test]
+    fn bot_branches_skip_vetting() {
+        let policy = Policy {
+            require_vetting: true,
+            ..Policy::default()
+        };
+        let facts = BranchFacts {
+            is_bot: true,
+            pull_requests: vec![closed_pr()],
+            ..BranchFacts::new("dependabot/foo".into())
+        };
+        assert!(!policy.needs_vetting(&facts));
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Delete { bot: true, .. }
+        ));
+
+        let facts = BranchFacts {
+            is_bot: false,
+            ..facts
+        };
+        assert!(policy.needs_vetting(&facts));
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Retain(Reason::NotVetted)
+        ));
+    }
+
+    #[test]
+    fn min_confidence_retains_unmerged_branches() {
+        let policy = Policy {
+            min_confidence: Some(Confidence::Medium),
+            ..Policy::default()
+        };
+        let facts = BranchFacts {
+            pull_requests: vec![closed_pr()],
+            merged: Some(false),
+            ..BranchFacts::new("feature".into())
+        };
+        assert!(policy.needs_vetting(&facts));
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Retain(Reason::LowConfidence(Confidence::Low))
+        ));
+
+        let facts = BranchFacts {
+            vetted: Some(true),
+            ..facts
+        };
+        assert!(decide(&facts, &policy).is_delete());
+    }
+
+    #[test]
+    fn squash_merged_branches_need_no_pull_requests() {
+        let policy = Policy {
+            require_vetting: true,
+            ..Policy::default()
+        };
+        let facts = BranchFacts {
+            merged: Some(false),
+            squashed: Some(true),
+            ..BranchFacts::new("feature".into())
+        };
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Delete {
+                confidence: Confidence::High,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn squash_merged_branches_with_open_pull_requests_are_retained() {
+        let policy = Policy::default();
+        let facts = BranchFacts {
+            merged: Some(false),
+            squashed: Some(true),
+            pull_requests: vec![PullRequestFacts {
+                open: true,
+                ..closed_pr()
+            }],
+            ..BranchFacts::new("backport".into())
+        };
+        assert!(policy.needs_pull_requests(&facts));
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Retain(Reason::OpenPullRequest)
+        ));
+    }
+
+    #[test]
+    fn require_merged_retains_branches_closed_unmerged() {
+        let policy = Policy {
+            require_merged: true,
+            ..Policy::default()
+        };
+        let facts = BranchFacts {
+            pull_requests: vec![closed_pr()],
+            ..BranchFacts::new("abandoned".into())
+        };
+        assert!(matches!(
+            decide(&facts, &policy).outcome,
+            Outcome::Retain(Reason::ClosedUnmerged)
+        ));
+
+        let merged = PullRequestFacts {
+            number: 2,
+            merged: Some(true),
+            ..closed_pr()
+        };
+        let facts = BranchFacts {
+            pull_requests: vec![closed_pr(), merged],
+            ..facts
+        };
+        assert!(decide(&facts, &policy).is_delete());
+    }
+}
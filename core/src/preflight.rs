@@ -0,0 +1,136 @@
+//! Checks that a planned deletion can actually be carried out.
+//!
+//! These run on dry runs too, so that the plan they print is trustworthy
+//! rather than optimistic.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use git2::Repository;
+
+use crate::SmallStr;
+
+/// Why a planned deletion would fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Obstacle {
+    /// The branch is checked out in the main working tree.
+    CheckedOut,
+    /// The branch is checked out in the named linked worktree.
+    CheckedOutInWorktree(String),
+    /// Another git process holds a lock on the branch or on packed refs.
+    Locked,
+    /// We may not write to the refs.
+    NotWritable,
+}
+
+impl fmt::Display for Obstacle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Obstacle::CheckedOut => f.write_str("checked out"),
+            Obstacle::CheckedOutInWorktree(worktree) => {
+                write!(f, "checked out in worktree {worktree}")
+            }
+            Obstacle::Locked => f.write_str("locked by another git process"),
+            Obstacle::NotWritable => f.write_str("refs are not writable"),
+        }
+    }
+}
+
+/// The directory holding refs shared by all worktrees.
+pub(crate) fn common_dir(repo: &Repository) -> PathBuf {
+    let git_dir = repo.path();
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir.to_owned(),
+    }
+}
+
+fn is_readonly(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.permissions().readonly())
+        .unwrap_or_default()
+}
+
+/// Preflight state for a whole run, gathered once.
+pub(crate) struct Preflight {
+    refs_dir: PathBuf,
+    common_dir: PathBuf,
+    /// `(worktree name, checked out refname)`; the main worktree has no name.
+    checked_out: Vec<(Option<String>, String)>,
+}
+
+impl Preflight {
+    pub fn new(repo: &Repository) -> Self {
+        let common_dir = common_dir(repo);
+
+        let mut checked_out = Vec::new();
+        if let Some(head) = repo
+            .head()
+            .ok()
+            .and_then(|head| head.name().map(str::to_owned))
+        {
+            checked_out.push((None, head));
+        }
+        if let Ok(worktrees) = repo.worktrees() {
+            for name in worktrees.iter().flatten() {
+                let head = repo
+                    .find_worktree(name)
+                    .and_then(|worktree| Repository::open_from_worktree(&worktree))
+                    .ok()
+                    .and_then(|worktree_repo| {
+                        worktree_repo
+                            .head()
+                            .ok()
+                            .and_then(|head| head.name().map(str::to_owned))
+                    });
+                if let Some(head) = head {
+                    checked_out.push((Some(name.to_owned()), head));
+                }
+            }
+        }
+
+        Self {
+            refs_dir: common_dir.join("refs").join("heads"),
+            common_dir,
+            checked_out,
+        }
+    }
+
+    /// Find the reason deleting this branch would fail, if any.
+    pub fn check(&self, branch_name: &str) -> Option<Obstacle> {
+        let refname = format!("refs/heads/{branch_name}");
+        if let Some((worktree, _)) = self.checked_out.iter().find(|(_, head)| *head == refname) {
+            return Some(match worktree {
+                None => Obstacle::CheckedOut,
+                Some(worktree) => Obstacle::CheckedOutInWorktree(worktree.clone()),
+            });
+        }
+
+        let ref_lock = self.refs_dir.join(format!("{branch_name}.lock"));
+        if ref_lock.exists() || self.common_dir.join("packed-refs.lock").exists() {
+            return Some(Obstacle::Locked);
+        }
+
+        if is_readonly(&self.refs_dir) || is_readonly(&self.common_dir.join("packed-refs")) {
+            return Some(Obstacle::NotWritable);
+        }
+
+        None
+    }
+
+    /// Check every branch, returning those which can't be deleted.
+    pub fn check_all<'a>(
+        &self,
+        branch_names: impl IntoIterator<Item = &'a SmallStr>,
+    ) -> Vec<(SmallStr, Obstacle)> {
+        branch_names
+            .into_iter()
+            .filter_map(|branch_name| {
+                self.check(branch_name)
+                    .map(|obstacle| (branch_name.clone(), obstacle))
+            })
+            .collect()
+    }
+}
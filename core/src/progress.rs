@@ -0,0 +1,58 @@
+//! Progress reports for embedders, who would otherwise have to parse the logs.
+
+use std::{fmt, sync::Arc};
+
+use crate::{
+    report::{Decision, PrSummary},
+    SmallStr,
+};
+
+/// Something which happened during a run.
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// This many branches are about to be evaluated.
+    Started { branches: usize },
+    /// A branch is queued for evaluation.
+    Queued { branch_name: SmallStr },
+    /// A branch was evaluated. Branches which could not be evaluated are
+    /// decided as [`Outcome::Failed`](crate::Outcome::Failed).
+    Decided {
+        decision: Decision,
+        /// The pull requests found for the branch under any of its names,
+        /// if they had to be looked up.
+        pull_requests: Vec<PrSummary>,
+    },
+    /// A branch was deleted.
+    Deleted { branch_name: SmallStr },
+}
+
+/// Called with each [`ProgressEvent`] as it happens.
+///
+/// Branches are evaluated concurrently, so it may be called from several
+/// threads at once.
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl ProgressHook {
+    pub fn new(on_progress: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(on_progress))
+    }
+
+    pub(crate) fn emit(&self, event: ProgressEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Report `event` to `hook`, if there is one. The event is only built if
+/// it will be reported.
+pub(crate) fn emit(hook: &Option<ProgressHook>, event: impl FnOnce() -> ProgressEvent) {
+    if let Some(hook) = hook {
+        hook.emit(event());
+    }
+}
@@ -0,0 +1,159 @@
+//! GitHub's pull requests, as far as we read them.
+//!
+//! octocrab's models fail to deserialize if any field they know of is missing
+//! or of a different type, so a field GitHub drops, or starts sending as
+//! `null`, would fail the lookup of every branch. Here only the fields we use
+//! are read, and only the number is required: a field which doesn't parse is
+//! treated as unknown, and reported once per run by [`report_drift`].
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::FatalError;
+
+/// A pull request as found by searching for its head branch.
+#[derive(Debug, Clone)]
+pub(crate) struct FoundPr {
+    pub number: u64,
+    /// Unless known to be closed: an unrecognized state keeps the branch.
+    pub open: bool,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub author: Option<String>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl FoundPr {
+    pub fn from_json(value: &Value) -> Result<Self, FatalError> {
+        let number = number(value)?;
+        Ok(Self {
+            number,
+            open: field::<String>(value, "/state").as_deref() != Some("closed"),
+            title: field(value, "/title"),
+            url: field(value, "/html_url"),
+            author: field(value, "/user/login"),
+            closed_at: field(value, "/closed_at"),
+        })
+    }
+}
+
+/// The parts of a pull request which its search result lacks.
+#[derive(Debug, Clone)]
+pub(crate) struct PullDetails {
+    pub merged: Option<bool>,
+    pub head_ref: Option<String>,
+    pub head_sha: Option<String>,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub merged_by: Option<String>,
+}
+
+impl PullDetails {
+    pub fn from_json(value: &Value) -> Self {
+        let merged_at = field(value, "/merged_at");
+        Self {
+            // should `merged` drift, a merge time is as good
+            merged: field(value, "/merged").or(merged_at.map(|_: DateTime<Utc>| true)),
+            head_ref: field(value, "/head/ref"),
+            head_sha: field(value, "/head/sha"),
+            merged_at,
+            merged_by: field(value, "/merged_by/login"),
+        }
+    }
+}
+
+fn number(value: &Value) -> Result<u64, FatalError> {
+    value
+        .get("number")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| FatalError::UnexpectedResponse("pull request without a number".into()))
+}
+
+/// The field at `pointer`, or `None` if it is `null`.
+///
+/// A field which is missing, or doesn't parse, is also `None`, but recorded
+/// as drift.
+fn field<T: DeserializeOwned>(value: &Value, pointer: &'static str) -> Option<T> {
+    match value.pointer(pointer) {
+        Some(Value::Null) => None,
+        Some(field) => T::deserialize(field)
+            .map_err(|err| drifted(pointer, err.to_string()))
+            .ok(),
+        // a parent which is null, as `merged_by` usually is, is no drift
+        None if parent_is_null(value, pointer) => None,
+        None => {
+            drifted(pointer, "missing".into());
+            None
+        }
+    }
+}
+
+fn parent_is_null(value: &Value, pointer: &str) -> bool {
+    pointer
+        .rsplit_once('/')
+        .filter(|(parent, _)| !parent.is_empty())
+        .and_then(|(parent, _)| value.pointer(parent))
+        .is_some_and(Value::is_null)
+}
+
+/// Fields which didn't parse, with why, and whether that was reported yet.
+static DRIFT: Mutex<BTreeMap<&str, (String, bool)>> = Mutex::new(BTreeMap::new());
+
+fn drifted(pointer: &'static str, why: String) {
+    DRIFT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(pointer)
+        .or_insert((why, false));
+}
+
+/// Warn of each field which didn't parse since the last report, and wasn't
+/// reported before.
+pub(crate) fn report_drift(logger: &slog::Logger) {
+    let mut drift = DRIFT.lock().unwrap_or_else(PoisonError::into_inner);
+    for (pointer, (why, reported)) in drift.iter_mut().filter(|(_, (_, reported))| !reported) {
+        slog::warn!(
+            logger, "unexpected field in GitHub's pull requests; ignoring it";
+            "field" => pointer.trim_start_matches('/').replace('/', "."),
+            "why" => &*why,
+        );
+        *reported = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerates_missing_and_mistyped_fields() {
+        let pr = FoundPr::from_json(&serde_json::json!({
+            "number": 7,
+            "state": "merged-somehow",
+            "title": 12,
+            "user": null,
+            "closed_at": null,
+        }))
+        .unwrap();
+        assert_eq!(pr.number, 7);
+        assert!(pr.open);
+        assert_eq!(pr.title, None);
+        assert_eq!(pr.author, None);
+
+        let pull = PullDetails::from_json(&serde_json::json!({
+            "head": { "ref": "fix/login" },
+            "merged_at": "2024-05-01T12:00:00Z",
+            "merged_by": null,
+        }));
+        assert_eq!(pull.head_ref.as_deref(), Some("fix/login"));
+        assert!(pull.merged_at.is_some());
+        assert_eq!(pull.merged_by, None);
+
+        assert!(FoundPr::from_json(&serde_json::json!({ "state": "open" })).is_err());
+    }
+}
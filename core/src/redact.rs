@@ -0,0 +1,63 @@
+//! Keeping tokens out of logs and error messages.
+//!
+//! Every token we are given is registered here. Anything which might end up
+//! in front of a user, a log file, or a bug report is passed through
+//! [`redact`] first, which masks all but the last four characters of each
+//! token.
+
+use std::{borrow::Cow, sync::RwLock};
+
+/// How many trailing characters of a token are left visible, so that users
+/// can still tell which token was involved.
+const VISIBLE: usize = 4;
+
+/// Strings shorter than this are too likely to occur by chance to be worth
+/// masking.
+const MIN_LEN: usize = 8;
+
+static TOKENS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Mask all but the last few characters of a token.
+pub fn mask(token: &str) -> String {
+    let len = token.chars().count();
+    let visible = token.chars().skip(len.saturating_sub(VISIBLE));
+    "*".repeat(len.saturating_sub(VISIBLE)) + &visible.collect::<String>()
+}
+
+/// Remember a token, so that [`redact`] masks it from now on.
+pub fn register(token: &str) {
+    if token.len() < MIN_LEN {
+        return;
+    }
+    let mut tokens = TOKENS.write().unwrap_or_else(|err| err.into_inner());
+    if !tokens.iter().any(|known| known == token) {
+        tokens.push(token.to_owned());
+    }
+}
+
+/// Mask every registered token which appears in `text`.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let tokens = TOKENS.read().unwrap_or_else(|err| err.into_inner());
+    let mut text = Cow::Borrowed(text);
+    for token in tokens.iter() {
+        if text.contains(token.as_str()) {
+            text = Cow::Owned(text.replace(token.as_str(), &mask(token)));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_registered_tokens() {
+        register("ghp_0123456789abcdef");
+        assert_eq!(
+            redact("bad credentials for ghp_0123456789abcdef"),
+            "bad credentials for ****************cdef"
+        );
+        assert_eq!(redact("nothing to see"), "nothing to see");
+    }
+}
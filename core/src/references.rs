@@ -0,0 +1,128 @@
+//! Find where branch names are still mentioned: in open issues, and in the
+//! repository's own tracked files, such as docs, runbooks and TODO comments.
+
+use std::collections::HashMap;
+
+use git2::Repository;
+use octocrab::{params, Octocrab};
+
+use crate::{error::ContextErr, FatalError, SmallStr};
+
+/// Files larger than this are not searched; they are rarely prose.
+const MAX_FILE_SIZE: usize = 1 << 20;
+
+/// At most this many mentions are kept per branch, which is plenty to explain
+/// why it was kept.
+const MAX_MENTIONS: usize = 5;
+
+/// Mentions of each branch, e.g. `issue #12` or `docs/runbook.md:40`.
+pub(crate) type Mentions = HashMap<SmallStr, Vec<String>>;
+
+/// Whether `c` could continue a branch name, so that a match ending or
+/// starting next to it is only part of a longer name.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '/')
+}
+
+/// Whether `text` mentions `name` as a whole word.
+fn mentions(text: &str, name: &str) -> bool {
+    text.match_indices(name).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + name.len()..].chars().next();
+        !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+    })
+}
+
+fn record(found: &mut Mentions, branch_name: &SmallStr, mention: String) {
+    let branch_mentions = found.entry(branch_name.clone()).or_default();
+    if branch_mentions.len() < MAX_MENTIONS {
+        branch_mentions.push(mention);
+    }
+}
+
+/// Search every tracked file for the branch names.
+///
+/// Files which can't be read, such as blobs missing from a partial clone, are
+/// skipped.
+pub(crate) fn in_tracked_files(
+    repo: &Repository,
+    branch_names: &[SmallStr],
+    found: &mut Mentions,
+) -> Result<(), FatalError> {
+    if repo.is_bare() {
+        return Ok(());
+    }
+    let index = repo.index().context("read index")?;
+    for entry in index.iter() {
+        let Ok(blob) = repo.find_blob(entry.id) else {
+            continue;
+        };
+        if blob.is_binary() || blob.size() > MAX_FILE_SIZE {
+            continue;
+        }
+        let content = String::from_utf8_lossy(blob.content());
+        let path = String::from_utf8_lossy(&entry.path);
+        for branch_name in branch_names {
+            if !content.contains(branch_name.as_str()) {
+                continue;
+            }
+            for (line_number, line) in content.lines().enumerate() {
+                if mentions(line, branch_name) {
+                    record(found, branch_name, format!("{path}:{}", line_number + 1));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Search the titles and bodies of the repository's open issues for the branch
+/// names.
+pub(crate) async fn in_open_issues(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+    branch_names: &[SmallStr],
+    found: &mut Mentions,
+) -> Result<(), FatalError> {
+    let page = octocrab
+        .issues(owner, repo_name)
+        .list()
+        .state(params::State::Open)
+        .per_page(100)
+        .send()
+        .await
+        .context("list open issues")?;
+    let issues = octocrab
+        .all_pages(page)
+        .await
+        .context("get rest of pages for open issues")?;
+
+    // the issues API lists pull requests too; those are looked up separately
+    for issue in issues.iter().filter(|issue| issue.pull_request.is_none()) {
+        for branch_name in branch_names {
+            if mentions(&issue.title, branch_name)
+                || issue
+                    .body
+                    .as_deref()
+                    .is_some_and(|body| mentions(body, branch_name))
+            {
+                record(found, branch_name, format!("issue #{}", issue.number));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_whole_names_only() {
+        assert!(mentions("see fix/login for details", "fix/login"));
+        assert!(mentions("TODO: drop once `fix/login` lands.", "fix/login"));
+        assert!(!mentions("see fix/login-v2", "fix/login"));
+        assert!(!mentions("hotfix/login", "fix/login"));
+    }
+}
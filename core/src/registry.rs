@@ -0,0 +1,70 @@
+//! Repositories registered in the config file, to be cleaned together by
+//! `git-clean all`.
+//!
+//! Each is registered once, by path, with whatever it needs that others
+//! don't: which remote to use, which forge that is on, and which branches
+//! to protect there.
+
+use std::path::Path;
+
+pub use crate::config::RegisteredRepository;
+use crate::{
+    config::{Config, Error},
+    Options,
+};
+
+impl RegisteredRepository {
+    /// Put this repository's own settings on top of `options`: they win over
+    /// the remote and forge given for every repository, and protect branches
+    /// besides theirs.
+    pub fn apply(&self, options: &mut Options) {
+        if self.remote.is_some() {
+            options.remote.clone_from(&self.remote);
+        }
+        if self.provider.is_some() {
+            options.provider = self.provider;
+        }
+        options
+            .exclude_branches
+            .extend(self.protected_branches.iter().cloned());
+    }
+}
+
+/// Register `repository`, replacing any registered at the same path.
+///
+/// Returns whether one was replaced.
+pub fn add(repository: RegisteredRepository) -> Result<bool, Error> {
+    let mut replaced = false;
+    Config::update(|config| {
+        match config
+            .repositories
+            .iter_mut()
+            .find(|registered| registered.path == repository.path)
+        {
+            Some(registered) => {
+                *registered = repository;
+                replaced = true;
+            }
+            None => config.repositories.push(repository),
+        }
+    })?;
+    Ok(replaced)
+}
+
+/// The registered repositories, in the order they were registered.
+pub fn list() -> Result<Vec<RegisteredRepository>, Error> {
+    Ok(Config::load_or_default()?.repositories)
+}
+
+/// Unregister the repository at `path`, returning whether there was one.
+pub fn remove(path: &Path) -> Result<bool, Error> {
+    let mut removed = false;
+    Config::update(|config| {
+        let before = config.repositories.len();
+        config
+            .repositories
+            .retain(|registered| registered.path != path);
+        removed = config.repositories.len() < before;
+    })?;
+    Ok(removed)
+}
@@ -0,0 +1,49 @@
+//! Recover the names a local branch was previously known by.
+//!
+//! If a branch was renamed after its PR was opened, the PR's head still has the
+//! old name. Renames are recorded in the branch's reflog, which moves along
+//! with the branch, so we can recover every earlier name from it.
+
+use git2::Repository;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::SmallStr;
+
+/// List the names `branch_name` was previously known by, most recent first.
+///
+/// Branches without a reflog, or which were never renamed, have none.
+pub(crate) fn previous_names(repo: &Repository, branch_name: &str) -> Vec<SmallStr> {
+    lazy_static! {
+        // git writes "Branch: renamed", libgit2 "branch: renamed"
+        static ref RENAME_RE: Regex =
+            Regex::new(r"^(?i:branch): renamed refs/heads/(?P<from>.+) to refs/heads/(?P<to>.+)$")
+                .unwrap();
+    }
+
+    let Ok(reflog) = repo.reflog(&format!("refs/heads/{branch_name}")) else {
+        return Vec::new();
+    };
+
+    // reflog entries are ordered most recent first
+    let renames = reflog
+        .iter()
+        .filter_map(|entry| {
+            let captures = RENAME_RE.captures(entry.message()?)?;
+            Some((
+                captures.name("from")?.as_str().to_owned(),
+                captures.name("to")?.as_str().to_owned(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut names = Vec::new();
+    let mut current = branch_name.to_owned();
+    for (from, to) in renames {
+        if to == current && from != branch_name && !names.iter().any(|name| name == &from) {
+            names.push(SmallStr::from_str(&from));
+            current = from;
+        }
+    }
+    names
+}
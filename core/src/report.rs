@@ -0,0 +1,341 @@
+//! Per-branch decisions made during a run, and their rendering.
+
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BranchIssue, PullRequestFacts, SmallStr};
+
+/// What was fetched about one of a branch's pull requests, for embedders to
+/// show without asking the forge again.
+///
+/// Whatever the forge did not say is `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrSummary {
+    pub number: u64,
+    pub open: bool,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub author: Option<String>,
+    pub closed_at: Option<SystemTime>,
+    /// Only known if the pull request itself was fetched, as it is when
+    /// heads are matched exactly.
+    pub merged_at: Option<SystemTime>,
+    pub merged_by: Option<String>,
+    /// The commit its head branch pointed to; likewise only known if the
+    /// pull request itself was fetched.
+    pub head_sha: Option<String>,
+}
+
+impl From<&PullRequestFacts> for PrSummary {
+    fn from(facts: &PullRequestFacts) -> Self {
+        Self {
+            number: facts.number,
+            open: facts.open,
+            title: None,
+            url: None,
+            author: None,
+            closed_at: None,
+            merged_at: None,
+            merged_by: None,
+            head_sha: None,
+        }
+    }
+}
+
+/// What we decided to do with one local branch.
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub branch_name: SmallStr,
+    pub outcome: Outcome,
+}
+
+impl Decision {
+    pub fn delete(branch_name: SmallStr, confidence: Confidence) -> Self {
+        Self {
+            branch_name,
+            outcome: Outcome::Delete {
+                bot: false,
+                confidence,
+            },
+        }
+    }
+
+    pub fn delete_bot(branch_name: SmallStr) -> Self {
+        Self {
+            branch_name,
+            outcome: Outcome::Delete {
+                bot: true,
+                confidence: Confidence::High,
+            },
+        }
+    }
+
+    pub fn retain(branch_name: SmallStr, reason: Reason) -> Self {
+        Self {
+            branch_name,
+            outcome: Outcome::Retain(reason),
+        }
+    }
+
+    pub fn failed(branch_name: SmallStr, err: impl Into<BranchIssue>) -> Self {
+        Self {
+            branch_name,
+            outcome: Outcome::Failed(err.into()),
+        }
+    }
+
+    pub fn is_delete(&self) -> bool {
+        matches!(self.outcome, Outcome::Delete { .. })
+    }
+
+    pub fn is_bot(&self) -> bool {
+        matches!(self.outcome, Outcome::Delete { bot: true, .. })
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self.outcome, Outcome::Failed(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Delete {
+        /// The branch was created by a bot, so was deleted on the fast path.
+        bot: bool,
+        confidence: Confidence,
+    },
+    Retain(Reason),
+    /// We could not decide, so the branch is retained.
+    Failed(BranchIssue),
+}
+
+/// What a run decided about every branch, and what became of it.
+#[derive(Debug, Clone, Default)]
+pub struct CleanReport {
+    /// Nothing was deleted: deletions were only planned.
+    pub dry_run: bool,
+    pub branches: Vec<BranchReport>,
+}
+
+impl CleanReport {
+    /// The branches deleted, or which would have been on a dry run.
+    pub fn deleted(&self) -> impl Iterator<Item = &BranchReport> {
+        self.branches
+            .iter()
+            .filter(|branch| branch.decision.is_delete())
+    }
+
+    /// The decisions alone, as [`render_markdown`] takes them.
+    pub fn decisions(&self) -> Vec<Decision> {
+        self.branches
+            .iter()
+            .map(|branch| branch.decision.clone())
+            .collect()
+    }
+}
+
+/// One branch of a [`CleanReport`].
+#[derive(Debug, Clone)]
+pub struct BranchReport {
+    pub decision: Decision,
+    /// The pull requests found for the branch, if they were looked up.
+    pub pull_requests: Vec<PrSummary>,
+    /// The commit a deleted branch pointed to, so that it can be restored
+    /// with `git branch <name> <tip>`.
+    pub tip: Option<String>,
+    /// Who owns the branch, per the repository's `.git-clean-owners`.
+    pub owners: Vec<String>,
+}
+
+/// How sure we are that a branch is done with.
+///
+/// A branch whose tip is in the default branch was certainly merged. One whose
+/// pull requests were closed without that has only been vetted, at best; it
+/// may have been squash-merged, or abandoned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// Its pull requests were closed, but that's all we know.
+    Low,
+    /// A closed pull request was vetted.
+    Medium,
+    /// It was merged, or is a bot's.
+    High,
+}
+
+impl Confidence {
+    const VARIANTS: &'static [(&'static str, Confidence)] = &[
+        ("low", Confidence::Low),
+        ("medium", Confidence::Medium),
+        ("high", Confidence::High),
+    ];
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = Self::VARIANTS
+            .iter()
+            .find(|(_, variant)| variant == self)
+            .expect("every variant is listed");
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Confidence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::VARIANTS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, variant)| *variant)
+            .ok_or_else(|| "expected one of: low, medium, high".to_owned())
+    }
+}
+
+/// Why a branch was retained.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    DefaultBranch,
+    NoPullRequests,
+    OpenPullRequest,
+    NotVetted,
+    ReviewRequested,
+    NotMerged,
+    ShallowClone,
+    IntegrationRewritten,
+    /// Deleting it would have been below the minimum confidence.
+    LowConfidence(Confidence),
+    /// Still mentioned in these places, e.g. `issue #12` or `docs/runbook.md:40`.
+    Referenced(Vec<String>),
+    NoUpstream,
+    UpstreamExists,
+    /// No pull request was merged, or whether one was isn't known.
+    ClosedUnmerged,
+    /// Skipped when asked whether to delete it.
+    Declined,
+    /// Checked out, in the main working tree or else in the named worktree,
+    /// so it can't be deleted.
+    CheckedOut(Option<String>),
+    /// This many commits are in neither its upstream nor its pull requests,
+    /// or, its upstream being gone, on no remote-tracking branch.
+    Unpushed(usize),
+    /// Its tip is on none of its closed pull requests' heads, as when it was
+    /// reused for other work.
+    TipMoved,
+    /// Its tip was committed this long ago, more recently than
+    /// [`Options::exclude_recent`](crate::Options::exclude_recent) allows.
+    RecentCommit(Duration),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::DefaultBranch => "default branch",
+            Reason::NoPullRequests => "no pull requests",
+            Reason::OpenPullRequest => "open pull request",
+            Reason::NotVetted => "closed pull requests were not approved or checked",
+            Reason::ReviewRequested => "awaiting my review",
+            Reason::NotMerged => "not merged into integration branch",
+            Reason::ShallowClone => "shallow clone",
+            Reason::IntegrationRewritten => "integration branch was rewritten",
+            Reason::NoUpstream => "no upstream",
+            Reason::UpstreamExists => "upstream still exists",
+            Reason::ClosedUnmerged => "pull requests closed without merging",
+            Reason::Declined => "skipped when asked",
+            Reason::LowConfidence(confidence) => {
+                return write!(f, "only {confidence} confidence that it is done with")
+            }
+            Reason::Referenced(mentions) => {
+                return write!(f, "referenced in {}", mentions.join(", "))
+            }
+            Reason::CheckedOut(None) => "checked out",
+            Reason::CheckedOut(Some(worktree)) => {
+                return write!(f, "checked out in worktree {worktree}")
+            }
+            Reason::TipMoved => "tip moved since its pull requests closed",
+            Reason::RecentCommit(since) => {
+                let minutes = since.as_secs() / 60;
+                let (count, unit) = match minutes {
+                    0..60 => (minutes, "minute"),
+                    60..2880 => (minutes / 60, "hour"),
+                    _ => (minutes / 1440, "day"),
+                };
+                let plural = if count == 1 { "" } else { "s" };
+                return write!(f, "committed to {count} {unit}{plural} ago");
+            }
+            Reason::Unpushed(1) => "1 commit never pushed",
+            Reason::Unpushed(commits) => return write!(f, "{commits} commits never pushed"),
+        })
+    }
+}
+
+/// Render decisions as a Markdown document suitable for sharing.
+///
+/// `review_branches` are local branches which are the head of an open PR
+/// awaiting our review; they get a section of their own whatever the decision.
+pub fn render_markdown(
+    title: &str,
+    decisions: &[Decision],
+    review_branches: &[SmallStr],
+    dry_run: bool,
+) -> String {
+    let mut deleted = Vec::new();
+    let mut bots = Vec::new();
+    let mut retained = Vec::new();
+    let mut failed = Vec::new();
+    for decision in decisions {
+        match &decision.outcome {
+            Outcome::Delete {
+                bot: false,
+                confidence,
+            } => deleted.push(format!(
+                "`{}` ({confidence} confidence)",
+                decision.branch_name
+            )),
+            Outcome::Delete { bot: true, .. } => bots.push(format!("`{}`", decision.branch_name)),
+            Outcome::Retain(reason) => {
+                retained.push(format!("`{}`: {reason}", decision.branch_name))
+            }
+            Outcome::Failed(err) => failed.push(format!("`{}`: {err}", decision.branch_name)),
+        }
+    }
+
+    let mut out = format!("# git-clean report for {title}\n");
+    if dry_run {
+        out.push_str("\nThis was a dry run: nothing has been deleted yet.\n");
+    }
+    let (deleted_heading, bots_heading) = if dry_run {
+        ("Would delete", "Would delete bot branches")
+    } else {
+        ("Deleted", "Deleted bot branches")
+    };
+    for (heading, mut lines) in [
+        (deleted_heading, deleted),
+        (bots_heading, bots),
+        ("Retained", retained),
+        ("Errors", failed),
+        (
+            "Awaiting my review",
+            review_branches
+                .iter()
+                .map(|branch_name| format!("`{branch_name}`"))
+                .collect(),
+        ),
+    ] {
+        if lines.is_empty() {
+            continue;
+        }
+        lines.sort_unstable();
+        let _ = write!(out, "\n## {heading} ({})\n\n", lines.len());
+        for line in lines {
+            let _ = writeln!(out, "- {line}");
+        }
+    }
+    out
+}
@@ -0,0 +1,182 @@
+//! Notice when the remote's default branch has changed, e.g. from `master` to
+//! `main`, and optionally catch the local repository up.
+
+use git2::{BranchType, Repository};
+
+use crate::{error::ContextErr, guard::Mutator, FatalError};
+
+/// Find the name of the branch the local repository still believes is the
+/// remote default, if it differs from `default_branch`.
+///
+/// This consults the remote HEAD symref (`refs/remotes/<remote>/HEAD`), which
+/// fetching never updates. The branch checked out tells nothing: tracking a
+/// branch of the same name is just as usual for a feature branch.
+fn stale_default(repo: &Repository, remote_name: &str, default_branch: &str) -> Option<String> {
+    let remote_prefix = format!("refs/remotes/{remote_name}/");
+    repo.find_reference(&format!("{remote_prefix}HEAD"))
+        .ok()
+        .and_then(|head| head.symbolic_target().map(str::to_owned))
+        .and_then(|target| target.strip_prefix(&remote_prefix).map(str::to_owned))
+        .filter(|head| head != default_branch)
+}
+
+/// Warn if the local repository is still set up for an old remote default
+/// branch, returning that branch.
+pub(crate) fn check(
+    repo: &Repository,
+    remote_name: &str,
+    default_branch: &str,
+    logger: &slog::Logger,
+) -> Option<String> {
+    let old_default = stale_default(repo, remote_name, default_branch)?;
+    slog::warn!(
+        logger, "remote default branch has changed";
+        "old" => &old_default,
+        "new" => default_branch,
+    );
+    Some(old_default)
+}
+
+/// Update the local repository from the old remote default branch
+/// `old_default` to the new `default_branch`.
+pub(crate) fn retarget(
+    mutator: &Mutator,
+    remote_name: &str,
+    default_branch: &str,
+    old_default: &str,
+    logger: &slog::Logger,
+) -> Result<(), FatalError> {
+    let repo = mutator.repo();
+    let new_upstream = format!("refs/remotes/{remote_name}/{default_branch}");
+    if repo.find_reference(&new_upstream).is_err() {
+        slog::warn!(
+            logger, "not retargeting: new default branch has not been fetched";
+            "hint" => format!("run `git fetch {remote_name}` first"),
+        );
+        return Ok(());
+    }
+
+    mutator
+        .reference_symbolic(
+            &format!("refs/remotes/{remote_name}/HEAD"),
+            &new_upstream,
+            true,
+            "git-clean: retarget remote HEAD to new default branch",
+        )
+        .context("retarget remote HEAD")?;
+
+    if let Ok(mut local) = repo.find_branch(old_default, BranchType::Local) {
+        if repo.find_branch(default_branch, BranchType::Local).is_err() {
+            local = mutator
+                .rename_branch(local, default_branch)
+                .context("rename local default branch")?;
+            slog::info!(logger, "renamed local default branch"; "old" => old_default, "new" => default_branch);
+        }
+        if local.name().ok().flatten() == Some(default_branch) {
+            mutator
+                .set_upstream(&mut local, &format!("{remote_name}/{default_branch}"))
+                .context("track new default branch")?;
+        }
+    }
+
+    mutator
+        .config()
+        .and_then(|mut config| config.set_str("init.defaultBranch", default_branch))
+        .context("set init.defaultBranch")?;
+
+    slog::info!(logger, "retargeted local repository to new default branch");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        guard::DeletionGuard,
+        testing::{commit, scratch_repo},
+    };
+
+    fn logger() -> slog::Logger {
+        slog::Logger::root(slog::Discard, slog::o!())
+    }
+
+    #[test]
+    fn ignores_feature_branches_tracking_their_namesakes() {
+        let (dir, repo) = scratch_repo("retarget-feature");
+        let tip = commit(&repo, "refs/heads/feature", "feature");
+        repo.reference("refs/remotes/origin/feature", tip, false, "")
+            .unwrap();
+        let main = repo.refname_to_id("refs/heads/main").unwrap();
+        repo.reference("refs/remotes/origin/main", main, false, "")
+            .unwrap();
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "",
+        )
+        .unwrap();
+        repo.remote("origin", "https://example.com/team/repo.git")
+            .unwrap();
+        repo.find_branch("feature", BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("origin/feature"))
+            .unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+
+        assert_eq!(check(&repo, "origin", "main", &logger()), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retargets_from_a_stale_remote_head() {
+        let (dir, repo) = scratch_repo("retarget-stale");
+        let tip = repo.refname_to_id("refs/heads/main").unwrap();
+        repo.find_reference("refs/heads/main")
+            .unwrap()
+            .rename("refs/heads/master", false, "")
+            .unwrap();
+        repo.set_head("refs/heads/master").unwrap();
+        for branch in ["master", "main"] {
+            repo.reference(&format!("refs/remotes/origin/{branch}"), tip, false, "")
+                .unwrap();
+        }
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/master",
+            false,
+            "",
+        )
+        .unwrap();
+        repo.remote("origin", "https://example.com/team/repo.git")
+            .unwrap();
+
+        let old_default = check(&repo, "origin", "main", &logger()).unwrap();
+        assert_eq!(old_default, "master");
+        let guard = DeletionGuard::new(false).unwrap();
+        retarget(
+            &guard.mutator(&repo),
+            "origin",
+            "main",
+            &old_default,
+            &logger(),
+        )
+        .unwrap();
+
+        assert!(repo.find_branch("master", BranchType::Local).is_err());
+        let main = repo.find_branch("main", BranchType::Local).unwrap();
+        assert_eq!(
+            main.upstream().unwrap().get().name(),
+            Some("refs/remotes/origin/main")
+        );
+        assert_eq!(check(&repo, "origin", "main", &logger()), None);
+        assert_eq!(
+            repo.config()
+                .unwrap()
+                .get_string("init.defaultBranch")
+                .unwrap(),
+            "main"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
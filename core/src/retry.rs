@@ -0,0 +1,94 @@
+//! Retry files: the branches a run could not decide about, so that the next
+//! run can look at just those.
+
+use std::{collections::HashSet, io::ErrorKind, path::Path};
+
+use crate::{report::Decision, SmallStr};
+
+/// Read the branches to retry.
+///
+/// `None` means there is nothing to retry from, so every branch should be
+/// evaluated.
+pub(crate) fn load(path: &Path, logger: &slog::Logger) -> Option<HashSet<SmallStr>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Some(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(SmallStr::from_str)
+                .collect(),
+        ),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => {
+            slog::warn!(
+                logger, "failed to read retry file; evaluating every branch";
+                "path" => %path.display(),
+                "err" => %err,
+            );
+            None
+        }
+    }
+}
+
+/// Write the branches which failed to the retry file, or remove it if none did.
+pub(crate) fn save(path: &Path, decisions: &[Decision], logger: &slog::Logger) {
+    let mut failed = decisions
+        .iter()
+        .filter(|decision| decision.is_failed())
+        .map(|decision| decision.branch_name.as_str())
+        .collect::<Vec<_>>();
+    failed.sort_unstable();
+
+    let result = if failed.is_empty() {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    } else {
+        let mut contents = failed.join("\n");
+        contents.push('\n');
+        std::fs::write(path, contents)
+    };
+    match result {
+        Ok(()) if failed.is_empty() => {}
+        Ok(()) => slog::info!(
+            logger, "wrote branches to retry";
+            "path" => %path.display(),
+            "branches" => failed.len(),
+        ),
+        Err(err) => slog::error!(
+            logger, "failed to update retry file";
+            "path" => %path.display(),
+            "err" => %err,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{report::Reason, FatalError};
+
+    #[test]
+    fn retries_only_failed_branches() {
+        let path = std::env::temp_dir().join(format!("git-clean-retry-{}", std::process::id()));
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(load(&path, &logger), None);
+
+        let decisions = [
+            Decision::failed("b".into(), FatalError::RemoteUrlNotGithub),
+            Decision::retain("c".into(), Reason::NoPullRequests),
+            Decision::failed("a".into(), FatalError::RemoteUrlNotGithub),
+        ];
+        save(&path, &decisions, &logger);
+        assert_eq!(
+            load(&path, &logger),
+            Some(HashSet::from(["a".into(), "b".into()]))
+        );
+
+        save(&path, &decisions[1..2], &logger);
+        assert!(!path.exists());
+    }
+}
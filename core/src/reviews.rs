@@ -0,0 +1,45 @@
+//! Find local branches which were checked out to review someone else's PR.
+//!
+//! Such branches usually have no PR of their own from our point of view, but
+//! deleting one while the review is still pending is a nuisance.
+
+use std::collections::HashSet;
+
+use octocrab::Octocrab;
+
+use crate::{error::ContextErr, FatalError, SmallStr};
+
+/// Head branch names of the open PRs on which the current user is a
+/// requested reviewer.
+///
+/// This needs an authenticated client.
+pub(crate) async fn review_requested_heads(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+) -> Result<HashSet<SmallStr>, FatalError> {
+    let page = octocrab
+        .search()
+        .issues_and_pull_requests(&format!(
+            "is:pr is:open repo:{owner}/{repo_name} review-requested:@me"
+        ))
+        .per_page(100)
+        .send()
+        .await
+        .context("search for pull requests awaiting my review")?;
+    let issues = octocrab
+        .all_pages(page)
+        .await
+        .context("get rest of pages for pull requests awaiting my review")?;
+
+    let mut heads = HashSet::with_capacity(issues.len());
+    for issue in issues {
+        let pr = octocrab
+            .pulls(owner, repo_name)
+            .get(issue.number)
+            .await
+            .context("get pull request awaiting my review")?;
+        heads.insert(SmallStr::from_string(pr.head.ref_field));
+    }
+    Ok(heads)
+}
@@ -0,0 +1,117 @@
+//! Per-branch log lines, thinned out on enormous runs.
+//!
+//! With tens of thousands of branches, a line per branch buries everything
+//! else and can outrun an asynchronous drain. The first few lines of each
+//! kind are logged as usual; the rest are only counted, and summed up in one
+//! line per kind once every branch is decided.
+
+use std::sync::Mutex;
+
+use crate::{Decision, Outcome, Reason};
+
+pub(crate) struct LogSampler {
+    /// How many lines of each kind to log; `None` logs them all.
+    limit: Option<usize>,
+    /// Lines seen of each kind, in the order the kinds were first seen.
+    seen: Mutex<Vec<(String, usize)>>,
+}
+
+impl LogSampler {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            seen: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Log what was decided about a branch, unless enough such decisions were
+    /// logged already.
+    pub fn log_decision(&self, decision: &Decision, logger: &slog::Logger) {
+        let kind = match &decision.outcome {
+            Outcome::Delete { bot: false, .. } => "deleting branch".to_owned(),
+            Outcome::Delete { bot: true, .. } => "deleting bot branch".to_owned(),
+            Outcome::Retain(reason) => format!("retaining branch: {}", kind(reason)),
+            Outcome::Failed(_) => return,
+        };
+        if !self.sample(kind) {
+            return;
+        }
+        match &decision.outcome {
+            Outcome::Delete {
+                bot: false,
+                confidence,
+            } => slog::info!(logger, "deleting branch"; "confidence" => %confidence),
+            Outcome::Delete { bot: true, .. } => slog::info!(logger, "deleting bot branch"),
+            Outcome::Retain(reason) => {
+                slog::debug!(logger, "retaining branch"; "reason" => %reason)
+            }
+            Outcome::Failed(_) => {}
+        }
+    }
+
+    /// Whether to log this line of `kind`.
+    fn sample(&self, kind: String) -> bool {
+        let Some(limit) = self.limit else {
+            return true;
+        };
+        let mut seen = self
+            .seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let count = match seen.iter_mut().find(|(known, _)| *known == kind) {
+            Some((_, count)) => count,
+            None => {
+                seen.push((kind, 0));
+                &mut seen.last_mut().expect("just pushed").1
+            }
+        };
+        *count += 1;
+        *count <= limit
+    }
+
+    /// Log how many lines of each kind were left out.
+    pub fn summarize(&self, logger: &slog::Logger) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let seen = self
+            .seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (kind, count) in seen.iter().filter(|(_, count)| *count > limit) {
+            slog::info!(
+                logger, "left branches out of the log";
+                "kind" => kind,
+                "skipped" => count - limit,
+                "total" => count,
+            );
+        }
+    }
+}
+
+/// Why a branch was retained, without what differs between branches.
+fn kind(reason: &Reason) -> String {
+    match reason {
+        Reason::LowConfidence(_) => "low confidence".to_owned(),
+        Reason::Referenced(_) => "still referenced".to_owned(),
+        Reason::CheckedOut(_) => "checked out".to_owned(),
+        Reason::Unpushed(_) => "unpushed commits".to_owned(),
+        Reason::RecentCommit(_) => "committed to recently".to_owned(),
+        reason => reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_each_kind_separately() {
+        let sampler = LogSampler::new(Some(2));
+        let sampled = ["a", "a", "b", "a", "b", "b"]
+            .map(|kind| sampler.sample(kind.to_owned()))
+            .to_vec();
+        assert_eq!(sampled, [true, true, true, false, true, false]);
+        assert!(LogSampler::new(None).sample("a".to_owned()));
+    }
+}
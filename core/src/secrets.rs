@@ -0,0 +1,156 @@
+//! Encryption of the config's secrets at rest.
+//!
+//! Tokens are sealed with ChaCha20-Poly1305 under a data key kept in a key
+//! file of its own. A copy of the config alone, such as the one in a cloud
+//! backup of the home directory, then reveals nothing; keep the key file
+//! somewhere which isn't backed up.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use crate::config::Error;
+
+/// Marks a sealed value, and the scheme it was sealed with.
+const PREFIX: &str = "enc:v1:";
+/// Overrides where the key file is kept.
+pub const KEY_FILE_VAR: &str = "GIT_CLEAN_KEY_FILE";
+const NONCE_LEN: usize = 12;
+
+fn secret_error(context: &str) -> Error {
+    Error::Secret(context.to_owned())
+}
+
+/// Where the data key is kept.
+///
+/// Defaults to the local (not roaming) data directory, as that is the least
+/// likely to be synced.
+pub fn key_path() -> PathBuf {
+    std::env::var_os(KEY_FILE_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            dirs::data_local_dir()
+                .expect("platform has a local data dir")
+                .join("git-clean")
+                .join("key")
+        })
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Load the data key from the key file at `path`, which must already exist.
+pub fn load_key_at(path: &Path) -> Result<Key, Error> {
+    let encoded = std::fs::read_to_string(path).map_err(|inner| Error::Io {
+        context: format!("read secrets key from {}", path.display()),
+        inner,
+    })?;
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| secret_error("secrets key file is not valid base64"))?;
+    if bytes.len() != 32 {
+        return Err(secret_error(
+            "secrets key file holds a key of the wrong length",
+        ));
+    }
+    Ok(*Key::from_slice(&bytes))
+}
+
+/// Load the data key, which must already exist.
+pub fn load_key() -> Result<Key, Error> {
+    load_key_at(&key_path())
+}
+
+/// Load the data key, generating one if there is none yet.
+pub fn load_or_create_key() -> Result<Key, Error> {
+    load_or_create_key_at(&key_path())
+}
+
+/// Load the data key from the key file at `path`, generating one if there is
+/// none yet.
+pub fn load_or_create_key_at(path: &Path) -> Result<Key, Error> {
+    if path.exists() {
+        return load_key_at(path);
+    }
+
+    let io_error = |context: &str| {
+        let context = format!("{context} {}", path.display());
+        move |inner| Error::Io { context, inner }
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_error("create directory for"))?;
+    }
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(io_error("create secrets key"))?;
+    writeln!(file, "{}", STANDARD.encode(key)).map_err(io_error("write secrets key"))?;
+    Ok(key)
+}
+
+/// Encrypt a secret for storage.
+pub fn seal(key: &Key, plaintext: &str) -> Result<String, Error> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| secret_error("failed to encrypt secret"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(format!("{PREFIX}{}", STANDARD.encode(sealed)))
+}
+
+/// Decrypt a secret sealed by [`seal`].
+pub fn open(key: &Key, sealed: &str) -> Result<String, Error> {
+    let bytes = sealed
+        .strip_prefix(PREFIX)
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .filter(|bytes| bytes.len() > NONCE_LEN)
+        .ok_or_else(|| secret_error("sealed secret is malformed"))?;
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| secret_error("failed to decrypt secret; is this the right key file?"))?;
+    String::from_utf8(plaintext).map_err(|_| secret_error("decrypted secret is not utf-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_key(name: &str) -> Key {
+        let path =
+            std::env::temp_dir().join(format!("git-clean-{name}-{}.key", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        load_or_create_key_at(&path).unwrap()
+    }
+
+    #[test]
+    fn opens_only_what_its_key_sealed() {
+        let key = scratch_key("key");
+        let sealed = seal(&key, "abc").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("abc"));
+        assert_eq!(open(&key, &sealed).unwrap(), "abc");
+
+        assert!(open(&scratch_key("other-key"), &sealed).is_err());
+        // a flipped character within the ciphertext
+        let at = sealed.len() - 5;
+        let flipped = if &sealed[at..=at] == "A" { "B" } else { "A" };
+        let tampered = format!("{}{flipped}{}", &sealed[..at], &sealed[at + 1..]);
+        assert!(open(&key, &tampered).is_err());
+        assert!(open(&key, "abc").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Fetching the token from AWS Secrets Manager.
+//!
+//! There's no SDK here: a single `GetSecretValue` call, signed with AWS
+//! Signature Version 4, is all we need.
+
+use hmac::{Hmac, Mac};
+use http::Request;
+use sha2::{Digest, Sha256};
+
+use crate::config::{AwsSecretSource, Error};
+
+const SERVICE: &str = "secretsmanager";
+const TARGET: &str = "secretsmanager.GetSecretValue";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+fn env(name: &str) -> Result<String, Error> {
+    std::env::var(name).map_err(|_| Error::TokenSource(format!("{name} is not set")))
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// The key requests are signed with, derived from the secret access key.
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{secret_access_key}").as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    hmac(&key, "aws4_request")
+}
+
+/// Read the token from the configured secret.
+pub(crate) async fn fetch(source: &AwsSecretSource) -> Result<String, Error> {
+    let region = match &source.region {
+        Some(region) => region.clone(),
+        None => env("AWS_REGION")?,
+    };
+    let access_key_id = env("AWS_ACCESS_KEY_ID")?;
+    let secret_access_key = env("AWS_SECRET_ACCESS_KEY")?;
+    let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+
+    let host = format!("{SERVICE}.{region}.amazonaws.com");
+    let body = serde_json::json!({ "SecretId": source.secret_id }).to_string();
+    let now = chrono::Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    // headers to sign, in order by name
+    let mut headers = vec![
+        ("content-type", CONTENT_TYPE.to_owned()),
+        ("host", host.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(session_token) = &session_token {
+        headers.push(("x-amz-security-token", session_token.clone()));
+    }
+    headers.push(("x-amz-target", TARGET.to_owned()));
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect::<String>();
+    let canonical_request = format!(
+        "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+        hex(&Sha256::digest(body.as_bytes()))
+    );
+    let scope = format!("{date}/{region}/{SERVICE}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex(&hmac(
+        &signing_key(&secret_access_key, &date, &region, SERVICE),
+        &string_to_sign,
+    ));
+
+    let mut request = Request::post(format!("https://{host}/")).header(
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={access_key_id}/{scope}, SignedHeaders={signed_headers}, Signature={signature}"
+        ),
+    );
+    for (name, value) in &headers {
+        request = request.header(*name, value);
+    }
+    let request = request
+        .body(body)
+        .map_err(|err| Error::TokenSource(format!("invalid Secrets Manager request: {err}")))?;
+
+    let (status, body) = crate::http::send(request)
+        .await
+        .map_err(|err| Error::TokenSource(format!("failed to reach Secrets Manager: {err}")))?;
+    let body = serde_json::from_slice::<serde_json::Value>(&body)
+        .map_err(|_| Error::TokenSource("Secrets Manager sent an unreadable response".into()))?;
+    if !status.is_success() {
+        let kind = body
+            .get("__type")
+            .and_then(|kind| kind.as_str())
+            .unwrap_or("unknown error");
+        return Err(Error::TokenSource(format!(
+            "Secrets Manager refused to read {}: {status} ({kind})",
+            source.secret_id
+        )));
+    }
+
+    let secret = body
+        .get("SecretString")
+        .and_then(|secret| secret.as_str())
+        .ok_or_else(|| {
+            Error::TokenSource(format!("secret {} has no string value", source.secret_id))
+        })?;
+    let Some(field) = &source.field else {
+        return Ok(secret.trim().to_owned());
+    };
+    serde_json::from_str::<serde_json::Value>(secret)
+        .ok()
+        .and_then(|secret| secret.get(field)?.as_str().map(str::to_owned))
+        .ok_or_else(|| {
+            Error::TokenSource(format!(
+                "secret {} has no field `{field}`",
+                source.secret_id
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The worked example from AWS's Signature Version 4 documentation.
+    #[test]
+    fn derives_signing_key() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
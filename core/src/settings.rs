@@ -0,0 +1,248 @@
+//! Every setting in effect for a repository, and where it came from, for
+//! `git-clean config show`.
+//!
+//! Settings come from, in increasing precedence, defaults, the config file,
+//! and git's config, whose own levels git orders system, global, local.
+//! Flags given to a run override them all, but are not listed, as
+//! `config show` takes none. Tokens for GitLab and Bitbucket come from the
+//! environment. When a setting is given in more
+//! than one place, every value is listed, and all but the one in effect are
+//! marked as overridden. A repository's own `apiBaseUrl` and `gitlabHost`
+//! are never in effect, so are always marked so.
+
+use std::{fmt, path::Path};
+
+use git2::ConfigLevel;
+
+use crate::{ancestry, config::Config, error::ContextErr, http::GITHUB_API, FatalError};
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    ConfigFile,
+    /// git's config, at this level, such as `global`.
+    GitConfig(&'static str),
+    /// This environment variable.
+    Env(&'static str),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => f.write_str("default"),
+            Origin::ConfigFile => write!(f, "config file {}", Config::path().display()),
+            Origin::GitConfig(level) => write!(f, "git config ({level})"),
+            Origin::Env(var) => write!(f, "environment ${var}"),
+        }
+    }
+}
+
+/// One value of one setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    /// The key as written where it was set.
+    pub key: String,
+    /// Secrets are shown only as `(set)`.
+    pub value: String,
+    pub origin: Origin,
+    /// Another value of the same setting takes precedence.
+    pub overridden: bool,
+}
+
+impl Setting {
+    fn new(key: &str, value: impl Into<String>, origin: Origin) -> Self {
+        Self {
+            key: key.to_owned(),
+            value: value.into(),
+            origin,
+            overridden: false,
+        }
+    }
+}
+
+/// Keys of `[git-clean]` in git's config which hold one value, the last one
+/// given winning, with what applies when none is.
+const SINGLE_KEYS: &[(&str, &str)] = &[
+    ("apiBaseUrl", GITHUB_API),
+    ("minConfidence", "(none)"),
+    ("requireVetting", "(none)"),
+    (
+        "integrationBranch",
+        "remote default, else init.defaultBranch, else main, else master",
+    ),
+    ("requireMerged", "false"),
+    ("keepReviewRequests", "false"),
+    ("grepReferences", "false"),
+];
+
+/// Keys of `[git-clean]` in git's config which may be given more than once,
+/// every value counting.
+const MULTI_KEYS: &[&str] = &["gitlabHost", "include", "exclude"];
+
+/// Keys which only count set outside of the repository, as they decide where
+/// tokens are sent.
+const OUTSIDE_REPO_KEYS: &[&str] = &["apiBaseUrl", "gitlabHost"];
+
+const ENV_VARS: &[&str] = &[
+    "GITLAB_TOKEN",
+    "BITBUCKET_TOKEN",
+    "VAULT_TOKEN",
+    "VAULT_ADDR",
+    crate::secrets::KEY_FILE_VAR,
+];
+
+/// Every setting in effect for the repository at `path`, and those
+/// overridden, grouped by setting.
+pub fn effective(path: impl AsRef<Path>) -> Result<Vec<Setting>, FatalError> {
+    let config = Config::load_or_default().map_err(FatalError::ConfigFile)?;
+    let repo = ancestry::open_repository(path)?;
+    let git_config = repo
+        .config()
+        .and_then(|mut config| config.snapshot())
+        .context("read git config")?;
+    let mut git_entries = Vec::new();
+    git_config
+        .entries(Some("^git-clean\\."))
+        .context("read git config")?
+        .for_each(|entry| {
+            let (Some(name), Some(value)) = (entry.name(), entry.value()) else {
+                return;
+            };
+            git_entries.push((
+                name.trim_start_matches("git-clean.").to_owned(),
+                value.to_owned(),
+                level_name(entry.level()),
+            ));
+        })
+        .context("read git config")?;
+
+    let mut settings = Vec::new();
+
+    // the primary token's sources, in decreasing precedence
+    let mut tokens = Vec::new();
+    // a command may well hold a secret of its own
+    if config.token_command.is_some() {
+        tokens.push(Setting::new("token_command", "(set)", Origin::ConfigFile));
+    }
+    if config.vault.is_some() {
+        tokens.push(Setting::new("vault", "(set)", Origin::ConfigFile));
+    }
+    if config.aws_secrets_manager.is_some() {
+        tokens.push(Setting::new(
+            "aws_secrets_manager",
+            "(set)",
+            Origin::ConfigFile,
+        ));
+    }
+    if !config.personal_access_token.is_empty() {
+        tokens.push(Setting::new(
+            "personal_access_token",
+            "(set)",
+            Origin::ConfigFile,
+        ));
+    }
+    for overridden in tokens.iter_mut().skip(1) {
+        overridden.overridden = true;
+    }
+    settings.extend(tokens);
+    if !config.additional_tokens.is_empty() {
+        settings.push(Setting::new(
+            "additional_tokens",
+            format!("({} set)", config.additional_tokens.len()),
+            Origin::ConfigFile,
+        ));
+    }
+    if config.encrypt_secrets {
+        settings.push(Setting::new("encrypt_secrets", "true", Origin::ConfigFile));
+    }
+
+    for (key, default) in SINGLE_KEYS {
+        let mut values = vec![Setting::new(key, *default, Origin::Default)];
+        if *key == "apiBaseUrl" {
+            if let Some(url) = &config.api_base_url {
+                values.push(Setting::new("api_base_url", url, Origin::ConfigFile));
+            }
+        }
+        let (ignored, applied): (Vec<_>, Vec<_>) = git_entries
+            .iter()
+            .filter(|(name, _, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value, level)| Setting::new(key, value, Origin::GitConfig(level)))
+            .partition(|setting| is_ignored(key, &setting.origin));
+        values.extend(applied);
+        // later sources take precedence
+        let last = values.len() - 1;
+        for overridden in &mut values[..last] {
+            overridden.overridden = true;
+        }
+        // a default which is overridden is no news
+        values.retain(|value| !(value.overridden && value.origin == Origin::Default));
+        settings.extend(values);
+        settings.extend(ignored.into_iter().map(|setting| Setting {
+            overridden: true,
+            ..setting
+        }));
+    }
+    for key in MULTI_KEYS {
+        settings.extend(
+            git_entries
+                .iter()
+                .filter(|(name, _, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value, level)| {
+                    let origin = Origin::GitConfig(level);
+                    Setting {
+                        overridden: is_ignored(key, &origin),
+                        ..Setting::new(key, value, origin)
+                    }
+                }),
+        );
+    }
+    if !config.repositories.is_empty() {
+        settings.push(Setting::new(
+            "repositories",
+            format!("({} registered)", config.repositories.len()),
+            Origin::ConfigFile,
+        ));
+    }
+    for pattern in &config.protected_branches {
+        settings.push(Setting::new(
+            "protected_branches",
+            pattern,
+            Origin::ConfigFile,
+        ));
+    }
+
+    for var in ENV_VARS {
+        if let Ok(value) = std::env::var(var) {
+            let value = if var.ends_with("_TOKEN") {
+                "(set)".to_owned()
+            } else {
+                value
+            };
+            settings.push(Setting::new(var, value, Origin::Env(var)));
+        }
+    }
+    Ok(settings)
+}
+
+/// Whether `key` is ignored where it is set, at `origin`: in git's config
+/// inside the repository, for a key which must come from outside of it.
+fn is_ignored(key: &str, origin: &Origin) -> bool {
+    OUTSIDE_REPO_KEYS.contains(&key)
+        && !matches!(
+            origin,
+            Origin::GitConfig("program data" | "system" | "xdg" | "global")
+        )
+}
+
+fn level_name(level: ConfigLevel) -> &'static str {
+    match level {
+        ConfigLevel::ProgramData => "program data",
+        ConfigLevel::System => "system",
+        ConfigLevel::XDG => "xdg",
+        ConfigLevel::Global => "global",
+        ConfigLevel::Local => "local",
+        ConfigLevel::App => "app",
+        ConfigLevel::Highest => "highest",
+    }
+}
@@ -0,0 +1,54 @@
+//! Clones which borrow objects from one another.
+//!
+//! A clone made with `--reference` lists the object store it borrows from in
+//! `objects/info/alternates`. Deleting a branch here is harmless to git, but
+//! the person working in the sibling clone may still be using a branch of the
+//! same name, and would be surprised when a later `gc` in one clone makes the
+//! other lose commits.
+
+use std::path::{Path, PathBuf};
+
+use git2::{BranchType, Repository};
+
+use crate::{preflight::common_dir, SmallStr};
+
+/// The git directories of the repositories whose object stores this one borrows.
+fn alternate_repos(repo: &Repository) -> Vec<PathBuf> {
+    let objects_dir = common_dir(repo).join("objects");
+    let Ok(alternates) = std::fs::read_to_string(objects_dir.join("info").join("alternates"))
+    else {
+        return Vec::new();
+    };
+
+    alternates
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            // relative entries are relative to our own objects directory
+            let alternate = objects_dir.join(Path::new(line));
+            alternate.parent().map(Path::to_owned)
+        })
+        .collect()
+}
+
+/// Find the doomed branches which also exist in a sibling clone.
+///
+/// Returns each such branch with the path of the sibling which has it.
+pub(crate) fn shared_branches(
+    repo: &Repository,
+    branch_names: &[SmallStr],
+) -> Vec<(SmallStr, PathBuf)> {
+    let mut shared = Vec::new();
+    for sibling_path in alternate_repos(repo) {
+        let Ok(sibling) = Repository::open(&sibling_path) else {
+            continue;
+        };
+        for branch_name in branch_names {
+            if sibling.find_branch(branch_name, BranchType::Local).is_ok() {
+                shared.push((branch_name.clone(), sibling_path.clone()));
+            }
+        }
+    }
+    shared
+}
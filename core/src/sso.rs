@@ -0,0 +1,78 @@
+//! Detect tokens which are not authorized for an organization's SAML SSO.
+//!
+//! GitHub does not fail searches made with such a token: it silently omits
+//! the organization's results and flags the omission in a response header.
+//! Every branch would then look like it had no PRs, so we check up front.
+
+use octocrab::Octocrab;
+
+use crate::{error::ContextErr, FatalError};
+
+const SSO_HEADER: &str = "x-github-sso";
+
+/// Where to authorize a token when GitHub doesn't tell us.
+const TOKEN_SETTINGS_URL: &str = "https://github.com/settings/tokens";
+
+/// Interpret an `X-GitHub-SSO` header value, returning the url at which the
+/// token can be authorized if it needs to be.
+///
+/// The header looks like `required; url=https://...` or
+/// `partial-results; organizations=123,456`.
+fn authorization_url(header: &str) -> Option<String> {
+    let mut parts = header.split(';').map(str::trim);
+    match parts.next()? {
+        "required" => Some(
+            parts
+                .find_map(|part| part.strip_prefix("url="))
+                .unwrap_or(TOKEN_SETTINGS_URL)
+                .to_owned(),
+        ),
+        "partial-results" => Some(TOKEN_SETTINGS_URL.to_owned()),
+        _ => None,
+    }
+}
+
+/// Fail if the token isn't SSO-authorized for the repository's organization.
+pub(crate) async fn check_authorized(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+) -> Result<(), FatalError> {
+    let search = format!("/search/issues?q=is%3Apr+repo%3A{owner}%2F{repo_name}&per_page=1");
+    for route in [format!("/repos/{owner}/{repo_name}"), search] {
+        let response = octocrab
+            ._get(route.as_str())
+            .await
+            .context("check token SSO authorization")?;
+        if let Some(url) = response
+            .headers()
+            .get(SSO_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(authorization_url)
+        {
+            return Err(FatalError::SsoAuthorizationRequired { url });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sso_header() {
+        assert_eq!(
+            authorization_url(
+                "required; url=https://github.com/orgs/acme/sso?authorization_request=abc"
+            )
+            .as_deref(),
+            Some("https://github.com/orgs/acme/sso?authorization_request=abc"),
+        );
+        assert_eq!(
+            authorization_url("partial-results; organizations=21955855,20582480").as_deref(),
+            Some(TOKEN_SETTINGS_URL),
+        );
+        assert_eq!(authorization_url("something-new"), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Clean up refs left behind by tools, such as `refs/original/*` from
+//! `git filter-branch`.
+//!
+//! These refs are invisible in day-to-day use, but they keep every object
+//! they reach alive.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use git2::Repository;
+use globset::GlobSet;
+
+use crate::{error::ContextErr, guard::Mutator, journal, FatalError};
+
+/// Ref namespaces cleaned when none are configured.
+pub const DEFAULT_STALE_REF_NAMESPACES: &[&str] = &["refs/original/**", "refs/backup/**"];
+
+/// The refs matching `namespaces` whose commits are older than `max_age`.
+pub(crate) fn find(
+    repo: &Repository,
+    namespaces: &GlobSet,
+    max_age: Duration,
+    logger: &slog::Logger,
+) -> Result<Vec<String>, FatalError> {
+    let cutoff = SystemTime::now()
+        .checked_sub(max_age)
+        .and_then(|cutoff| cutoff.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_secs() as i64)
+        .unwrap_or_default();
+
+    let mut stale = Vec::new();
+    for reference in repo
+        .references()
+        .context("list references")?
+        .filter_map(Result::ok)
+    {
+        let Some(refname) = reference.name() else {
+            continue;
+        };
+        if !namespaces.is_match(refname) {
+            continue;
+        }
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        if commit.time().seconds() < cutoff {
+            slog::info!(logger, "stale ref"; "ref" => refname);
+            stale.push(refname.to_owned());
+        } else {
+            slog::debug!(logger, "retaining recent ref"; "ref" => refname);
+        }
+    }
+
+    Ok(stale)
+}
+
+/// Delete the `stale` refs in a single transaction.
+pub(crate) fn delete(
+    mutator: &Mutator,
+    stale: &[String],
+    logger: &slog::Logger,
+) -> Result<(), FatalError> {
+    if stale.is_empty() {
+        return Ok(());
+    }
+    let run_id = journal::record(mutator, stale, &HashMap::new())?;
+    slog::info!(logger, "journaled stale refs before deleting them"; "run id" => run_id);
+
+    let mut transaction = mutator
+        .transaction()
+        .map_err(FatalError::DeletionTransaction)?;
+    for refname in stale {
+        transaction
+            .lock_ref(refname)
+            .and_then(|()| transaction.remove(refname))
+            .map_err(FatalError::DeletionTransaction)?;
+    }
+    transaction
+        .commit()
+        .map_err(FatalError::DeletionTransaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::{Signature, Time};
+    use globset::{Glob, GlobSetBuilder};
+
+    use super::*;
+    use crate::{
+        guard::DeletionGuard,
+        testing::{commit, scratch_repo},
+    };
+
+    #[test]
+    fn deletes_only_old_refs_in_the_namespaces_and_journals_them() {
+        let (dir, repo) = scratch_repo("stale-refs");
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let long_ago = Signature::new("test", "test@example.com", &Time::new(0, 0)).unwrap();
+        let tree = repo
+            .find_commit(repo.refname_to_id("refs/heads/main").unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        for refname in ["refs/original/refs/heads/main", "refs/kept/old"] {
+            repo.commit(Some(refname), &long_ago, &long_ago, "old", &tree, &[])
+                .unwrap();
+        }
+        commit(&repo, "refs/backup/recent", "recent");
+
+        let mut namespaces = GlobSetBuilder::new();
+        for namespace in DEFAULT_STALE_REF_NAMESPACES {
+            namespaces.add(Glob::new(namespace).unwrap());
+        }
+        let namespaces = namespaces.build().unwrap();
+        let stale = find(&repo, &namespaces, Duration::from_secs(86400), &logger).unwrap();
+        assert_eq!(stale, ["refs/original/refs/heads/main"]);
+
+        let guard = DeletionGuard::new(false).unwrap();
+        delete(&guard.mutator(&repo), &stale, &logger).unwrap();
+        assert!(repo
+            .find_reference("refs/original/refs/heads/main")
+            .is_err());
+        assert!(repo.find_reference("refs/kept/old").is_ok());
+        assert!(repo.find_reference("refs/backup/recent").is_ok());
+
+        let run_id = std::fs::read_dir(dir.join(".git/git-clean/journal"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .file_name();
+        let restored = journal::recover(&dir, run_id.to_str().unwrap(), None, None, false).unwrap();
+        assert_eq!(restored, stale);
+        assert!(repo.find_reference("refs/original/refs/heads/main").is_ok());
+    }
+}
@@ -0,0 +1,37 @@
+//! Scratch repositories for tests.
+
+use std::path::PathBuf;
+
+use git2::{Oid, Repository, Signature};
+
+/// A repository in a fresh temporary directory, with a commit on `main`.
+pub(crate) fn scratch_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = std::env::temp_dir().join(format!("git-clean-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    commit(&repo, "refs/heads/main", "init");
+    repo.set_head("refs/heads/main").unwrap();
+    (dir, repo)
+}
+
+/// Commit to `refname`, on top of its commit if it has one.
+pub(crate) fn commit(repo: &Repository, refname: &str, message: &str) -> Oid {
+    let signature = Signature::now("test", "test@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parent = repo
+        .refname_to_id(refname)
+        .ok()
+        .map(|oid| repo.find_commit(oid).unwrap());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(
+        Some(refname),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+
+use slog::Logger;
+
+use crate::{
+    config::{Config, Error},
+    logging,
+};
+
+pub fn save<'a>(personal_access_token: impl Into<Cow<'a, str>>) -> Result<(), Error> {
+    let personal_access_token = personal_access_token.into().into_owned();
+    Config::update(|config| config.personal_access_token = personal_access_token)
+}
+
+/// Add a token to spread the rate limit across, if it isn't already saved.
+pub fn add<'a>(token: impl Into<Cow<'a, str>>) -> Result<(), Error> {
+    let token = token.into().into_owned();
+    Config::update(|config| {
+        if config.personal_access_token != token && !config.additional_tokens.contains(&token) {
+            config.additional_tokens.push(token);
+        }
+    })
+}
+
+/// Get the primary token from a command, such as a secret manager's CLI,
+/// instead of storing it.
+///
+/// The command is run by the shell whenever tokens are loaded; once it is set,
+/// it takes precedence over a stored personal access token.
+pub fn set_command(command: impl Into<String>) -> Result<(), Error> {
+    let command = command.into();
+    Config::update(|config| config.token_command = Some(command))
+}
+
+/// Run the configured token command, returning what it printed.
+fn run_token_command(command: &str) -> Result<String, Error> {
+    #[cfg(windows)]
+    let output = std::process::Command::new("cmd")
+        .args(["/C", command])
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .output();
+
+    let output = output.map_err(|inner| Error::Io {
+        context: "run token command".into(),
+        inner,
+    })?;
+    if !output.status.success() {
+        // stderr is not shown: some tools print the secret in their errors
+        return Err(Error::TokenSource(format!(
+            "token command failed ({})",
+            output.status
+        )));
+    }
+    let token = String::from_utf8(output.stdout)
+        .map_err(|_| Error::TokenSource("token command printed invalid utf-8".into()))?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(Error::TokenSource("token command printed nothing".into()));
+    }
+    Ok(token.to_owned())
+}
+
+/// Talk to the GitHub Enterprise Server API at `url` from now on, instead of
+/// github.com's; the saved tokens are for that server.
+pub fn set_api_base_url(url: impl Into<String>) -> Result<(), Error> {
+    let url = url.into();
+    Config::update(|config| config.api_base_url = Some(url))
+}
+
+/// The saved GitHub Enterprise Server API url, if any.
+pub fn api_base_url() -> Result<Option<String>, Error> {
+    Ok(Config::load_or_default()?.api_base_url)
+}
+
+/// The branches the config protects from deletion, as names or globs.
+///
+/// Pass them on as [`Options::exclude_branches`](crate::Options::exclude_branches).
+pub fn protected_branches() -> Result<Vec<String>, Error> {
+    Ok(Config::load_or_default()?.protected_branches)
+}
+
+/// Store saved tokens encrypted from now on.
+///
+/// The key is kept apart from the config, at [`crate::secrets::key_path`], and is
+/// created if need be.
+pub fn encrypt() -> Result<(), Error> {
+    Config::update(|config| config.encrypt_secrets = true)
+}
+
+/// Fetch the primary token from whichever secret store the config names.
+///
+/// Returns `None` when it names none, so the stored token is used.
+async fn fetch_primary(config: &Config) -> Result<Option<String>, Error> {
+    if let Some(command) = &config.token_command {
+        return run_token_command(command).map(Some);
+    }
+    if let Some(source) = &config.vault {
+        #[cfg(feature = "vault")]
+        return crate::vault::fetch(source).await.map(Some);
+        #[cfg(not(feature = "vault"))]
+        {
+            let _ = source;
+            return Err(Error::TokenSource(
+                "config names a Vault secret, but git-clean was built without the `vault` feature"
+                    .into(),
+            ));
+        }
+    }
+    if let Some(source) = &config.aws_secrets_manager {
+        #[cfg(feature = "aws-secrets-manager")]
+        return crate::secrets_manager::fetch(source).await.map(Some);
+        #[cfg(not(feature = "aws-secrets-manager"))]
+        {
+            let _ = source;
+            return Err(Error::TokenSource(
+                "config names an AWS secret, but git-clean was built without the `aws-secrets-manager` feature"
+                    .into(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+/// Load all saved tokens, primary token first.
+///
+/// The primary token comes from the first of `token_command`, `vault` and
+/// `aws_secrets_manager` which is configured, else from the config itself.
+///
+/// Unknown config keys are logged as warnings, or rejected when `strict`. A
+/// missing or unreadable config just means there are no tokens, but a config
+/// we must not misread is an error.
+pub async fn load(strict: bool) -> Result<Vec<String>, Error> {
+    load_tokens(&logging::logger(), strict).await
+}
+
+/// As [`load`], logging to `logger`.
+#[deprecated(
+    since = "1.1.0",
+    note = "set the logger once with `set_logger`, and call `load`"
+)]
+pub async fn load_logged(logger: &Logger, strict: bool) -> Result<Vec<String>, Error> {
+    load_tokens(logger, strict).await
+}
+
+async fn load_tokens(logger: &Logger, strict: bool) -> Result<Vec<String>, Error> {
+    let config = match Config::load_checked(strict) {
+        Ok((config, unknown_keys)) => {
+            for unknown_key in unknown_keys {
+                slog::warn!(logger, "{unknown_key}"; "path" => %Config::path().display());
+            }
+            config
+        }
+        Err(err @ (Error::NewerVersion { .. } | Error::UnknownKey(_))) => return Err(err),
+        Err(err) => {
+            slog::info!(logger, "attempting to get personal access token from config"; "err" => %err);
+            return Ok(Vec::new());
+        }
+    };
+
+    let primary = match fetch_primary(&config).await? {
+        Some(token) => token,
+        None => config.personal_access_token,
+    };
+    let tokens = std::iter::once(primary)
+        .filter(|token| !token.is_empty())
+        .chain(config.additional_tokens)
+        .collect::<Vec<_>>();
+    for token in &tokens {
+        crate::redact::register(token);
+    }
+    Ok(tokens)
+}
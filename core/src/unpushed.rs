@@ -0,0 +1,131 @@
+//! Commits on a local branch which were never pushed.
+//!
+//! A branch whose pull request was closed may since have had more work
+//! committed to it locally. That work is in neither the pull request nor the
+//! remote, so deleting the branch would leave it only in the reflog. Or the
+//! branch may have been reset and reused for other work, pushed or not.
+
+use git2::{Oid, Repository};
+
+use crate::{PrSummary, SmallStr};
+
+/// How many commits on `branch_name` are in neither its upstream nor the
+/// heads of `pull_requests`.
+///
+/// `None` if there is nothing to compare against: the upstream is gone and no
+/// pull request's head is known locally.
+pub(crate) fn count(
+    repo: &Repository,
+    branch_name: &SmallStr,
+    pull_requests: &[PrSummary],
+) -> Option<usize> {
+    let refname = format!("refs/heads/{branch_name}");
+    let tip = repo.refname_to_id(&refname).ok()?;
+
+    let upstream = repo
+        .branch_upstream_name(&refname)
+        .ok()
+        .and_then(|upstream| repo.refname_to_id(upstream.as_str()?).ok());
+    // heads which were never fetched can't be compared
+    let heads = pull_requests
+        .iter()
+        .filter_map(|pr| Oid::from_str(pr.head_sha.as_deref()?).ok())
+        .filter(|head| repo.find_commit(*head).is_ok());
+    let pushed = upstream.into_iter().chain(heads).collect::<Vec<_>>();
+    if pushed.is_empty() {
+        return None;
+    }
+
+    let mut walk = repo.revwalk().ok()?;
+    walk.push(tip).ok()?;
+    for oid in pushed {
+        walk.hide(oid).ok()?;
+    }
+    Some(walk.count())
+}
+
+/// Whether the tip of `branch_name` is the head of one of the closed
+/// `pull_requests`, or an ancestor of one.
+///
+/// `None` if no closed pull request's head is known. A head which was never
+/// fetched can't be an ancestor, so only matches exactly.
+pub(crate) fn on_pull_request_head(
+    repo: &Repository,
+    branch_name: &SmallStr,
+    pull_requests: &[PrSummary],
+) -> Option<bool> {
+    let tip = repo
+        .refname_to_id(&format!("refs/heads/{branch_name}"))
+        .ok()?;
+    let heads = pull_requests
+        .iter()
+        .filter(|pr| !pr.open)
+        .filter_map(|pr| Oid::from_str(pr.head_sha.as_deref()?).ok())
+        .collect::<Vec<_>>();
+    if heads.is_empty() {
+        return None;
+    }
+    Some(
+        heads
+            .into_iter()
+            .any(|head| head == tip || repo.graph_descendant_of(head, tip).unwrap_or_default()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Signature;
+
+    use super::*;
+
+    fn commit(repo: &Repository, parent: Option<Oid>) -> Oid {
+        let parent = parent.map(|parent| repo.find_commit(parent).unwrap());
+        let tree = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(None, &signature, &signature, "commit", &tree, &parents)
+            .unwrap()
+    }
+
+    fn pull_request(head: Oid) -> PrSummary {
+        PrSummary {
+            number: 1,
+            open: false,
+            title: None,
+            url: None,
+            author: None,
+            closed_at: None,
+            merged_at: None,
+            merged_by: None,
+            head_sha: Some(head.to_string()),
+        }
+    }
+
+    #[test]
+    fn counts_commits_past_pull_request_head() {
+        let dir = std::env::temp_dir().join(format!("git-clean-unpushed-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let pushed = commit(&repo, None);
+        let local = commit(&repo, Some(pushed));
+        let local = commit(&repo, Some(local));
+        repo.reference("refs/heads/feature", local, false, "test")
+            .unwrap();
+
+        let name = SmallStr::from("feature");
+        assert_eq!(count(&repo, &name, &[pull_request(pushed)]), Some(2));
+        assert_eq!(count(&repo, &name, &[pull_request(local)]), Some(0));
+        assert_eq!(count(&repo, &name, &[]), None);
+        assert_eq!(
+            on_pull_request_head(&repo, &name, &[pull_request(pushed)]),
+            Some(false)
+        );
+        assert_eq!(
+            on_pull_request_head(&repo, &name, &[pull_request(local)]),
+            Some(true)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
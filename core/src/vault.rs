@@ -0,0 +1,66 @@
+//! Fetching the token from HashiCorp Vault's KV version 2 secrets engine.
+
+use http::Request;
+
+use crate::config::{Error, VaultSource};
+
+const ADDRESS_VAR: &str = "VAULT_ADDR";
+const TOKEN_VAR: &str = "VAULT_TOKEN";
+
+/// Vault's own token, as its CLI finds it.
+fn vault_token() -> Result<String, Error> {
+    if let Ok(token) = std::env::var(TOKEN_VAR) {
+        return Ok(token);
+    }
+    let path = dirs::home_dir()
+        .map(|home| home.join(".vault-token"))
+        .ok_or_else(|| Error::TokenSource(format!("{TOKEN_VAR} is not set")))?;
+    std::fs::read_to_string(&path)
+        .map(|token| token.trim().to_owned())
+        .map_err(|_| Error::TokenSource(format!("{TOKEN_VAR} is not set")))
+}
+
+/// Read the token from the configured secret.
+pub(crate) async fn fetch(source: &VaultSource) -> Result<String, Error> {
+    let address = match &source.address {
+        Some(address) => address.clone(),
+        None => std::env::var(ADDRESS_VAR).map_err(|_| {
+            Error::TokenSource(format!(
+                "no Vault address configured, and {ADDRESS_VAR} is not set"
+            ))
+        })?,
+    };
+    let uri = format!(
+        "{}/v1/{}/data/{}",
+        address.trim_end_matches('/'),
+        source.mount.trim_matches('/'),
+        source.path.trim_start_matches('/'),
+    );
+    let request = Request::get(&uri)
+        .header("X-Vault-Token", vault_token()?)
+        .body(String::new())
+        .map_err(|err| Error::TokenSource(format!("invalid Vault request: {err}")))?;
+
+    let (status, body) = crate::http::send(request)
+        .await
+        .map_err(|err| Error::TokenSource(format!("failed to reach Vault: {err}")))?;
+    if !status.is_success() {
+        return Err(Error::TokenSource(format!(
+            "Vault refused to read {}: {status}",
+            source.path
+        )));
+    }
+
+    let body = serde_json::from_slice::<serde_json::Value>(&body)
+        .map_err(|_| Error::TokenSource("Vault sent an unreadable response".into()))?;
+    body.pointer("/data/data")
+        .and_then(|data| data.get(&source.field))
+        .and_then(|token| token.as_str())
+        .map(str::to_owned)
+        .ok_or_else(|| {
+            Error::TokenSource(format!(
+                "Vault secret {} has no field `{}`",
+                source.path, source.field
+            ))
+        })
+}
@@ -0,0 +1,171 @@
+//! Optional extra evidence that a closed pull request was genuinely finished.
+//!
+//! A PR closed by an overzealous stale-bot looks just like one closed by a
+//! human who was done with it. Requiring that the PR was approved, or that its
+//! checks passed, distinguishes the two at the cost of a few more API calls per
+//! candidate branch.
+
+use std::{fmt, str::FromStr};
+
+use octocrab::{models::pulls::ReviewState, Octocrab};
+use serde::Deserialize;
+
+use crate::{error::ContextErr, pulls::PullDetails, FatalError};
+
+/// What a closed pull request must have had for its branch to be deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vetting {
+    /// At least one approving review.
+    Approval,
+    /// Passing status checks on its head commit.
+    Checks,
+    /// Either of the above.
+    ApprovalOrChecks,
+}
+
+impl Vetting {
+    const VARIANTS: &'static [(&'static str, Vetting)] = &[
+        ("approval", Vetting::Approval),
+        ("checks", Vetting::Checks),
+        ("approval-or-checks", Vetting::ApprovalOrChecks),
+    ];
+}
+
+impl fmt::Display for Vetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = Self::VARIANTS
+            .iter()
+            .find(|(_, variant)| variant == self)
+            .expect("every variant is listed");
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Vetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::VARIANTS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, variant)| *variant)
+            .ok_or_else(|| {
+                let names = Self::VARIANTS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>();
+                format!("expected one of: {}", names.join(", "))
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CombinedStatus {
+    state: String,
+    total_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRun {
+    conclusion: Option<String>,
+}
+
+async fn is_approved(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+    number: u64,
+) -> Result<bool, FatalError> {
+    let reviews = octocrab
+        .pulls(owner, repo_name)
+        .list_reviews(number)
+        .per_page(100)
+        .send()
+        .await
+        .context("list reviews for pull request")?;
+    Ok(reviews
+        .items
+        .iter()
+        .any(|review| review.state == Some(ReviewState::Approved)))
+}
+
+/// Whether the PR's head commit has at least one status or check run, and all
+/// of them passed.
+async fn checks_passed(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+    (number, head_sha): &(u64, Option<String>),
+) -> Result<bool, FatalError> {
+    let head_sha = match head_sha {
+        Some(head_sha) => head_sha.clone(),
+        None => {
+            let pull: serde_json::Value = octocrab
+                .get(
+                    format!("/repos/{owner}/{repo_name}/pulls/{number}"),
+                    None::<&()>,
+                )
+                .await
+                .context("get pull request")?;
+            PullDetails::from_json(&pull).head_sha.ok_or_else(|| {
+                FatalError::UnexpectedResponse("pull request without a head commit".into())
+            })?
+        }
+    };
+
+    let status: CombinedStatus = octocrab
+        .get(
+            format!("/repos/{owner}/{repo_name}/commits/{head_sha}/status"),
+            None::<&()>,
+        )
+        .await
+        .context("get combined status for pull request head")?;
+    let runs: CheckRuns = octocrab
+        .get(
+            format!("/repos/{owner}/{repo_name}/commits/{head_sha}/check-runs"),
+            Some(&[("per_page", 100)]),
+        )
+        .await
+        .context("list check runs for pull request head")?;
+
+    let statuses_ok = status.total_count == 0 || status.state == "success";
+    let runs_ok = runs.check_runs.iter().all(|run| {
+        matches!(
+            run.conclusion.as_deref(),
+            Some("success" | "neutral" | "skipped")
+        )
+    });
+    let any_checks = status.total_count > 0 || !runs.check_runs.is_empty();
+
+    Ok(any_checks && statuses_ok && runs_ok)
+}
+
+/// Whether at least one of `prs`, by number and head commit if already known,
+/// satisfies `vetting`.
+pub(crate) async fn is_vetted(
+    vetting: Vetting,
+    octocrab: &Octocrab,
+    owner: &str,
+    repo_name: &str,
+    prs: &[(u64, Option<String>)],
+) -> Result<bool, FatalError> {
+    for pr in prs {
+        let vetted = match vetting {
+            Vetting::Approval => is_approved(octocrab, owner, repo_name, pr.0).await?,
+            Vetting::Checks => checks_passed(octocrab, owner, repo_name, pr).await?,
+            Vetting::ApprovalOrChecks => {
+                is_approved(octocrab, owner, repo_name, pr.0).await?
+                    || checks_passed(octocrab, owner, repo_name, pr).await?
+            }
+        };
+        if vetted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,89 @@
+//! Golden tests of the cleaning policy.
+//!
+//! Each file in `tests/scenarios` is a [`Snapshot`], as written by
+//! `--dump-snapshot`, with a description and the expected outcome for every
+//! branch in it. A policy change which alters any of them fails here, so the
+//! change to that scenario has to be made deliberately.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use git_clean_core::{Outcome, Snapshot};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Scenario {
+    #[allow(dead_code)]
+    description: String,
+    #[serde(flatten)]
+    snapshot: Snapshot,
+    /// Branch name to outcome, as written by [`describe`].
+    expected: BTreeMap<String, String>,
+}
+
+fn describe(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Delete { bot: true, .. } => "delete bot".to_owned(),
+        Outcome::Delete { confidence, .. } => format!("delete ({confidence} confidence)"),
+        Outcome::Retain(reason) => format!("retain: {reason}"),
+        Outcome::Failed(issue) => format!("failed: {issue}"),
+    }
+}
+
+#[test]
+fn scenarios_decide_as_expected() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let text = fs::read_to_string(path).unwrap();
+        let scenario: Scenario = match serde_json::from_str(&text) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                failures.push(format!("{name}: {err}"));
+                continue;
+            }
+        };
+
+        let actual = scenario
+            .snapshot
+            .decide()
+            .into_iter()
+            .map(|decision| {
+                (
+                    decision.branch_name.to_string(),
+                    describe(&decision.outcome),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (branch_name, outcome) in &actual {
+            match scenario.expected.get(branch_name) {
+                Some(expected) if expected == outcome => {}
+                Some(expected) => failures.push(format!(
+                    "{name}: {branch_name}: expected {expected:?}, decided {outcome:?}"
+                )),
+                None => failures.push(format!(
+                    "{name}: {branch_name}: no expectation, decided {outcome:?}"
+                )),
+            }
+        }
+        for branch_name in scenario.expected.keys() {
+            if !actual.contains_key(branch_name) {
+                failures.push(format!(
+                    "{name}: {branch_name}: expected, but not in branches"
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,43 @@
+//! Remote urls are recognized without panicking, and whatever is recognized
+//! round-trips.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use git_clean_core::fuzzing::{
+    bitbucket_repository, gitlab_project, parse_git_url, rewrite_git_url, split_remote_url,
+};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    url: String,
+    /// A GitHub Enterprise Server host, or github.com's.
+    host: Option<String>,
+}
+
+fuzz_target!(|input: Input| {
+    let host = input.host.as_deref().unwrap_or("github.com");
+    if let Some((owner, repo_name)) = parse_git_url(&input.url, host) {
+        let name_like = |part: &str| !part.is_empty() && !part.contains(['/', ':', '.', '@']);
+        assert!(name_like(&owner), "owner {owner:?}");
+        assert!(name_like(&repo_name), "repository {repo_name:?}");
+        assert_eq!(
+            rewrite_git_url(&input.url, host, &owner, &repo_name).as_deref(),
+            Some(input.url.as_str()),
+            "rewriting to the same repository changed the url"
+        );
+    }
+
+    if let Some((remote_host, path)) = split_remote_url(&input.url) {
+        assert!(!remote_host.contains('/'), "host {remote_host:?}");
+        assert!(!path.ends_with(".git"), "path {path:?}");
+    }
+    let hosts = input.host.into_iter().collect::<Vec<_>>();
+    if let Some((_, path)) = gitlab_project(&input.url, &hosts) {
+        assert!(path.contains('/'), "project path {path:?}");
+    }
+    if let Some((workspace, slug)) = bitbucket_repository(&input.url) {
+        assert!(!workspace.is_empty() && !workspace.contains('/'));
+        assert!(!slug.is_empty() && !slug.contains('/'));
+    }
+});
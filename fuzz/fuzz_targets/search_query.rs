@@ -0,0 +1,26 @@
+//! Whatever a branch is called, the search query names exactly that branch,
+//! quoted so that nothing in its name is taken as search syntax.
+#![no_main]
+
+use git_clean_core::fuzzing::pr_search_query;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|branch_name: &str| {
+    let query = pr_search_query("owner", "repo", branch_name);
+    let quoted = query
+        .strip_prefix("is:pr repo:owner/repo head:\"")
+        .and_then(|rest| rest.strip_suffix('"'))
+        .unwrap_or_else(|| panic!("query {query:?}"));
+
+    // unquote, checking that no quote ends the string early
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next().expect("dangling escape")),
+            '"' => panic!("unescaped quote in {query:?}"),
+            c => unquoted.push(c),
+        }
+    }
+    assert_eq!(unquoted, branch_name);
+});